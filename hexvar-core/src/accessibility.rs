@@ -0,0 +1,70 @@
+use crate::color::hex_to_rgba;
+use serde::{Deserialize, Serialize};
+
+/// WCAG 2.1 relative luminance of an sRGB color with channels in `0.0..=1.0`
+///
+/// ```
+/// use hexvar_core::accessibility::relative_luminance;
+///
+/// assert_eq!(relative_luminance(1.0, 1.0, 1.0), 1.0);
+/// assert_eq!(relative_luminance(0.0, 0.0, 0.0), 0.0);
+/// ```
+pub fn relative_luminance(r: f32, g: f32, b: f32) -> f32 {
+    let linear = |c: f32| {
+        if c <= 0.03928 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+}
+
+/// WCAG contrast ratio between two relative luminances, from 1 to 21.
+/// The order of the arguments doesn't matter.
+///
+/// ```
+/// use hexvar_core::accessibility::contrast_ratio;
+///
+/// assert!((contrast_ratio(1.0, 0.0) - 21.0).abs() < 1e-4);
+/// assert_eq!(contrast_ratio(0.5, 0.5), 1.0);
+/// ```
+pub fn contrast_ratio(l1: f32, l2: f32) -> f32 {
+    let (lighter, darker) = if l1 > l2 { (l1, l2) } else { (l2, l1) };
+    (lighter + 0.05) / (darker + 0.05)
+}
+
+/// Contrast of a color against white and black text or backgrounds. The
+/// pass/fail levels use whichever of the two contrasts better.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct WcagContrast {
+    pub contrast_white: f32,
+    pub contrast_black: f32,
+    /// At least 4.5:1
+    pub aa_normal: bool,
+    /// At least 3:1
+    pub aa_large: bool,
+    /// At least 7:1
+    pub aaa_normal: bool,
+    /// At least 4.5:1
+    pub aaa_large: bool,
+}
+
+impl WcagContrast {
+    /// Ratings for a hex code, ignoring any alpha channel
+    pub fn for_hex(hex: &str) -> Option<WcagContrast> {
+        let (r, g, b, _) = hex_to_rgba(hex)?;
+        let l = relative_luminance(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
+        let (white, black) = (contrast_ratio(l, 1.0), contrast_ratio(l, 0.0));
+        let best = white.max(black);
+        let round = |x: f32| (x * 100.0).round() / 100.0;
+        Some(WcagContrast {
+            contrast_white: round(white),
+            contrast_black: round(black),
+            aa_normal: best >= 4.5,
+            aa_large: best >= 3.0,
+            aaa_normal: best >= 7.0,
+            aaa_large: best >= 4.5,
+        })
+    }
+}
@@ -0,0 +1,29 @@
+use crate::paths::read_source;
+use rayon::prelude::*;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+/// Custom properties from a vars file, split by whether any source references them
+#[derive(Serialize)]
+pub struct AuditReport {
+    pub unused: Vec<String>,
+    pub used: Vec<String>,
+}
+
+/// Check which custom properties defined in `css` appear as `var(--name)` in `paths`
+pub fn audit_vars(css: &str, paths: &[PathBuf]) -> AuditReport {
+    let def_re = Regex::new(r"(--[\w-]+)\s*:").unwrap();
+    let use_re = Regex::new(r"var\(\s*(--[\w-]+)").unwrap();
+    let defined: BTreeSet<String> = def_re.captures_iter(css).map(|c| c[1].to_string()).collect();
+    let referenced: BTreeSet<String> = paths
+        .par_iter()
+        .filter_map(|path| read_source(path).ok())
+        .flat_map_iter(|content| {
+            use_re.captures_iter(&content).map(|c| c[1].to_string()).collect::<Vec<_>>()
+        })
+        .collect();
+    let (used, unused) = defined.into_iter().partition(|var| referenced.contains(var));
+    AuditReport { unused, used }
+}
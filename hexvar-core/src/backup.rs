@@ -0,0 +1,63 @@
+use crate::error::{HexvarError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Default suffix appended to backed-up files by `replace --backup`
+pub const DEFAULT_BACKUP_SUFFIX: &str = ".bak";
+
+/// Manifest of backups written by `replace --backup`, read by `restore`
+pub const BACKUP_MANIFEST_FILE: &str = ".hexvar-backups.json";
+
+/// Which files have been backed up, and where each backup lives
+#[derive(Serialize, Deserialize, Default)]
+pub struct BackupManifest {
+    /// Original path -> backup path
+    pub files: BTreeMap<String, String>,
+}
+
+impl BackupManifest {
+    /// Load the manifest at `path`, or `None` if it doesn't exist or is invalid
+    pub fn load(path: &Path) -> Option<BackupManifest> {
+        let text = fs::read_to_string(path).ok()?;
+        serde_json::from_str(&text).ok()
+    }
+
+    /// Write the manifest, or remove the file once no backups are left
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if self.files.is_empty() {
+            return match fs::remove_file(path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(HexvarError::io(path, e)),
+                _ => Ok(()),
+            };
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| HexvarError::json(path, e))?;
+        fs::write(path, json).map_err(|e| HexvarError::io(path, e))
+    }
+
+    /// Copy `path` to `<path><suffix>` and record it. An existing backup is
+    /// kept, so repeated runs never overwrite the oldest original.
+    pub fn backup(&mut self, path: &Path, suffix: &str) -> Result<()> {
+        let original = path.display().to_string();
+        if self.files.get(&original).is_some_and(|b| Path::new(b).exists()) {
+            return Ok(());
+        }
+        let backup = format!("{}{}", original, suffix);
+        fs::copy(path, &backup).map_err(|e| HexvarError::io(&backup, e))?;
+        self.files.insert(original, backup);
+        Ok(())
+    }
+
+    /// Copy the backup of `original` back over it and delete the backup
+    pub fn restore(&mut self, original: &str) -> Result<()> {
+        let Some(backup) = self.files.get(original) else {
+            return Err(HexvarError::MissingBackup { path: PathBuf::from(original) });
+        };
+        fs::copy(backup, original).map_err(|e| HexvarError::io(backup, e))?;
+        fs::remove_file(backup).map_err(|e| HexvarError::io(backup, e))?;
+        self.files.remove(original);
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+use crate::error::{HexvarError, Result};
+use crate::scanner::{ScanOptions, ScanTally};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+/// Bumped whenever the cache layout or scan semantics change, so caches written
+/// by an older hexvar are discarded instead of misread
+pub const CACHE_VERSION: u32 = 18;
+
+/// Default cache file used by `hexvar scan`
+pub const DEFAULT_CACHE_FILE: &str = ".hexvar-cache.json";
+
+/// Per-file scan results from a previous run, reused when a file is unchanged
+#[derive(Serialize, Deserialize)]
+pub struct ScanCache {
+    pub version: u32,
+    /// Options the cached results were produced with; a mismatch invalidates the cache
+    pub options: ScanOptions,
+    pub files: Vec<CacheEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CacheEntry {
+    pub path: String,
+    /// Modification time in nanoseconds since the Unix epoch
+    pub mtime: u64,
+    pub size: u64,
+    #[serde(flatten)]
+    pub tally: ScanTally,
+}
+
+impl ScanCache {
+    /// An empty cache for scans run with `options`
+    pub fn new(options: ScanOptions) -> ScanCache {
+        ScanCache { version: CACHE_VERSION, options, files: Vec::new() }
+    }
+
+    /// Load the cache at `path`, falling back to an empty cache if it is missing,
+    /// unreadable, from another hexvar version, or built with different options
+    pub fn load(path: &Path, options: ScanOptions) -> ScanCache {
+        let cache: Option<ScanCache> = fs::read_to_string(path)
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok());
+        match cache {
+            Some(cache) if cache.version == CACHE_VERSION && cache.options == options => cache,
+            _ => ScanCache::new(options),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string(self).map_err(|e| HexvarError::json(path, e))?;
+        fs::write(path, json).map_err(|e| HexvarError::io(path, e))
+    }
+
+    /// Cached entries keyed by path
+    pub fn by_path(&self) -> HashMap<&str, &CacheEntry> {
+        self.files.iter().map(|e| (e.path.as_str(), e)).collect()
+    }
+}
+
+/// Modification time (ns since epoch) and size of a file, used to detect changes
+pub fn file_stamp(path: &Path) -> Option<(u64, u64)> {
+    let meta = fs::metadata(path).ok()?;
+    let mtime = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_nanos() as u64;
+    Some((mtime, meta.len()))
+}
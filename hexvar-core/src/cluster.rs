@@ -0,0 +1,432 @@
+use crate::color::{hex_to_rgba, hue_family, rgb_to_lab};
+use crate::naming::NameResolver;
+use palette::color_difference::{Ciede2000, DeltaE};
+use palette::{FromColor, Lab, Oklab};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use tracing::debug;
+
+/// Algorithm used to merge similar colors
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum ClusterAlgo {
+    /// Join the first cluster within the Delta E threshold
+    #[default]
+    Greedy,
+    /// k-means in Lab space with a fixed number of clusters
+    Kmeans,
+}
+
+/// Formula `--color-space` measures the distance between two colors with when
+/// clustering. Thresholds are Delta E values whichever is used, but the more
+/// accurate formulas cost more: CIEDE2000 is about ten times the work of CIE76.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum DistanceMetric {
+    /// Euclidean distance in CIE Lab, the same as CIE76
+    #[default]
+    Lab,
+    /// Euclidean distance in Oklab, scaled by 100 to match Lab's range
+    Oklab,
+    /// CIE76 Delta E; fast, but overestimates distances between blues and violets
+    Cie76,
+    /// CIE94 Delta E (graphic arts weights), which discounts chroma differences
+    /// between saturated colors
+    Cie94,
+    /// CIEDE2000 Delta E, the most perceptually uniform, especially for blues
+    Ciede2000,
+}
+
+impl DistanceMetric {
+    /// The distance function for this metric
+    ///
+    /// ```
+    /// use hexvar_core::cluster::DistanceMetric;
+    /// use hexvar_core::color::rgb_to_lab;
+    ///
+    /// let (a, b) = (rgb_to_lab(0, 0, 255), rgb_to_lab(30, 30, 255));
+    /// let cie76 = DistanceMetric::Cie76.distance()(a, b);
+    /// assert_eq!(DistanceMetric::Lab.distance()(a, b), cie76);
+    /// assert!(DistanceMetric::Cie94.distance()(a, b) < cie76);
+    /// assert!(DistanceMetric::Ciede2000.distance()(a, b) < cie76);
+    /// assert_eq!(DistanceMetric::Ciede2000.distance()(a, a), 0.0);
+    /// ```
+    pub fn distance(self) -> fn(Lab, Lab) -> f32 {
+        match self {
+            DistanceMetric::Lab | DistanceMetric::Cie76 => |a, b| a.delta_e(b),
+            DistanceMetric::Oklab => oklab_distance,
+            DistanceMetric::Cie94 => cie94,
+            DistanceMetric::Ciede2000 => |a, b| a.difference(b),
+        }
+    }
+}
+
+impl fmt::Display for DistanceMetric {
+    /// The `--color-space` value, e.g. `ciede2000`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DistanceMetric::Lab => "lab",
+            DistanceMetric::Oklab => "oklab",
+            DistanceMetric::Cie76 => "cie76",
+            DistanceMetric::Cie94 => "cie94",
+            DistanceMetric::Ciede2000 => "ciede2000",
+        })
+    }
+}
+
+/// Euclidean distance in Oklab, times 100
+fn oklab_distance(a: Lab, b: Lab) -> f32 {
+    let (a, b) = (Oklab::from_color(a), Oklab::from_color(b));
+    ((a.l - b.l).powi(2) + (a.a - b.a).powi(2) + (a.b - b.b).powi(2)).sqrt() * 100.0
+}
+
+/// CIE94 Delta E with the graphic arts weights (kL = 1, K1 = 0.045, K2 = 0.015),
+/// taking `a` as the reference color
+fn cie94(a: Lab, b: Lab) -> f32 {
+    let (c1, c2) = (a.a.hypot(a.b), b.a.hypot(b.b));
+    let dl = a.l - b.l;
+    let dc = c1 - c2;
+    // ΔH² is what's left of the a*/b* difference once the chroma difference is taken out
+    let dh2 = ((a.a - b.a).powi(2) + (a.b - b.b).powi(2) - dc.powi(2)).max(0.0);
+    let sc = 1.0 + 0.045 * c1;
+    let sh = 1.0 + 0.015 * c1;
+    (dl.powi(2) + (dc / sc).powi(2) + dh2 / sh.powi(2)).sqrt()
+}
+
+/// A canonical color and every hex code merged into it
+#[derive(Clone, Debug)]
+pub struct Cluster {
+    /// Canonical hex code, spelled as it was first found
+    pub hex: String,
+    pub lab: Lab,
+    pub alpha: u8,
+    /// CSS custom property name, e.g. `--color-tomato`
+    pub name: String,
+    /// Every hex code merged into this cluster, including the canonical one
+    pub members: Vec<String>,
+}
+
+/// Greedily group colors whose Delta E to a cluster's canonical color is below
+/// `delta_e`. Only colors with the same alpha merge, so alpha is never lost.
+/// A threshold of `0.0` disables clustering (one cluster per hex). Colors are
+/// visited most-used first, then by hex, so each cluster's canonical color is
+/// its most-used member and the result is the same on every run.
+///
+/// ```
+/// use std::collections::HashMap;
+///
+/// let counts = HashMap::from([("#ff0000".to_string(), 3), ("#fe0000".to_string(), 1)]);
+/// let clusters = hexvar_core::cluster_colors(&counts, 10.0);
+/// assert_eq!(clusters.len(), 1);
+/// assert_eq!(clusters[0].name, "--color-red");
+/// assert_eq!(clusters[0].members.len(), 2);
+/// ```
+pub fn cluster_colors(counts: &HashMap<String, u32>, delta_e: f32) -> Vec<Cluster> {
+    cluster_colors_with(counts, delta_e, DistanceMetric::default())
+}
+
+/// Like [`cluster_colors`], measuring distances with `metric`
+///
+/// ```
+/// use std::collections::HashMap;
+/// use hexvar_core::cluster::{cluster_colors_with, DistanceMetric};
+///
+/// // Two blues CIE76 puts further apart than CIEDE2000 does
+/// let counts = HashMap::from([("#0000ff".to_string(), 2), ("#1e1eff".to_string(), 1)]);
+/// assert_eq!(cluster_colors_with(&counts, 5.0, DistanceMetric::Cie76).len(), 2);
+/// assert_eq!(cluster_colors_with(&counts, 5.0, DistanceMetric::Ciede2000).len(), 1);
+///
+/// // Whatever order the map iterates in, the most-used red is canonical
+/// let counts = HashMap::from([
+///     ("#fe0101".to_string(), 1),
+///     ("#ff0000".to_string(), 2),
+///     ("#ff0101".to_string(), 2),
+///     ("#000000".to_string(), 1),
+/// ]);
+/// let canonical = |counts: &HashMap<String, u32>| -> Vec<String> {
+///     cluster_colors_with(counts, 10.0, DistanceMetric::Cie76).into_iter().map(|c| c.hex).collect()
+/// };
+/// assert_eq!(canonical(&counts), ["#ff0000", "#000000"]);
+/// assert_eq!(canonical(&counts.clone().into_iter().collect()), canonical(&counts));
+/// ```
+pub fn cluster_colors_with(counts: &HashMap<String, u32>, delta_e: f32, metric: DistanceMetric) -> Vec<Cluster> {
+    let distance = metric.distance();
+    let mut clusters: Vec<Cluster> = Vec::new();
+    let mut hexes: Vec<&String> = counts.keys().collect();
+    hexes.sort_by(|a, b| counts[*b].cmp(&counts[*a]).then_with(|| a.cmp(b)));
+    for hex in hexes {
+        let Some((r, g, b, alpha)) = hex_to_rgba(hex) else { continue };
+        let lab = rgb_to_lab(r, g, b);
+        match clusters
+            .iter_mut()
+            .find(|c| c.alpha == alpha && distance(c.lab, lab) < delta_e)
+        {
+            Some(cluster) => {
+                debug!("Merging {} into {} (Delta E {:.2} < {})", hex, cluster.hex, distance(cluster.lab, lab), delta_e);
+                cluster.members.push(hex.clone());
+            }
+            None => clusters.push(Cluster {
+                hex: hex.clone(),
+                lab,
+                alpha,
+                name: var_name_for(hex),
+                members: vec![hex.clone()],
+            }),
+        }
+    }
+    clusters
+}
+
+/// Iterations after which k-means stops even if assignments are still changing
+const KMEANS_MAX_ITERATIONS: usize = 100;
+
+/// Squared Euclidean distance in Lab space (the square of CIE76 Delta E)
+fn lab_dist2(a: Lab, b: Lab) -> f32 {
+    (a.l - b.l).powi(2) + (a.a - b.a).powi(2) + (a.b - b.b).powi(2)
+}
+
+/// Mean of a set of Lab colors
+fn lab_mean<'a>(labs: impl Iterator<Item = &'a Lab>) -> Option<Lab> {
+    let (mut l, mut a, mut b, mut n) = (0.0, 0.0, 0.0, 0.0);
+    for lab in labs {
+        l += lab.l;
+        a += lab.a;
+        b += lab.b;
+        n += 1.0;
+    }
+    (n > 0.0).then(|| Lab::new(l / n, a / n, b / n))
+}
+
+/// Small deterministic xorshift generator so k-means++ gives the same result on every run
+struct XorShift(u64);
+
+impl XorShift {
+    /// Uniform float in `[0, 1)`
+    fn next_f32(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+/// Group colors into `k` clusters with k-means in Lab space, seeded with k-means++.
+///
+/// As with [`cluster_colors`], colors only merge with others of the same alpha:
+/// every distinct alpha gets at least one centroid, so fewer than `k` alpha
+/// values may produce more than `k` clusters. Seeding is deterministic, and
+/// each cluster's canonical hex is the member closest to its centroid.
+///
+/// ```
+/// use std::collections::HashMap;
+/// use hexvar_core::cluster::kmeans_colors;
+///
+/// let counts = HashMap::from([
+///     ("#ff0000".to_string(), 3),
+///     ("#fe0101".to_string(), 1),
+///     ("#0000ff".to_string(), 2),
+///     ("#0101fe".to_string(), 1),
+/// ]);
+/// let clusters = kmeans_colors(&counts, 2);
+/// assert_eq!(clusters.len(), 2);
+/// assert!(clusters.iter().all(|c| c.members.len() == 2));
+/// ```
+pub fn kmeans_colors(counts: &HashMap<String, u32>, k: usize) -> Vec<Cluster> {
+    let mut points: Vec<(&String, Lab, u8)> = counts
+        .keys()
+        .filter_map(|hex| {
+            let (r, g, b, alpha) = hex_to_rgba(hex)?;
+            Some((hex, rgb_to_lab(r, g, b), alpha))
+        })
+        .collect();
+    // Sort so the result doesn't depend on HashMap iteration order
+    points.sort_by(|a, b| a.0.cmp(b.0));
+    if points.is_empty() {
+        return Vec::new();
+    }
+
+    // One seed per alpha value, then k-means++: pick further seeds with
+    // probability proportional to the squared distance to the nearest seed
+    let mut centroids: Vec<(Lab, u8)> = Vec::new();
+    for &(_, lab, alpha) in &points {
+        if !centroids.iter().any(|c| c.1 == alpha) {
+            centroids.push((lab, alpha));
+        }
+    }
+    let mut rng = XorShift(0x9E37_79B9_7F4A_7C15);
+    while centroids.len() < k.min(points.len()) {
+        let dists: Vec<f32> = points
+            .iter()
+            .map(|&(_, lab, alpha)| {
+                centroids
+                    .iter()
+                    .filter(|c| c.1 == alpha)
+                    .map(|c| lab_dist2(lab, c.0))
+                    .fold(f32::INFINITY, f32::min)
+            })
+            .collect();
+        let total: f32 = dists.iter().sum();
+        if total <= 0.0 {
+            // Every remaining color coincides with a seed
+            break;
+        }
+        let mut target = rng.next_f32() * total;
+        let mut pick = dists.iter().rposition(|d| *d > 0.0).unwrap();
+        for (i, d) in dists.iter().enumerate() {
+            if target < *d {
+                pick = i;
+                break;
+            }
+            target -= d;
+        }
+        centroids.push((points[pick].1, points[pick].2));
+    }
+
+    // Lloyd iterations: assign to the nearest same-alpha centroid, then move centroids to the mean
+    let nearest = |centroids: &[(Lab, u8)], lab: Lab, alpha: u8| {
+        (0..centroids.len())
+            .filter(|&i| centroids[i].1 == alpha)
+            .min_by(|&i, &j| lab_dist2(lab, centroids[i].0).total_cmp(&lab_dist2(lab, centroids[j].0)))
+            .unwrap()
+    };
+    let mut assignment: Vec<usize> = points.iter().map(|p| nearest(&centroids, p.1, p.2)).collect();
+    for _ in 0..KMEANS_MAX_ITERATIONS {
+        for (i, centroid) in centroids.iter_mut().enumerate() {
+            let members = points.iter().zip(&assignment).filter(|(_, a)| **a == i).map(|(p, _)| &p.1);
+            if let Some(mean) = lab_mean(members) {
+                centroid.0 = mean;
+            }
+        }
+        let next: Vec<usize> = points.iter().map(|p| nearest(&centroids, p.1, p.2)).collect();
+        if next == assignment {
+            break;
+        }
+        assignment = next;
+    }
+
+    centroids
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &(centroid, alpha))| {
+            let members: Vec<&(&String, Lab, u8)> =
+                points.iter().zip(&assignment).filter(|(_, a)| **a == i).map(|(p, _)| p).collect();
+            let canonical = members
+                .iter()
+                .min_by(|a, b| lab_dist2(a.1, centroid).total_cmp(&lab_dist2(b.1, centroid)))?;
+            Some(Cluster {
+                hex: canonical.0.clone(),
+                lab: canonical.1,
+                alpha,
+                name: var_name_for(canonical.0),
+                members: members.iter().map(|p| p.0.clone()).collect(),
+            })
+        })
+        .collect()
+}
+
+/// Within-cluster sum of squares: the total squared Delta E of every member
+/// from the mean of its cluster. Lower means tighter clusters.
+pub fn wcss(clusters: &[Cluster]) -> f32 {
+    clusters
+        .iter()
+        .map(|c| {
+            let labs: Vec<Lab> = c
+                .members
+                .iter()
+                .filter_map(|hex| hex_to_rgba(hex).map(|(r, g, b, _)| rgb_to_lab(r, g, b)))
+                .collect();
+            match lab_mean(labs.iter()) {
+                Some(mean) => labs.iter().map(|lab| lab_dist2(*lab, mean)).sum(),
+                None => 0.0,
+            }
+        })
+        .sum()
+}
+
+/// Total occurrences of every color merged into `cluster`
+pub fn cluster_usage(cluster: &Cluster, counts: &HashMap<String, u32>) -> u32 {
+    cluster.members.iter().filter_map(|m| counts.get(m)).sum()
+}
+
+/// Sort clusters from most-used to least-used, breaking ties by hex
+fn sort_by_usage(clusters: &mut [Cluster], counts: &HashMap<String, u32>) {
+    clusters.sort_by_cached_key(|c| (std::cmp::Reverse(cluster_usage(c, counts)), c.hex.clone()));
+}
+
+/// The `n` most-used clusters, most-used first
+///
+/// ```
+/// use std::collections::HashMap;
+/// use hexvar_core::cluster::top_clusters;
+///
+/// let counts = HashMap::from([("#ff0000".to_string(), 5), ("#0000ff".to_string(), 2), ("#00ff00".to_string(), 9)]);
+/// let top = top_clusters(hexvar_core::cluster_colors(&counts, 10.0), &counts, 2);
+/// let hexes: Vec<_> = top.iter().map(|c| c.hex.as_str()).collect();
+/// assert_eq!(hexes, ["#00ff00", "#ff0000"]);
+/// ```
+pub fn top_clusters(mut clusters: Vec<Cluster>, counts: &HashMap<String, u32>, n: usize) -> Vec<Cluster> {
+    sort_by_usage(&mut clusters, counts);
+    clusters.truncate(n);
+    clusters
+}
+
+/// The `n` most-used clusters in each hue family (see [`hue_family`]), most-used first
+///
+/// ```
+/// use std::collections::HashMap;
+/// use hexvar_core::cluster::top_clusters_per_family;
+///
+/// let counts = HashMap::from([
+///     ("#ff0000".to_string(), 5),
+///     ("#8b0000".to_string(), 1),
+///     ("#0000ff".to_string(), 2),
+/// ]);
+/// let top = top_clusters_per_family(hexvar_core::cluster_colors(&counts, 10.0), &counts, 1);
+/// let hexes: Vec<_> = top.iter().map(|c| c.hex.as_str()).collect();
+/// assert_eq!(hexes, ["#ff0000", "#0000ff"]);
+/// ```
+pub fn top_clusters_per_family(mut clusters: Vec<Cluster>, counts: &HashMap<String, u32>, n: usize) -> Vec<Cluster> {
+    sort_by_usage(&mut clusters, counts);
+    let mut per_family: HashMap<&str, usize> = HashMap::new();
+    clusters.retain(|c| {
+        let taken = per_family.entry(hue_family(&c.lab)).or_insert(0);
+        *taken += 1;
+        *taken <= n
+    });
+    clusters
+}
+
+/// Pick a variable name for a hex code: an exact CSS color name match, else
+/// the closest CSS color by Euclidean RGB distance, else the hex digits
+pub fn var_name_for(hex: &str) -> String {
+    format!("--color-{}", css_name_for(hex))
+}
+
+/// The bare name used by [`var_name_for`], e.g. `tomato` or `dark-red`
+pub fn css_name_for(hex: &str) -> String {
+    NameResolver::default().name_for(hex)
+}
+
+/// Mapping of canonical hex -> every merged spelling, as written to `colours_map.json`.
+/// Original `rgb()` spellings are included so `replace` can rewrite them too.
+pub fn canonical_map(
+    clusters: &[Cluster],
+    sources: &HashMap<String, BTreeSet<String>>,
+) -> BTreeMap<String, Vec<String>> {
+    clusters
+        .iter()
+        .map(|c| {
+            let mut merged = Vec::new();
+            for hex in &c.members {
+                merged.push(hex.clone());
+                if let Some(srcs) = sources.get(hex) {
+                    merged.extend(srcs.iter().cloned());
+                }
+            }
+            (c.hex.clone(), merged)
+        })
+        .collect()
+}
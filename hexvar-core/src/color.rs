@@ -0,0 +1,186 @@
+use crate::css_color_names::CSS_COLOR_NAMES;
+use palette::chromatic_adaptation::AdaptIntoUnclamped;
+use palette::white_point::{D50, D65};
+use palette::{FromColor, Lab, Lch, Oklch, Srgb, Xyz};
+
+/// Matches 8, 6, 4, or 3 digit hex codes (longest first to avoid partial matches)
+pub const HEX_PATTERN: &str = r"#(?:[0-9a-fA-F]{8}|[0-9a-fA-F]{6}|[0-9a-fA-F]{4}|[0-9a-fA-F]{3})";
+
+/// Matches `rgb()`/`rgba()` literals, capturing the three channels
+pub const RGB_PATTERN: &str = r"rgba?\(\s*(\d{1,3})\s*,\s*(\d{1,3})\s*,\s*(\d{1,3})(?:\s*,\s*[\d.]+)?\s*\)";
+
+/// Matches `hsl()`/`hsla()` literals, capturing hue (degrees), saturation and lightness (percent)
+pub const HSL_PATTERN: &str =
+    r"hsla?\(\s*(\d+(?:\.\d+)?)\s*,\s*(\d+(?:\.\d+)?)%\s*,\s*(\d+(?:\.\d+)?)%(?:\s*,\s*[\d.]+)?\s*\)";
+
+/// Matches `oklch()` literals, capturing lightness, chroma (each a number or a
+/// percentage, with its `%` captured separately), and hue in degrees
+pub const OKLCH_PATTERN: &str =
+    r"\boklch\(\s*(\d*\.?\d+)(%?)\s+(\d*\.?\d+)(%?)\s+(\d*\.?\d+)(?:deg)?\s*(?:/\s*\d*\.?\d+%?\s*)?\)";
+
+/// Matches `lch()` literals, with the same captures as [`OKLCH_PATTERN`]
+pub const LCH_PATTERN: &str =
+    r"\blch\(\s*(\d*\.?\d+)(%?)\s+(\d*\.?\d+)(%?)\s+(\d*\.?\d+)(?:deg)?\s*(?:/\s*\d*\.?\d+%?\s*)?\)";
+
+/// Matches a CSS declaration, capturing its value (up to `;`, `{` or `}`)
+pub const DECLARATION_PATTERN: &str = r"[\w-]+\s*:([^;{}]*)";
+
+/// Hex code of a CSS color keyword such as `cornflowerblue` (case-insensitive)
+pub fn named_color_to_hex(name: &str) -> Option<&'static str> {
+    CSS_COLOR_NAMES
+        .iter()
+        .find(|(css_name, _)| css_name.eq_ignore_ascii_case(name))
+        .map(|(_, hex)| *hex)
+}
+
+/// Parse a 3, 4, 6, or 8 digit hex code into RGBA channels (alpha defaults to 255)
+pub fn hex_to_rgba(hex: &str) -> Option<(u8, u8, u8, u8)> {
+    let hex = hex.trim_start_matches('#');
+    // Short forms (width 1) double each nibble, e.g. `a` -> `aa`
+    let channel = |i: usize, width: usize| -> Option<u8> {
+        let digits = hex.get(i * width..(i + 1) * width)?;
+        u8::from_str_radix(&digits.repeat(3 - width), 16).ok()
+    };
+    match hex.len() {
+        3 => Some((channel(0, 1)?, channel(1, 1)?, channel(2, 1)?, 255)),
+        4 => Some((channel(0, 1)?, channel(1, 1)?, channel(2, 1)?, channel(3, 1)?)),
+        6 => Some((channel(0, 2)?, channel(1, 2)?, channel(2, 2)?, 255)),
+        8 => Some((channel(0, 2)?, channel(1, 2)?, channel(2, 2)?, channel(3, 2)?)),
+        _ => None,
+    }
+}
+
+/// Convert captured `rgb()`/`rgba()` channels to `#rrggbb`, dropping any alpha
+pub fn rgb_to_hex(r: &str, g: &str, b: &str) -> Option<String> {
+    let r: u8 = r.parse().ok()?;
+    let g: u8 = g.parse().ok()?;
+    let b: u8 = b.parse().ok()?;
+    Some(format!("#{:02x}{:02x}{:02x}", r, g, b))
+}
+
+/// Convert an HSL color to `#rrggbb`. `h` is in degrees, `s` and `l` are
+/// fractions in `0.0..=1.0` (so `hsl(210, 100%, 56%)` is `hsl_to_hex(210.0, 1.0, 0.56)`).
+///
+/// ```
+/// use hexvar_core::color::hsl_to_hex;
+///
+/// assert_eq!(hsl_to_hex(0.0, 1.0, 0.5), "#ff0000");
+/// assert_eq!(hsl_to_hex(120.0, 1.0, 0.25), "#008000");
+/// assert_eq!(hsl_to_hex(240.0, 1.0, 0.5), "#0000ff");
+/// assert_eq!(hsl_to_hex(210.0, 1.0, 0.56), "#1f8fff");
+/// assert_eq!(hsl_to_hex(9.0, 1.0, 0.64), "#ff6347");
+/// assert_eq!(hsl_to_hex(0.0, 0.0, 1.0), "#ffffff");
+/// assert_eq!(hsl_to_hex(360.0, 0.0, 0.5), "#808080");
+/// ```
+pub fn hsl_to_hex(h: f32, s: f32, l: f32) -> String {
+    let (s, l) = (s.clamp(0.0, 1.0), l.clamp(0.0, 1.0));
+    let h = h.rem_euclid(360.0) / 60.0;
+    let chroma = (1.0 - (2.0 * l - 1.0).abs()) * s;
+    let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = l - chroma / 2.0;
+    let channel = |c: f32| ((c + m) * 255.0).round() as u8;
+    format!("#{:02x}{:02x}{:02x}", channel(r), channel(g), channel(b))
+}
+
+/// `#rrggbb` of an sRGB color, clamping channels outside the gamut
+fn srgb_to_hex(rgb: Srgb) -> String {
+    let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!("#{:02x}{:02x}{:02x}", channel(rgb.red), channel(rgb.green), channel(rgb.blue))
+}
+
+/// Convert an OKLCH color to `#rrggbb`, clamping to the sRGB gamut. `l` is a
+/// fraction in `0.0..=1.0` (so `oklch(62.8% 0.2577 29.23)` is
+/// `oklch_to_hex(0.628, 0.2577, 29.23)`) and `h` is in degrees.
+///
+/// ```
+/// use hexvar_core::color::oklch_to_hex;
+///
+/// // Reference values from the CSS Color 4 sample code
+/// assert_eq!(oklch_to_hex(0.628, 0.2577, 29.23), "#ff0000");
+/// assert_eq!(oklch_to_hex(0.8664, 0.2948, 142.5), "#00ff00");
+/// assert_eq!(oklch_to_hex(0.452, 0.313, 264.05), "#0000ff");
+/// assert_eq!(oklch_to_hex(0.6, 0.0, 0.0), "#808080");
+/// assert_eq!(oklch_to_hex(1.0, 0.0, 0.0), "#ffffff");
+/// ```
+pub fn oklch_to_hex(l: f32, c: f32, h: f32) -> String {
+    srgb_to_hex(Srgb::from_color(Oklch::new(l, c, h)))
+}
+
+/// Convert a CIE LCH color to `#rrggbb`, clamping to the sRGB gamut. As in CSS,
+/// LCH is relative to a D50 white and adapted to sRGB's D65 with the Bradford
+/// transform. `l` is in `0.0..=100.0` and `h` in degrees.
+///
+/// ```
+/// use hexvar_core::color::lch_to_hex;
+///
+/// // Reference values from the CSS Color 4 spec and sample code
+/// assert_eq!(lch_to_hex(54.2917, 106.8390, 40.8526), "#ff0000");
+/// assert_eq!(lch_to_hex(29.5683, 131.2014, 301.3642), "#0000ff");
+/// assert_eq!(lch_to_hex(52.2345, 72.2, 56.2), "#c65d06");
+/// assert_eq!(lch_to_hex(50.0, 0.0, 0.0), "#777777");
+/// ```
+pub fn lch_to_hex(l: f32, c: f32, h: f32) -> String {
+    // f64, since channels near 0 amplify rounding errors in the conversion
+    let xyz: Xyz<D50, f64> = Xyz::from_color(Lch::<D50, f64>::new(l as f64, c as f64, h as f64));
+    let xyz: Xyz<D65, f64> = xyz.adapt_into_unclamped();
+    srgb_to_hex(Srgb::<f64>::from_color(xyz).into_format())
+}
+
+/// Colors with a Lab chroma below this are grouped as `neutral` by [`hue_family`]
+pub const NEUTRAL_CHROMA: f32 = 10.0;
+
+/// Hue families by the upper bound of their Lab hue angle in degrees; hues
+/// at or above the last bound wrap around to `pink`
+pub const HUE_FAMILIES: &[(f32, &str)] = &[
+    (10.0, "pink"),
+    (55.0, "red"),
+    (85.0, "orange"),
+    (120.0, "yellow"),
+    (170.0, "green"),
+    (220.0, "cyan"),
+    (310.0, "blue"),
+    (340.0, "purple"),
+];
+
+/// Coarse color family ("red", "blue", "neutral", ...) from the hue angle and
+/// chroma of a Lab color
+///
+/// ```
+/// use hexvar_core::color::{hue_family, rgb_to_lab};
+///
+/// assert_eq!(hue_family(&rgb_to_lab(255, 99, 71)), "red");
+/// assert_eq!(hue_family(&rgb_to_lab(0, 0, 128)), "blue");
+/// assert_eq!(hue_family(&rgb_to_lab(255, 105, 180)), "pink");
+/// assert_eq!(hue_family(&rgb_to_lab(128, 128, 128)), "neutral");
+/// ```
+pub fn hue_family(lab: &Lab) -> &'static str {
+    if lab.a.hypot(lab.b) < NEUTRAL_CHROMA {
+        return "neutral";
+    }
+    let hue = lab.b.atan2(lab.a).to_degrees().rem_euclid(360.0);
+    HUE_FAMILIES.iter().find(|(bound, _)| hue < *bound).map(|(_, name)| *name).unwrap_or("pink")
+}
+
+/// Convert 8-bit sRGB channels to CIE Lab
+pub fn rgb_to_lab(r: u8, g: u8, b: u8) -> Lab {
+    Lab::from_color(Srgb::new(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0))
+}
+
+/// Convert CIE Lab to `#rrggbb`, clamping to the sRGB gamut
+///
+/// ```
+/// use hexvar_core::color::{lab_to_hex, rgb_to_lab};
+///
+/// assert_eq!(lab_to_hex(rgb_to_lab(255, 99, 71)), "#ff6347");
+/// ```
+pub fn lab_to_hex(lab: Lab) -> String {
+    srgb_to_hex(Srgb::from_color(lab))
+}
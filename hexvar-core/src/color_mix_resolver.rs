@@ -0,0 +1,147 @@
+use crate::color::hex_to_rgba;
+use palette::{FromColor, LinSrgb, Oklab, Srgb};
+use std::ops::Range;
+
+/// Byte ranges of the `color-mix()` calls in `text`, each up to and including
+/// its closing parenthesis. Calls nested inside another are listed too, after
+/// the one containing them; an unclosed call runs to the end of `text`.
+///
+/// ```
+/// use hexvar_core::color_mix_resolver::color_mix_ranges;
+///
+/// let css = "a { color: color-mix(in srgb, #f00 40%, var(--x)); border: 1px solid #000 }";
+/// let ranges = color_mix_ranges(css);
+/// assert_eq!(ranges.len(), 1);
+/// assert_eq!(&css[ranges[0].clone()], "color-mix(in srgb, #f00 40%, var(--x))");
+/// ```
+pub fn color_mix_ranges(text: &str) -> Vec<Range<usize>> {
+    let lower = text.to_ascii_lowercase();
+    let mut ranges = Vec::new();
+    for (start, _) in lower.match_indices("color-mix(") {
+        // `--my-color-mix(` or similar isn't the CSS function
+        if lower[..start].ends_with(|c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            continue;
+        }
+        let mut depth = 0;
+        let mut end = text.len();
+        for (i, c) in text[start..].char_indices() {
+            match c {
+                '(' => depth += 1,
+                ')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        end = start + i + 1;
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        ranges.push(start..end);
+    }
+    ranges
+}
+
+/// Interpolation spaces [`resolve`] can mix in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MixSpace {
+    Srgb,
+    SrgbLinear,
+    Oklab,
+}
+
+/// An RGBA color to mix
+type Rgba = (u8, u8, u8, u8);
+
+/// A color to mix and its percentage, if given
+fn parse_component(part: &str) -> Option<(Rgba, Option<f32>)> {
+    let mut color = None;
+    let mut percent = None;
+    for token in part.split_whitespace() {
+        if let Some(p) = token.strip_suffix('%') {
+            percent = Some(p.parse::<f32>().ok().filter(|p| (0.0..=100.0).contains(p))?);
+        } else if color.is_none() && token.starts_with('#') {
+            color = Some(hex_to_rgba(token)?);
+        } else {
+            return None;
+        }
+    }
+    Some((color?, percent))
+}
+
+/// The `#rrggbb` (or `#rrggbbaa` when not opaque) color a `color-mix()` call
+/// between two hex colors mixes to, following CSS Color 5: missing percentages
+/// make up the rest of 100%, percentages that don't add to 100% are scaled to,
+/// a total below 100% makes the result that much more transparent, and alpha
+/// is premultiplied. `srgb`, `srgb-linear`, and `oklab` can be mixed in; any
+/// other space, or a color that isn't a hex code, gives `None`.
+///
+/// ```
+/// use hexvar_core::color_mix_resolver::resolve;
+///
+/// assert_eq!(resolve("color-mix(in srgb, #ff0000, #0000ff)").as_deref(), Some("#800080"));
+/// assert_eq!(resolve("color-mix(in srgb, #ff0000 25%, #0000ff)").as_deref(), Some("#4000bf"));
+/// assert_eq!(resolve("color-mix(in srgb, #ffffff 20%, #000000 20%)").as_deref(), Some("#80808066"));
+/// assert_eq!(resolve("color-mix(in oklab, #000 50%, #fff)").as_deref(), Some("#636363"));
+/// assert_eq!(resolve("color-mix(in srgb, #f00, var(--x))"), None);
+/// assert_eq!(resolve("color-mix(in hsl, #f00, #00f)"), None);
+/// ```
+pub fn resolve(call: &str) -> Option<String> {
+    let args = call.trim().strip_suffix(')')?;
+    let args = args.get(args.find('(')? + 1..)?;
+    let mut parts = args.split(',');
+    let space = match parts.next()?.split_whitespace().collect::<Vec<_>>()[..] {
+        [kw, space] if kw.eq_ignore_ascii_case("in") => match space.to_ascii_lowercase().as_str() {
+            "srgb" => MixSpace::Srgb,
+            "srgb-linear" => MixSpace::SrgbLinear,
+            "oklab" => MixSpace::Oklab,
+            _ => return None,
+        },
+        _ => return None,
+    };
+    let (first, p1) = parse_component(parts.next()?)?;
+    let (second, p2) = parse_component(parts.next()?)?;
+    if parts.next().is_some() {
+        return None;
+    }
+    let (p1, p2) = match (p1, p2) {
+        (None, None) => (50.0, 50.0),
+        (Some(p1), None) => (p1, 100.0 - p1),
+        (None, Some(p2)) => (100.0 - p2, p2),
+        (Some(p1), Some(p2)) => (p1, p2),
+    };
+    let total = p1 + p2;
+    if total <= 0.0 {
+        return None;
+    }
+    let (w1, w2) = (p1 / total, p2 / total);
+
+    let to_space = |(r, g, b, _): Rgba| -> [f32; 3] {
+        let srgb = Srgb::new(r, g, b).into_format::<f32>();
+        match space {
+            MixSpace::Srgb => [srgb.red, srgb.green, srgb.blue],
+            MixSpace::SrgbLinear => {
+                let lin: LinSrgb = srgb.into_linear();
+                [lin.red, lin.green, lin.blue]
+            }
+            MixSpace::Oklab => {
+                let lab = Oklab::from_color(srgb.into_linear());
+                [lab.l, lab.a, lab.b]
+            }
+        }
+    };
+    let (a1, a2) = (first.3 as f32 / 255.0, second.3 as f32 / 255.0);
+    let alpha = a1 * w1 + a2 * w2;
+    let (c1, c2) = (to_space(first), to_space(second));
+    let mixed: [f32; 3] =
+        std::array::from_fn(|i| if alpha > 0.0 { (c1[i] * a1 * w1 + c2[i] * a2 * w2) / alpha } else { 0.0 });
+    let srgb: Srgb = match space {
+        MixSpace::Srgb => Srgb::new(mixed[0], mixed[1], mixed[2]),
+        MixSpace::SrgbLinear => Srgb::from_linear(LinSrgb::new(mixed[0], mixed[1], mixed[2])),
+        MixSpace::Oklab => Srgb::from_color(Oklab::new(mixed[0], mixed[1], mixed[2])),
+    };
+    let alpha = alpha * total.min(100.0) / 100.0;
+    let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+    let hex = format!("#{:02x}{:02x}{:02x}", channel(srgb.red), channel(srgb.green), channel(srgb.blue));
+    Some(if channel(alpha) == 255 { hex } else { format!("{}{:02x}", hex, channel(alpha)) })
+}
@@ -0,0 +1,44 @@
+use regex::Regex;
+
+/// Letter case applied to converted hex codes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HexCase {
+    Preserve,
+    Upper,
+    Lower,
+}
+
+/// How `hexvar convert` rewrites each hex literal
+#[derive(Clone, Copy, Debug)]
+pub struct ConvertOptions {
+    pub case: HexCase,
+    /// Expand `#rgb` to `#rrggbb` and `#rgba` to `#rrggbbaa`
+    pub expand_short: bool,
+}
+
+/// Convert a single hex literal (including the leading `#`)
+pub fn convert_hex(hex: &str, opts: ConvertOptions) -> String {
+    let digits = hex.trim_start_matches('#');
+    let mut out = String::with_capacity(9);
+    out.push('#');
+    // Only the short forms are expanded; 6 and 8 digit codes keep their digits
+    if opts.expand_short && (digits.len() == 3 || digits.len() == 4) {
+        for c in digits.chars() {
+            out.push(c);
+            out.push(c);
+        }
+    } else {
+        out.push_str(digits);
+    }
+    match opts.case {
+        HexCase::Preserve => out,
+        HexCase::Upper => out.to_uppercase(),
+        HexCase::Lower => out.to_lowercase(),
+    }
+}
+
+/// Rewrite every hex literal matched by `re` in `content`
+pub fn convert_content(content: &str, re: &Regex, opts: ConvertOptions) -> String {
+    re.replace_all(content, |caps: &regex::Captures| convert_hex(&caps[0], opts))
+        .into_owned()
+}
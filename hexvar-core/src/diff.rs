@@ -0,0 +1,93 @@
+use crate::report::ColorReport;
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct ColorCount {
+    pub hex: String,
+    pub count: u32,
+}
+
+#[derive(Serialize)]
+pub struct CountChange {
+    pub hex: String,
+    pub before: u32,
+    pub after: u32,
+    pub delta: i64,
+}
+
+/// Colors removed, added, or changed in count between two scan reports
+#[derive(Serialize)]
+pub struct DiffReport {
+    pub removed: Vec<ColorCount>,
+    pub added: Vec<ColorCount>,
+    pub changed: Vec<CountChange>,
+}
+
+impl DiffReport {
+    /// Compare two reports, dropping entries whose absolute count change is below `threshold`
+    pub fn new(before: &ColorReport, after: &ColorReport, threshold: u32) -> DiffReport {
+        let keep = |delta: i64| delta.unsigned_abs() >= threshold as u64;
+        let (before_map, after_map) = (before.by_hex(), after.by_hex());
+        let mut removed = Vec::new();
+        let mut changed = Vec::new();
+        for (hex, old) in &before.0 {
+            match after_map.get(hex.as_str()) {
+                None if keep(old.count as i64) => removed.push(ColorCount { hex: hex.clone(), count: old.count }),
+                Some(new) if new.count != old.count => {
+                    let delta = new.count as i64 - old.count as i64;
+                    if keep(delta) {
+                        changed.push(CountChange { hex: hex.clone(), before: old.count, after: new.count, delta });
+                    }
+                }
+                _ => {}
+            }
+        }
+        let mut added: Vec<ColorCount> = after
+            .0
+            .iter()
+            .filter(|(hex, new)| !before_map.contains_key(hex.as_str()) && keep(new.count as i64))
+            .map(|(hex, new)| ColorCount { hex: hex.clone(), count: new.count })
+            .collect();
+        removed.sort_by(|a, b| a.hex.cmp(&b.hex));
+        added.sort_by(|a, b| a.hex.cmp(&b.hex));
+        changed.sort_by(|a, b| a.hex.cmp(&b.hex));
+        DiffReport { removed, added, changed }
+    }
+
+    /// Render the diff as a human-readable ASCII table
+    pub fn render_table(&self) -> String {
+        let mut rows: Vec<[String; 5]> = Vec::new();
+        for c in &self.removed {
+            rows.push(["removed".into(), c.hex.clone(), c.count.to_string(), "0".into(), format!("-{}", c.count)]);
+        }
+        for c in &self.added {
+            rows.push(["added".into(), c.hex.clone(), "0".into(), c.count.to_string(), format!("+{}", c.count)]);
+        }
+        for c in &self.changed {
+            rows.push(["changed".into(), c.hex.clone(), c.before.to_string(), c.after.to_string(), format!("{:+}", c.delta)]);
+        }
+        if rows.is_empty() {
+            return "No differences.\n".to_string();
+        }
+        let header = ["status", "hex", "before", "after", "delta"].map(String::from);
+        let mut widths = header.clone().map(|h| h.len());
+        for row in &rows {
+            for (w, cell) in widths.iter_mut().zip(row) {
+                *w = (*w).max(cell.len());
+            }
+        }
+        let border = format!("+{}+\n", widths.iter().map(|w| "-".repeat(w + 2)).collect::<Vec<_>>().join("+"));
+        let line = |row: &[String; 5]| {
+            let cells: Vec<String> = row.iter().zip(&widths).map(|(c, w)| format!(" {:<w$} ", c, w = w)).collect();
+            format!("|{}|\n", cells.join("|"))
+        };
+        let mut out = border.clone();
+        out.push_str(&line(&header));
+        out.push_str(&border);
+        for row in &rows {
+            out.push_str(&line(row));
+        }
+        out.push_str(&border);
+        out
+    }
+}
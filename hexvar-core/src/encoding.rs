@@ -0,0 +1,101 @@
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+
+/// How source files are decoded before they are scanned. A byte order mark
+/// always wins, so UTF-16 files with one are read whatever the setting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub enum SourceEncoding {
+    /// Guess from the content: UTF-16 if every other byte of the first pair is
+    /// zero, UTF-8 if it is valid UTF-8, and Windows-1252 otherwise
+    Detect,
+    /// Always this encoding
+    Fixed(&'static Encoding),
+}
+
+impl Default for SourceEncoding {
+    fn default() -> Self {
+        SourceEncoding::Fixed(UTF_8)
+    }
+}
+
+impl SourceEncoding {
+    /// Decode `bytes` to text, or fail with the encoding they aren't valid in.
+    /// UTF-8 without a byte order mark is borrowed rather than copied.
+    ///
+    /// ```
+    /// use std::borrow::Cow;
+    /// use hexvar_core::encoding::SourceEncoding;
+    ///
+    /// let latin1 = b"/* caf\xe9 */ a { color: #fff }";
+    /// assert!(SourceEncoding::default().decode(latin1).is_err());
+    /// assert_eq!(SourceEncoding::Detect.decode(latin1).unwrap(), "/* café */ a { color: #fff }");
+    /// let windows: SourceEncoding = "windows-1252".parse().unwrap();
+    /// assert_eq!(windows.decode(latin1), SourceEncoding::Detect.decode(latin1));
+    ///
+    /// // A byte order mark overrides the encoding, and is dropped
+    /// let utf16 = [0xff, 0xfe, b'#', 0, b'f', 0, b'0', 0, b'0', 0];
+    /// assert_eq!(SourceEncoding::default().decode(&utf16).unwrap(), "#f00");
+    /// assert_eq!(SourceEncoding::Detect.decode(&utf16[2..]).unwrap(), "#f00");
+    /// assert!(matches!(SourceEncoding::default().decode(b"#fff"), Ok(Cow::Borrowed("#fff"))));
+    /// ```
+    pub fn decode(self, bytes: &[u8]) -> Result<Cow<'_, str>, &'static Encoding> {
+        let encoding = match self {
+            SourceEncoding::Fixed(encoding) => encoding,
+            SourceEncoding::Detect => match bytes {
+                _ if Encoding::for_bom(bytes).is_some() => UTF_8,
+                [first, 0, ..] if *first != 0 => UTF_16LE,
+                [0, second, ..] if *second != 0 => UTF_16BE,
+                _ if std::str::from_utf8(bytes).is_ok() => UTF_8,
+                _ => WINDOWS_1252,
+            },
+        };
+        // Sniffs the byte order mark, which takes precedence over `encoding`
+        let (text, used, had_errors) = encoding.decode(bytes);
+        if had_errors {
+            Err(used)
+        } else {
+            Ok(text)
+        }
+    }
+}
+
+impl FromStr for SourceEncoding {
+    type Err = String;
+
+    /// `detect`, or a WHATWG encoding label like `utf-8`, `windows-1252`, `latin1`, or `utf-16le`
+    fn from_str(label: &str) -> Result<Self, Self::Err> {
+        if label.eq_ignore_ascii_case("detect") {
+            return Ok(SourceEncoding::Detect);
+        }
+        Encoding::for_label(label.trim().as_bytes())
+            .map(SourceEncoding::Fixed)
+            .ok_or_else(|| format!("unknown encoding {:?}; use `detect` or a label like `utf-8` or `windows-1252`", label))
+    }
+}
+
+impl fmt::Display for SourceEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SourceEncoding::Detect => f.write_str("detect"),
+            SourceEncoding::Fixed(encoding) => f.write_str(encoding.name()),
+        }
+    }
+}
+
+impl From<SourceEncoding> for String {
+    fn from(encoding: SourceEncoding) -> String {
+        encoding.to_string()
+    }
+}
+
+impl TryFrom<String> for SourceEncoding {
+    type Error = String;
+
+    fn try_from(label: String) -> Result<Self, Self::Error> {
+        label.parse()
+    }
+}
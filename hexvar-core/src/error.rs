@@ -0,0 +1,103 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// Everything that can go wrong in hexvar-core. Variants wrapping another
+/// error expose it through [`std::error::Error::source`] rather than
+/// repeating it in their message.
+#[derive(Debug)]
+pub enum HexvarError {
+    /// A glob pattern could not be parsed
+    Glob { pattern: String, source: glob::PatternError },
+    /// An `--ignore` pattern could not be parsed
+    IgnorePattern { pattern: String, source: ignore::Error },
+    /// A file could not be read or written
+    Io { path: PathBuf, source: io::Error },
+    /// A JSON file could not be parsed or serialized
+    Json { path: PathBuf, source: serde_json::Error },
+    /// A YAML file could not be parsed or serialized
+    Yaml { path: PathBuf, source: serde_yaml::Error },
+    /// The `colours_map.json` mapping could not be read
+    MissingMapFile { path: PathBuf, source: io::Error },
+    /// The CSS variables file could not be read
+    MissingCssFile { path: PathBuf, source: io::Error },
+    /// A canonical color in the mapping has no variable in the CSS variables file
+    MissingVariable { hex: String },
+    /// A `--name-template` is malformed or uses an unknown placeholder
+    NameTemplate { template: String, message: String },
+    /// A custom color name table maps a name to something that isn't a hex code
+    InvalidColorName { name: String, hex: String },
+    /// No backup is recorded for a file being restored
+    MissingBackup { path: PathBuf },
+    /// More files matched than the `--max-files` limit allows
+    TooManyFiles { limit: usize },
+    /// `--since` was given outside a git work tree
+    NotGitRepository,
+    /// A git command failed, e.g. because the `--since` ref doesn't exist
+    Git { message: String },
+}
+
+/// Result type returned by hexvar-core functions
+pub type Result<T> = std::result::Result<T, HexvarError>;
+
+impl HexvarError {
+    /// Wrap an I/O error with the path it happened on
+    pub fn io(path: impl Into<PathBuf>, source: io::Error) -> HexvarError {
+        HexvarError::Io { path: path.into(), source }
+    }
+
+    /// Wrap a JSON error with the path of the file being read or written
+    pub fn json(path: impl Into<PathBuf>, source: serde_json::Error) -> HexvarError {
+        HexvarError::Json { path: path.into(), source }
+    }
+}
+
+impl fmt::Display for HexvarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HexvarError::Glob { pattern, .. } => write!(f, "invalid glob pattern \"{}\"", pattern),
+            HexvarError::IgnorePattern { pattern, .. } => write!(f, "invalid ignore pattern \"{}\"", pattern),
+            HexvarError::Io { path, .. } => write!(f, "failed to access {}", path.display()),
+            HexvarError::Json { path, .. } => write!(f, "invalid JSON in {}", path.display()),
+            HexvarError::Yaml { path, .. } => write!(f, "invalid YAML in {}", path.display()),
+            HexvarError::MissingMapFile { path, .. } => {
+                write!(f, "failed to read color mapping {} (run `hexvar scan --css-vars` first)", path.display())
+            }
+            HexvarError::MissingCssFile { path, .. } => write!(f, "failed to read CSS variables file {}", path.display()),
+            HexvarError::MissingVariable { hex } => {
+                write!(f, "no variable name found in the CSS variables file for canonical hex {}", hex)
+            }
+            HexvarError::NameTemplate { template, message } => write!(f, "{} in name template \"{}\"", message, template),
+            HexvarError::InvalidColorName { name, hex } => {
+                write!(f, "color name \"{}\" maps to \"{}\", which is not a hex code", name, hex)
+            }
+            HexvarError::MissingBackup { path } => write!(f, "no backup recorded for {}", path.display()),
+            HexvarError::TooManyFiles { limit } => {
+                write!(f, "more than {} files matched; narrow the glob or raise --max-files", limit)
+            }
+            HexvarError::NotGitRepository => write!(f, "the current directory is not inside a git repository"),
+            HexvarError::Git { message } => write!(f, "git failed: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for HexvarError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            HexvarError::Glob { source, .. } => Some(source),
+            HexvarError::IgnorePattern { source, .. } => Some(source),
+            HexvarError::Io { source, .. }
+            | HexvarError::MissingMapFile { source, .. }
+            | HexvarError::MissingCssFile { source, .. } => Some(source),
+            HexvarError::Json { source, .. } => Some(source),
+            HexvarError::Yaml { source, .. } => Some(source),
+            HexvarError::MissingVariable { .. }
+            | HexvarError::NameTemplate { .. }
+            | HexvarError::InvalidColorName { .. }
+            | HexvarError::MissingBackup { .. }
+            | HexvarError::TooManyFiles { .. }
+            | HexvarError::NotGitRepository
+            | HexvarError::Git { .. } => None,
+        }
+    }
+}
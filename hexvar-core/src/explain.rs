@@ -0,0 +1,182 @@
+use crate::cluster::DistanceMetric;
+use crate::color::{hex_to_rgba, rgb_to_lab};
+use crate::error::{HexvarError, Result};
+use crate::replace::css_var_definitions;
+use crate::scanner::normalize_hex;
+use palette::Lab;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+/// One of the colors `hexvar explain` compares, and where the last scan put it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExplainedColor {
+    /// The color as normalized by the scanner, e.g. `#aabbcc`
+    pub hex: String,
+    /// CIE L*, a*, and b*
+    pub lab: [f32; 3],
+    /// Canonical color of the mapping cluster it is in, if any
+    pub canonical: Option<String>,
+    /// Variable the canonical color was assigned, if the CSS variables file names one
+    pub name: Option<String>,
+}
+
+/// Where the threshold `hexvar explain` compares against came from. The mapping
+/// doesn't record the threshold of the scan that wrote it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThresholdSource {
+    /// Given with `--delta-e`
+    Flag,
+    /// `delta_e` in the config file
+    Config,
+    /// Neither, so the scan's default
+    Default,
+}
+
+/// Why two colors were or were not merged, printed by `hexvar explain`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Explanation {
+    pub a: ExplainedColor,
+    pub b: ExplainedColor,
+    pub delta_e: f32,
+    /// Delta E threshold the colors are compared against
+    pub threshold: f32,
+    pub threshold_source: ThresholdSource,
+    /// Formula `delta_e` is measured with
+    pub color_space: DistanceMetric,
+    /// Whether the mapping puts both in the same cluster
+    pub merged: bool,
+}
+
+impl Explanation {
+    /// Compare two hex codes against a `colours_map.json` mapping, naming their
+    /// clusters from `hex_to_var` (as built by
+    /// [`build_hex_to_var`](crate::replace::build_hex_to_var)). `None` if either
+    /// isn't a hex code.
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use hexvar_core::cluster::DistanceMetric;
+    /// use hexvar_core::explain::{Explanation, ThresholdSource};
+    ///
+    /// let map = HashMap::from([("#ff0000".to_string(), vec!["#ff0000".to_string(), "#F00".to_string(), "#fe0000".to_string()])]);
+    /// let hex_to_var = HashMap::from([("#ff0000".to_string(), "--color-red".to_string())]);
+    /// let explain = |a: &str, b: &str, metric| Explanation::new(a, b, &map, &hex_to_var, 10.0, ThresholdSource::Default, metric);
+    /// let merged = explain("#F00", "#FE0000", DistanceMetric::Lab).unwrap();
+    /// assert!(merged.merged);
+    /// assert_eq!(merged.a.hex, "#ff0000");
+    /// assert_eq!(merged.b.name.as_deref(), Some("--color-red"));
+    ///
+    /// let apart = explain("#ff0000", "#0000ff", DistanceMetric::Lab).unwrap();
+    /// assert!(!apart.merged);
+    /// assert_eq!(apart.b.canonical, None);
+    /// assert!(apart.delta_e > apart.threshold);
+    /// assert!(explain("#ff0000", "red", DistanceMetric::Lab).is_none());
+    ///
+    /// // Measured as the scan did: CIEDE2000 puts these blues much closer than CIE76
+    /// let cie76 = explain("#0000ff", "#1e1eff", DistanceMetric::Cie76).unwrap();
+    /// let ciede2000 = explain("#0000ff", "#1e1eff", DistanceMetric::Ciede2000).unwrap();
+    /// assert!(ciede2000.delta_e < 5.0 && cie76.delta_e > 5.0);
+    /// assert!(ciede2000.render_text().contains("Delta E (ciede2000): "));
+    /// ```
+    pub fn new(
+        a: &str,
+        b: &str,
+        map: &HashMap<String, Vec<String>>,
+        hex_to_var: &HashMap<String, String>,
+        threshold: f32,
+        threshold_source: ThresholdSource,
+        color_space: DistanceMetric,
+    ) -> Option<Explanation> {
+        let a = explain_color(a, map, hex_to_var)?;
+        let b = explain_color(b, map, hex_to_var)?;
+        let lab = |c: &ExplainedColor| -> Lab { Lab::new(c.lab[0], c.lab[1], c.lab[2]) };
+        let delta_e = color_space.distance()(lab(&a), lab(&b));
+        let merged = a.canonical.is_some() && a.canonical == b.canonical;
+        Some(Explanation { a, b, delta_e, threshold, threshold_source, color_space, merged })
+    }
+
+    /// Read the mapping and, if it exists, the CSS variables file naming its
+    /// canonical colors, then compare as in [`Explanation::new`]
+    pub fn load(
+        a: &str,
+        b: &str,
+        map_path: &Path,
+        css_path: &Path,
+        threshold: f32,
+        threshold_source: ThresholdSource,
+        color_space: DistanceMetric,
+    ) -> Result<Option<Explanation>> {
+        let map_json = fs::read_to_string(map_path)
+            .map_err(|source| HexvarError::MissingMapFile { path: map_path.to_path_buf(), source })?;
+        let map: HashMap<String, Vec<String>> =
+            serde_json::from_str(&map_json).map_err(|e| HexvarError::json(map_path, e))?;
+        // Without a variables file the clusters are still explained, just not named
+        let css = fs::read_to_string(css_path).unwrap_or_default();
+        let hex_to_var = css_var_definitions(&css).into_iter().map(|(var, hex)| (hex, var)).collect();
+        Ok(Explanation::new(a, b, &map, &hex_to_var, threshold, threshold_source, color_space))
+    }
+
+    /// The explanation as the lines printed by `hexvar explain`
+    pub fn render_text(&self) -> String {
+        let mut out = String::new();
+        for c in [&self.a, &self.b] {
+            let [l, a, b] = c.lab;
+            write!(out, "{}  Lab({:.1}, {:.1}, {:.1})  ", c.hex, l, a, b).unwrap();
+            match (&c.canonical, &c.name) {
+                (Some(canonical), Some(name)) => writeln!(out, "cluster {} ({})", canonical, name),
+                (Some(canonical), None) => writeln!(out, "cluster {} (no variable)", canonical),
+                (None, _) => writeln!(out, "not in the mapping"),
+            }
+            .unwrap();
+        }
+        let source = match self.threshold_source {
+            ThresholdSource::Flag => "from --delta-e",
+            ThresholdSource::Config => "from delta_e in the config file",
+            ThresholdSource::Default => "the default; pass --delta-e if the scan used another",
+        };
+        writeln!(out, "Delta E ({}): {:.2} (threshold {}, {})", self.color_space, self.delta_e, self.threshold, source)
+            .unwrap();
+        let margin = (self.delta_e - self.threshold).abs();
+        if self.merged {
+            let name = self.a.name.as_deref().map(|n| format!(" ({})", n)).unwrap_or_default();
+            writeln!(out, "Merged into {}{}", self.a.canonical.as_deref().unwrap_or_default(), name).unwrap();
+        } else if self.delta_e >= self.threshold {
+            writeln!(out, "Not merged: {:.2} above the threshold", margin).unwrap();
+        } else {
+            // Clustering compares each color with canonical colors, not with each other,
+            // so two close colors can still end up in neighboring clusters
+            writeln!(
+                out,
+                "Not merged, although {:.2} below the threshold: each is closer to a different canonical color, or the mapping is out of date",
+                margin
+            )
+            .unwrap();
+        }
+        out
+    }
+}
+
+/// Lab coordinates of `hex` and the mapping cluster holding it
+fn explain_color(
+    hex: &str,
+    map: &HashMap<String, Vec<String>>,
+    hex_to_var: &HashMap<String, String>,
+) -> Option<ExplainedColor> {
+    let (r, g, b, _) = hex_to_rgba(hex.strip_prefix('#')?)?;
+    let hex = normalize_hex(hex);
+    let lab = rgb_to_lab(r, g, b);
+    let mut canonical: Vec<&String> = map
+        .iter()
+        .filter(|(canon, members)| normalize_hex(canon) == hex || members.iter().any(|m| normalize_hex(m) == hex))
+        .map(|(canon, _)| canon)
+        .collect();
+    // A color only belongs to one cluster, but don't depend on map order if it doesn't
+    canonical.sort();
+    let canonical = canonical.first().map(|c| normalize_hex(c));
+    let name = canonical.as_ref().and_then(|c| hex_to_var.get(c).cloned());
+    Some(ExplainedColor { hex, lab: [lab.l, lab.a, lab.b], canonical, name })
+}
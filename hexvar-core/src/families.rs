@@ -0,0 +1,122 @@
+use crate::cluster::{cluster_usage, Cluster};
+use crate::color::NEUTRAL_CHROMA;
+use palette::{FromColor, Lab, Lch};
+use serde::ser::Serializer;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Hue-based family of a color, in the order families are reported
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorFamily {
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Cyan,
+    Blue,
+    Violet,
+    Magenta,
+    /// Grays, black, white, and colors too faint to have a clear hue
+    Achromatic,
+}
+
+/// Chroma below which [`hue_family`] calls a color achromatic
+pub const DEFAULT_ACHROMATIC_CHROMA: f32 = NEUTRAL_CHROMA;
+
+/// Families by the upper bound of their LCh hue angle in degrees; hues at or
+/// above the last bound, and below the first, are magenta (which takes in the pinks)
+const FAMILY_HUES: &[(f32, ColorFamily)] = &[
+    (10.0, ColorFamily::Magenta),
+    (55.0, ColorFamily::Red),
+    (85.0, ColorFamily::Orange),
+    (120.0, ColorFamily::Yellow),
+    (170.0, ColorFamily::Green),
+    (235.0, ColorFamily::Cyan),
+    (310.0, ColorFamily::Blue),
+    (322.0, ColorFamily::Violet),
+];
+
+/// Family of a Lab color from its LCh hue angle, or `Achromatic` below
+/// [`DEFAULT_ACHROMATIC_CHROMA`]
+///
+/// ```
+/// use hexvar_core::color::rgb_to_lab;
+/// use hexvar_core::families::{hue_family, ColorFamily};
+///
+/// assert_eq!(hue_family(rgb_to_lab(255, 99, 71)), ColorFamily::Red);
+/// assert_eq!(hue_family(rgb_to_lab(0, 0, 128)), ColorFamily::Blue);
+/// assert_eq!(hue_family(rgb_to_lab(138, 43, 226)), ColorFamily::Violet);
+/// assert_eq!(hue_family(rgb_to_lab(255, 20, 147)), ColorFamily::Magenta);
+/// assert_eq!(hue_family(rgb_to_lab(128, 128, 128)), ColorFamily::Achromatic);
+/// ```
+pub fn hue_family(lab: Lab) -> ColorFamily {
+    hue_family_with(lab, DEFAULT_ACHROMATIC_CHROMA)
+}
+
+/// Like [`hue_family`], with colors below `achromatic_chroma` counted as achromatic
+///
+/// ```
+/// use hexvar_core::color::rgb_to_lab;
+/// use hexvar_core::families::{hue_family_with, ColorFamily};
+///
+/// // Slate gray has a chroma of about 11
+/// assert_eq!(hue_family_with(rgb_to_lab(112, 128, 144), 10.0), ColorFamily::Blue);
+/// assert_eq!(hue_family_with(rgb_to_lab(112, 128, 144), 15.0), ColorFamily::Achromatic);
+/// ```
+pub fn hue_family_with(lab: Lab, achromatic_chroma: f32) -> ColorFamily {
+    let lch = Lch::from_color(lab);
+    if lch.chroma < achromatic_chroma {
+        return ColorFamily::Achromatic;
+    }
+    let hue = lch.hue.into_positive_degrees();
+    FAMILY_HUES.iter().find(|(bound, _)| hue < *bound).map_or(ColorFamily::Magenta, |(_, family)| *family)
+}
+
+/// Canonical colors grouped by family for `scan --group-by-family`: an object
+/// keyed by family name, each holding `{hex: count}` with the most-used first.
+/// Empty families are left out.
+pub struct FamilyReport(pub Vec<(ColorFamily, Vec<(String, u32)>)>);
+
+impl FamilyReport {
+    /// Group `clusters` by the family of their canonical color, counting every
+    /// color merged into each
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use hexvar_core::families::{FamilyReport, DEFAULT_ACHROMATIC_CHROMA};
+    ///
+    /// let counts = HashMap::from([("#ff0000".to_string(), 3), ("#fe0000".to_string(), 1), ("#808080".to_string(), 2)]);
+    /// let clusters = hexvar_core::cluster_colors(&counts, 10.0);
+    /// let report = FamilyReport::new(&clusters, &counts, DEFAULT_ACHROMATIC_CHROMA);
+    /// let json = serde_json::to_value(&report).unwrap();
+    /// // Both reds merge into one canonical color, used 4 times
+    /// assert_eq!(json["red"].as_object().unwrap().values().collect::<Vec<_>>(), [4]);
+    /// assert_eq!(json["achromatic"]["#808080"], 2);
+    /// ```
+    pub fn new(clusters: &[Cluster], counts: &HashMap<String, u32>, achromatic_chroma: f32) -> FamilyReport {
+        let mut families: HashMap<ColorFamily, Vec<(String, u32)>> = HashMap::new();
+        for cluster in clusters {
+            let family = hue_family_with(cluster.lab, achromatic_chroma);
+            families.entry(family).or_default().push((cluster.hex.clone(), cluster_usage(cluster, counts)));
+        }
+        let mut families: Vec<_> = families.into_iter().collect();
+        families.sort_by_key(|(family, _)| *family);
+        for (_, colors) in &mut families {
+            colors.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        }
+        FamilyReport(families)
+    }
+}
+
+impl Serialize for FamilyReport {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        struct Colors<'a>(&'a [(String, u32)]);
+        impl Serialize for Colors<'_> {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_map(self.0.iter().map(|(hex, count)| (hex, count)))
+            }
+        }
+        serializer.collect_map(self.0.iter().map(|(family, colors)| (family, Colors(colors))))
+    }
+}
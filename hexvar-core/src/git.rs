@@ -0,0 +1,30 @@
+use crate::error::{HexvarError, Result};
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Run `git` with `args` in the current directory, returning its stdout
+fn git(args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .output()
+        .map_err(|e| HexvarError::Git { message: format!("could not run git: {}", e) })?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(HexvarError::Git { message: stderr.trim().to_string() });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Files changed between `reference` and the working tree, as listed by
+/// `git diff --name-only`, relative to the current directory. Only files
+/// inside the current directory are included; deleted files are listed too.
+///
+/// Fails with [`HexvarError::NotGitRepository`] outside a git work tree, and
+/// with [`HexvarError::Git`] if git can't resolve `reference`.
+pub fn changed_since(reference: &str) -> Result<Vec<PathBuf>> {
+    if git(&["rev-parse", "--is-inside-work-tree"]).map_or(true, |out| out.trim() != "true") {
+        return Err(HexvarError::NotGitRepository);
+    }
+    let out = git(&["diff", "--name-only", "--relative", reference, "--"])?;
+    Ok(out.lines().filter(|l| !l.is_empty()).map(PathBuf::from).collect())
+}
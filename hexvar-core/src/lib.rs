@@ -0,0 +1,42 @@
+//! Core scanning, clustering, and rewriting logic behind the `hexvar` CLI.
+//!
+//! The typical pipeline is: [`collect_paths`] to expand globs, [`scan_paths`] to
+//! count hex codes, [`cluster_colors`] to merge perceptually similar colors,
+//! [`generate_css_vars`] to render a variables file, and [`replace_in_content`]
+//! to rewrite sources to reference those variables.
+
+pub mod accessibility;
+pub mod audit;
+pub mod backup;
+pub mod cache;
+pub mod cluster;
+pub mod color;
+pub mod color_mix_resolver;
+pub mod convert;
+pub mod css_color_names;
+pub mod diff;
+pub mod encoding;
+pub mod error;
+pub mod explain;
+pub mod families;
+pub mod git;
+pub mod matrix;
+pub mod naming;
+pub mod normalize;
+pub mod output;
+pub mod paths;
+pub mod preprocessor;
+pub mod replace;
+pub mod report;
+pub mod sarif;
+pub mod scanner;
+pub mod stats;
+pub mod validate;
+
+pub use cluster::{cluster_colors, Cluster, ClusterAlgo};
+pub use error::HexvarError;
+pub use output::{generate_css_vars, VarFormat};
+pub use paths::collect_paths;
+pub use replace::replace_in_content;
+pub use report::{ColorEntry, ColorReport, Location, ReportSort};
+pub use scanner::scan_paths;
@@ -0,0 +1,147 @@
+use crate::cluster::Cluster;
+use crate::stats::ColorPair;
+use palette::color_difference::DeltaE;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Delta E (CIE76) between every pair of canonical colors, written by `hexvar matrix`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DistanceMatrix {
+    /// Canonical hex codes in hex order; row and column `i` are `colors[i]`
+    pub colors: Vec<String>,
+    pub distances: Vec<Vec<f32>>,
+}
+
+/// Two canonical colors close enough that a reviewer may want to merge them,
+/// listed under `"near_duplicates"` in the report by `scan --min-delta-e`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NearDuplicate {
+    pub a: String,
+    pub a_name: String,
+    pub b: String,
+    pub b_name: String,
+    pub delta_e: f32,
+}
+
+/// Pairs of `clusters` whose Delta E is below `below` but at least `merged_at`,
+/// the threshold they were clustered with, closest first
+///
+/// ```
+/// use std::collections::HashMap;
+/// use hexvar_core::matrix::near_duplicates;
+///
+/// let counts = HashMap::from([("#ff0000".to_string(), 1), ("#e00000".to_string(), 1), ("#0000ff".to_string(), 1)]);
+/// let clusters = hexvar_core::cluster_colors(&counts, 5.0);
+/// let pairs = near_duplicates(&clusters, 15.0, 5.0);
+/// assert_eq!(pairs.len(), 1);
+/// assert_eq!((pairs[0].a.as_str(), pairs[0].b.as_str()), ("#e00000", "#ff0000"));
+/// assert_eq!(pairs[0].b_name, "--color-red");
+/// assert!(pairs[0].delta_e >= 5.0 && pairs[0].delta_e < 15.0);
+/// ```
+pub fn near_duplicates(clusters: &[Cluster], below: f32, merged_at: f32) -> Vec<NearDuplicate> {
+    let names: HashMap<&str, &str> = clusters.iter().map(|c| (c.hex.as_str(), c.name.as_str())).collect();
+    let name = |hex: &str| names.get(hex).map_or_else(String::new, |n| n.to_string());
+    DistanceMatrix::new(clusters)
+        .pairs_below(below)
+        .into_iter()
+        .filter(|pair| pair.delta_e >= merged_at)
+        .map(|pair| NearDuplicate {
+            a_name: name(&pair.a),
+            b_name: name(&pair.b),
+            a: pair.a,
+            b: pair.b,
+            delta_e: pair.delta_e,
+        })
+        .collect()
+}
+
+/// Round a distance to two decimals, enough to compare against thresholds
+fn round(delta_e: f32) -> f32 {
+    (delta_e * 100.0).round() / 100.0
+}
+
+impl DistanceMatrix {
+    /// Build the matrix over `clusters`, ordered by canonical hex
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use hexvar_core::matrix::DistanceMatrix;
+    ///
+    /// let counts = HashMap::from([("#000000".to_string(), 1), ("#ffffff".to_string(), 1)]);
+    /// let matrix = DistanceMatrix::new(&hexvar_core::cluster_colors(&counts, 0.0));
+    /// assert_eq!(matrix.colors, ["#000000", "#ffffff"]);
+    /// assert_eq!(matrix.distances, [[0.0, 100.0], [100.0, 0.0]]);
+    /// ```
+    pub fn new(clusters: &[Cluster]) -> DistanceMatrix {
+        let mut sorted: Vec<&Cluster> = clusters.iter().collect();
+        sorted.sort_by(|a, b| a.hex.cmp(&b.hex));
+        let distances = sorted
+            .iter()
+            .map(|a| sorted.iter().map(|b| round(a.lab.delta_e(b.lab))).collect())
+            .collect();
+        DistanceMatrix { colors: sorted.iter().map(|c| c.hex.clone()).collect(), distances }
+    }
+
+    /// Each pair of distinct colors closer than `threshold`, closest first
+    /// (ties in hex order), for a sparse view of a large palette
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use hexvar_core::matrix::DistanceMatrix;
+    ///
+    /// let counts = HashMap::from([("#000000".to_string(), 1), ("#010101".to_string(), 1), ("#ffffff".to_string(), 1)]);
+    /// let matrix = DistanceMatrix::new(&hexvar_core::cluster_colors(&counts, 0.0));
+    /// let pairs = matrix.pairs_below(5.0);
+    /// assert_eq!(pairs.len(), 1);
+    /// assert_eq!((pairs[0].a.as_str(), pairs[0].b.as_str()), ("#000000", "#010101"));
+    /// ```
+    pub fn pairs_below(&self, threshold: f32) -> Vec<ColorPair> {
+        let mut pairs = Vec::new();
+        for (i, a) in self.colors.iter().enumerate() {
+            for (j, b) in self.colors.iter().enumerate().skip(i + 1) {
+                let delta_e = self.distances[i][j];
+                if delta_e < threshold {
+                    pairs.push(ColorPair { a: a.clone(), b: b.clone(), delta_e });
+                }
+            }
+        }
+        pairs.sort_by(|x, y| x.delta_e.total_cmp(&y.delta_e));
+        pairs
+    }
+
+    /// The matrix as CSV, with the colors as the header row and first column
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use hexvar_core::matrix::DistanceMatrix;
+    ///
+    /// let counts = HashMap::from([("#000000".to_string(), 1), ("#ffffff".to_string(), 1)]);
+    /// let matrix = DistanceMatrix::new(&hexvar_core::cluster_colors(&counts, 0.0));
+    /// assert_eq!(matrix.render_csv(), ",#000000,#ffffff\n#000000,0.00,100.00\n#ffffff,100.00,0.00\n");
+    /// ```
+    pub fn render_csv(&self) -> String {
+        let mut out = String::new();
+        for hex in &self.colors {
+            write!(out, ",{}", hex).unwrap();
+        }
+        out.push('\n');
+        for (hex, row) in self.colors.iter().zip(&self.distances) {
+            out.push_str(hex);
+            for d in row {
+                write!(out, ",{:.2}", d).unwrap();
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Pairs from [`DistanceMatrix::pairs_below`] as CSV with an `a,b,delta_e` header
+pub fn render_pairs_csv(pairs: &[ColorPair]) -> String {
+    let mut out = String::from("a,b,delta_e\n");
+    for pair in pairs {
+        writeln!(out, "{},{},{:.2}", pair.a, pair.b, pair.delta_e).unwrap();
+    }
+    out
+}
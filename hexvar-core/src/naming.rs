@@ -0,0 +1,286 @@
+use crate::cluster::{cluster_usage, Cluster};
+use crate::color::{hex_to_rgba, lab_to_hex, rgb_to_lab};
+use crate::css_color_names::CSS_COLOR_NAMES;
+use crate::error::{HexvarError, Result};
+use palette::Lab;
+use schemes::{NamingScheme, NamingSchemeKind, PaletteScheme};
+use std::collections::{HashMap, HashSet};
+
+pub mod schemes;
+
+/// Template used when `--name-template` isn't given, producing `--color-tomato`
+pub const DEFAULT_NAME_TEMPLATE: &str = "{prefix}-{name}";
+
+/// Prefix substituted for `{prefix}` unless overridden
+pub const DEFAULT_PREFIX: &str = "color";
+
+/// Placeholders a name template may use
+pub const PLACEHOLDERS: &[&str] = &["prefix", "name", "hex", "r", "g", "b"];
+
+/// Picks the `{name}` of a color from a custom table of names (e.g. loaded
+/// from `--color-names`) and then [`CSS_COLOR_NAMES`], in that order, or from
+/// another [`NamingSchemeKind`]'s palette
+#[derive(Clone, Debug, Default)]
+pub struct NameResolver {
+    /// Names of preprocessor variables declared with exactly these colors,
+    /// tried before anything else
+    variables: Vec<(String, (u8, u8, u8))>,
+    /// Custom names and their RGB channels, in priority order
+    custom: Vec<(String, (u8, u8, u8))>,
+    /// Palette used instead of the CSS names, if any
+    palette: Option<PaletteScheme>,
+}
+
+impl NameResolver {
+    /// A resolver that tries `custom` names before the CSS ones. Fails with
+    /// [`HexvarError::InvalidColorName`] for a value that isn't a hex code.
+    pub fn new<I>(custom: I) -> Result<NameResolver>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let custom = custom
+            .into_iter()
+            .map(|(name, hex)| match hex_to_rgba(&hex) {
+                Some((r, g, b, _)) => Ok((name, (r, g, b))),
+                None => Err(HexvarError::InvalidColorName { name, hex }),
+            })
+            .collect::<Result<_>>()?;
+        Ok(NameResolver { variables: Vec::new(), custom, palette: None })
+    }
+
+    /// Name colors that are exactly the value of an SCSS `$var` or Less
+    /// `@var` declaration after the variable, without its sigil, whatever the
+    /// other tables say. `variables` pairs hex codes with variable names, as
+    /// in [`ScanTally::variables`](crate::scanner::ScanTally::variables).
+    ///
+    /// ```
+    /// use hexvar_core::naming::NameResolver;
+    ///
+    /// let names = NameResolver::default().with_variables([("#0052cc".to_string(), "$brand-blue".to_string())]);
+    /// assert_eq!(names.name_for("#0052cc"), "brand-blue");
+    /// assert_eq!(names.name_for("#0050c8"), "royalblue");
+    /// ```
+    pub fn with_variables<I>(mut self, variables: I) -> NameResolver
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        self.variables = variables
+            .into_iter()
+            .filter_map(|(hex, var)| {
+                let (r, g, b, _) = hex_to_rgba(&hex)?;
+                Some((var.trim_start_matches(['$', '@']).to_string(), (r, g, b)))
+            })
+            .collect();
+        self
+    }
+
+    /// Name colors from `kind`'s table instead of the CSS names. Custom names
+    /// still win for exact matches, but the nearest color always comes from `kind`.
+    ///
+    /// ```
+    /// use hexvar_core::naming::schemes::NamingSchemeKind;
+    /// use hexvar_core::naming::NameResolver;
+    ///
+    /// let names = NameResolver::new([("brand".to_string(), "#0052cc".to_string())]).unwrap();
+    /// let names = names.with_scheme(NamingSchemeKind::Tailwind);
+    /// assert_eq!(names.name_for("#0052cc"), "brand");
+    /// assert_eq!(names.name_for("#3b82f6"), "blue-500");
+    /// ```
+    pub fn with_scheme(mut self, kind: NamingSchemeKind) -> NameResolver {
+        self.palette = PaletteScheme::for_kind(kind);
+        self
+    }
+
+    /// Every named color, custom ones first
+    fn tables(&self) -> impl Iterator<Item = (&str, (u8, u8, u8))> {
+        let builtin = CSS_COLOR_NAMES.iter().filter_map(|(name, hex)| {
+            hex_to_rgba(hex).map(|(r, g, b, _)| (*name, (r, g, b)))
+        });
+        self.custom.iter().map(|(name, rgb)| (name.as_str(), *rgb)).chain(builtin)
+    }
+
+    /// Name of an exact match for `hex`, else of the closest color by Euclidean
+    /// RGB distance (custom names winning ties), else the hex digits. With a
+    /// [scheme](NameResolver::with_scheme), the name of its nearest color.
+    ///
+    /// ```
+    /// use hexvar_core::naming::NameResolver;
+    ///
+    /// let names = NameResolver::new([("brand-blue".to_string(), "#0052cc".to_string())]).unwrap();
+    /// assert_eq!(names.name_for("#0052cc"), "brand-blue");
+    /// assert_eq!(names.name_for("#0050c8"), "brand-blue");
+    /// assert_eq!(names.name_for("#ff6347"), "tomato");
+    /// assert_eq!(NameResolver::default().name_for("#0052cc"), "royalblue");
+    /// ```
+    pub fn name_for(&self, hex: &str) -> String {
+        let Some((r, g, b, _)) = hex_to_rgba(hex) else {
+            return hex.trim_start_matches('#').to_lowercase();
+        };
+        if let Some((name, _)) = self.variables.iter().find(|(_, rgb)| *rgb == (r, g, b)) {
+            return name.replace('_', "-");
+        }
+        if let Some(palette) = &self.palette {
+            return match self.custom.iter().find(|(_, rgb)| *rgb == (r, g, b)) {
+                Some((name, _)) => name.replace('_', "-"),
+                None => palette.resolve(rgb_to_lab(r, g, b)),
+            };
+        }
+        let dist = |(cr, cg, cb): (u8, u8, u8)| {
+            (r as i32 - cr as i32).pow(2) + (g as i32 - cg as i32).pow(2) + (b as i32 - cb as i32).pow(2)
+        };
+        // `min_by_key` keeps the first of equally close colors, so custom names win ties
+        match self.tables().min_by_key(|(_, rgb)| dist(*rgb)) {
+            Some((name, _)) => name.replace('_', "-"),
+            None => hex.trim_start_matches('#').to_lowercase(),
+        }
+    }
+}
+
+impl NamingScheme for NameResolver {
+    fn resolve(&self, lab: Lab) -> String {
+        self.name_for(&lab_to_hex(lab))
+    }
+}
+
+/// Everything a name template can refer to for one canonical color
+#[derive(Clone, Debug)]
+pub struct ResolvedColor {
+    pub prefix: String,
+    /// CSS color name, or the hex digits if there is no close match
+    pub name: String,
+    /// Bare lowercase hex digits, without `#`
+    pub hex: String,
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl ResolvedColor {
+    pub fn new(hex: &str, prefix: &str) -> Self {
+        ResolvedColor::with_names(hex, prefix, &NameResolver::default())
+    }
+
+    /// Like [`ResolvedColor::new`], taking `{name}` from `names`
+    pub fn with_names(hex: &str, prefix: &str, names: &NameResolver) -> Self {
+        let (r, g, b, _) = hex_to_rgba(hex).unwrap_or((0, 0, 0, 255));
+        ResolvedColor {
+            prefix: prefix.to_string(),
+            name: names.name_for(hex),
+            hex: hex.trim_start_matches('#').to_lowercase(),
+            r,
+            g,
+            b,
+        }
+    }
+}
+
+/// Check that every `{placeholder}` in `template` is one of [`PLACEHOLDERS`]
+///
+/// ```
+/// use hexvar_core::naming::validate_template;
+///
+/// assert!(validate_template("brand-{name}-{hex}").is_ok());
+/// assert!(validate_template("{family}-{shade}").is_err());
+/// ```
+pub fn validate_template(template: &str) -> Result<()> {
+    let error = |message: String| HexvarError::NameTemplate { template: template.to_string(), message };
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        let Some(close) = rest[open..].find('}') else {
+            return Err(error("unclosed '{'".to_string()));
+        };
+        let placeholder = &rest[open + 1..open + close];
+        if !PLACEHOLDERS.contains(&placeholder) {
+            return Err(error(format!(
+                "unknown placeholder {{{}}} (expected one of {})",
+                placeholder,
+                PLACEHOLDERS.iter().map(|p| format!("{{{}}}", p)).collect::<Vec<_>>().join(", ")
+            )));
+        }
+        rest = &rest[open + close + 1..];
+    }
+    Ok(())
+}
+
+/// Expand a name template into a CSS custom property name (with the leading `--`).
+/// Templates should be checked with [`validate_template`] first; unknown
+/// placeholders are left as-is.
+///
+/// ```
+/// use hexvar_core::naming::{render_var_name, ResolvedColor};
+///
+/// let color = ResolvedColor::new("#ff6347", "brand");
+/// assert_eq!(render_var_name("{prefix}-{name}", &color), "--brand-tomato");
+/// assert_eq!(render_var_name("c-{r}-{g}-{b}", &color), "--c-255-99-71");
+/// ```
+pub fn render_var_name(template: &str, color: &ResolvedColor) -> String {
+    let name = template
+        .replace("{prefix}", &color.prefix)
+        .replace("{name}", &color.name)
+        .replace("{hex}", &color.hex)
+        .replace("{r}", &color.r.to_string())
+        .replace("{g}", &color.g.to_string())
+        .replace("{b}", &color.b.to_string());
+    format!("--{}", name)
+}
+
+/// Whether variable name `var` was made from hex digits rather than a color
+/// name, i.e. it ends in 6 (or 8, with alpha) hex digits like `--color-1a2b3c`
+///
+/// ```
+/// use hexvar_core::naming::is_hex_derived;
+///
+/// assert!(is_hex_derived("--color-1a2b3c"));
+/// assert!(is_hex_derived("--brand-1A2B3C80"));
+/// assert!(!is_hex_derived("--color-tomato"));
+/// assert!(!is_hex_derived("--color-primary-40"));
+/// ```
+pub fn is_hex_derived(var: &str) -> bool {
+    let last = var.rsplit('-').next().unwrap_or(var);
+    matches!(last.len(), 6 | 8) && last.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Rename every cluster from `template`, with `{name}` from `names`, appending `-2`, `-3`, ... to names
+/// that are already taken. Clusters are named most-used first (by `counts`), then by hex, so the
+/// plain name goes to the most-used one. Returns a warning for each renamed duplicate.
+///
+/// ```
+/// use std::collections::HashMap;
+/// use hexvar_core::naming::{assign_names, NameResolver};
+///
+/// let counts = HashMap::from([("#ff0000".to_string(), 1), ("#ff000080".to_string(), 3)]);
+/// let mut clusters = hexvar_core::cluster_colors(&counts, 10.0);
+/// let warnings = assign_names(&mut clusters, "brand-{name}", "color", &counts, &NameResolver::default());
+/// let names: HashMap<_, _> = clusters.iter().map(|c| (c.hex.as_str(), c.name.as_str())).collect();
+/// assert_eq!(names["#ff000080"], "--brand-red");
+/// assert_eq!(names["#ff0000"], "--brand-red-2");
+/// assert_eq!(warnings.len(), 1);
+/// ```
+pub fn assign_names(
+    clusters: &mut [Cluster],
+    template: &str,
+    prefix: &str,
+    counts: &HashMap<String, u32>,
+    names: &NameResolver,
+) -> Vec<String> {
+    let mut order: Vec<usize> = (0..clusters.len()).collect();
+    order.sort_by_cached_key(|&i| (std::cmp::Reverse(cluster_usage(&clusters[i], counts)), clusters[i].hex.clone()));
+    let mut taken = HashSet::new();
+    let mut warnings = Vec::new();
+    for i in order {
+        let cluster = &mut clusters[i];
+        let base = render_var_name(template, &ResolvedColor::with_names(&cluster.hex, prefix, names));
+        let mut name = base.clone();
+        let mut n = 2;
+        while taken.contains(&name) {
+            name = format!("{}-{}", base, n);
+            n += 1;
+        }
+        if name != base {
+            warnings.push(format!("Duplicate variable name {} for {}, using {}", base, cluster.hex, name));
+        }
+        taken.insert(name.clone());
+        cluster.name = name;
+    }
+    warnings
+}
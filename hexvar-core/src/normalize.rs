@@ -0,0 +1,39 @@
+use crate::convert::{convert_hex, ConvertOptions, HexCase};
+use regex::Regex;
+
+/// Rewrite the digits of every hex literal matched by `re` in `content` to
+/// `case`, keeping short forms short and everything else as written. Returns
+/// the new content and how many literals changed.
+///
+/// ```
+/// use hexvar_core::color::HEX_PATTERN;
+/// use hexvar_core::convert::HexCase;
+/// use hexvar_core::normalize::normalize_content;
+/// use regex::Regex;
+///
+/// let re = Regex::new(HEX_PATTERN).unwrap();
+/// let css = "a { color: #FF0000; background: #ff0000; border-color: #Ff0000 }";
+/// let (out, changed) = normalize_content(css, &re, HexCase::Lower);
+/// assert_eq!(out, "a { color: #ff0000; background: #ff0000; border-color: #ff0000 }");
+/// assert_eq!(changed, 2);
+///
+/// // Short forms and alpha keep their digits, and nothing besides hex codes changes
+/// let (out, changed) = normalize_content("A { COLOR: #aBc; FILL: #1a2B3c80 }", &re, HexCase::Upper);
+/// assert_eq!(out, "A { COLOR: #ABC; FILL: #1A2B3C80 }");
+/// assert_eq!(changed, 2);
+///
+/// let (out, changed) = normalize_content("a { color: #fff }", &re, HexCase::Lower);
+/// assert_eq!((out.as_str(), changed), ("a { color: #fff }", 0));
+/// ```
+pub fn normalize_content(content: &str, re: &Regex, case: HexCase) -> (String, usize) {
+    let opts = ConvertOptions { case, expand_short: false };
+    let mut changed = 0;
+    let out = re.replace_all(content, |caps: &regex::Captures| {
+        let hex = convert_hex(&caps[0], opts);
+        if hex != caps[0] {
+            changed += 1;
+        }
+        hex
+    });
+    (out.into_owned(), changed)
+}
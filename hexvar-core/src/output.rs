@@ -0,0 +1,603 @@
+use crate::cluster::{cluster_usage, Cluster};
+use crate::color::{hex_to_rgba, NEUTRAL_CHROMA};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// Syntax used when writing the variables file
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[serde(rename_all = "lowercase")]
+pub enum VarFormat {
+    /// `:root { --color-x: #hex; }`
+    #[default]
+    Css,
+    /// `$color-x: #hex;`
+    Scss,
+    /// `@color-x: #hex;`
+    Less,
+    /// `export const colorX = '#hex';`
+    Js,
+    /// `export const colorX = '#hex';`
+    Ts,
+}
+
+/// Letter case of the hex values written to the variables file
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum CanonicalCase {
+    /// `#ff6347`
+    #[default]
+    Lower,
+    /// `#FF6347`
+    Upper,
+}
+
+impl CanonicalCase {
+    /// `hex` in this case
+    ///
+    /// ```
+    /// use hexvar_core::output::CanonicalCase;
+    ///
+    /// assert_eq!(CanonicalCase::Upper.apply("#ff6347"), "#FF6347");
+    /// assert_eq!(CanonicalCase::Lower.apply("#FF6347"), "#ff6347");
+    /// ```
+    pub fn apply(self, hex: &str) -> String {
+        match self {
+            CanonicalCase::Lower => hex.to_ascii_lowercase(),
+            CanonicalCase::Upper => hex.to_ascii_uppercase(),
+        }
+    }
+}
+
+/// Order of the variables in the variables file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum CssVarsSort {
+    /// By variable name
+    Alpha,
+    /// Most occurrences of the merged colors first
+    Frequency,
+    /// By CIE L*, dark to light
+    Lightness,
+    /// By hue angle, with neutral grays first from dark to light
+    Hue,
+}
+
+impl CssVarsSort {
+    /// Sort `clusters` in this order, breaking ties by canonical hex. `counts`
+    /// are the occurrence counts [`CssVarsSort::Frequency`] sorts by.
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use hexvar_core::output::CssVarsSort;
+    ///
+    /// let counts = HashMap::from([("#ffffff".to_string(), 1), ("#0000ff".to_string(), 2), ("#ff0000".to_string(), 3)]);
+    /// let mut clusters = hexvar_core::cluster_colors(&counts, 0.0);
+    /// let hexes = |clusters: &[hexvar_core::Cluster]| clusters.iter().map(|c| c.hex.clone()).collect::<Vec<_>>();
+    ///
+    /// CssVarsSort::Alpha.sort(&mut clusters, &counts);
+    /// assert_eq!(hexes(&clusters), ["#0000ff", "#ff0000", "#ffffff"]);
+    /// CssVarsSort::Lightness.sort(&mut clusters, &counts);
+    /// assert_eq!(hexes(&clusters), ["#0000ff", "#ff0000", "#ffffff"]);
+    /// CssVarsSort::Frequency.sort(&mut clusters, &counts);
+    /// assert_eq!(hexes(&clusters), ["#ff0000", "#0000ff", "#ffffff"]);
+    /// CssVarsSort::Hue.sort(&mut clusters, &counts);
+    /// assert_eq!(hexes(&clusters), ["#ffffff", "#ff0000", "#0000ff"]);
+    /// ```
+    pub fn sort(self, clusters: &mut [Cluster], counts: &HashMap<String, u32>) {
+        match self {
+            CssVarsSort::Alpha => clusters.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.hex.cmp(&b.hex))),
+            CssVarsSort::Frequency => {
+                clusters.sort_by_cached_key(|c| (std::cmp::Reverse(cluster_usage(c, counts)), c.hex.clone()))
+            }
+            CssVarsSort::Lightness => clusters.sort_by(|a, b| a.lab.l.total_cmp(&b.lab.l).then_with(|| a.hex.cmp(&b.hex))),
+            CssVarsSort::Hue => {
+                // Grays have no meaningful hue, so they come first as one group
+                let key = |c: &Cluster| {
+                    let chroma = c.lab.a.hypot(c.lab.b);
+                    let hue =
+                        if chroma < NEUTRAL_CHROMA { -1.0 } else { c.lab.b.atan2(c.lab.a).to_degrees().rem_euclid(360.0) };
+                    (hue, c.lab.l)
+                };
+                clusters.sort_by(|a, b| {
+                    let (ka, kb) = (key(a), key(b));
+                    ka.0.total_cmp(&kb.0).then(ka.1.total_cmp(&kb.1)).then_with(|| a.hex.cmp(&b.hex))
+                })
+            }
+        }
+    }
+}
+
+/// Variable name without the leading `--` and the default `color-` prefix,
+/// e.g. `tomato` for `--color-tomato` and `brand-red` for `--brand-red`
+pub(crate) fn bare_name(var: &str) -> &str {
+    var.strip_prefix("--color-").unwrap_or_else(|| var.trim_start_matches("--"))
+}
+
+/// Convert a CSS custom property name (`--color-dark-red`) to camelCase (`colorDarkRed`)
+pub fn camel_case(var: &str) -> String {
+    let mut out = String::new();
+    for (i, part) in var.trim_start_matches("--").split('-').filter(|p| !p.is_empty()).enumerate() {
+        if i == 0 {
+            out.push_str(part);
+        } else {
+            let mut chars = part.chars();
+            if let Some(first) = chars.next() {
+                out.extend(first.to_uppercase());
+                out.push_str(chars.as_str());
+            }
+        }
+    }
+    out
+}
+
+/// Render one variable per canonical cluster in the given syntax
+///
+/// ```
+/// use std::collections::HashMap;
+/// use hexvar_core::VarFormat;
+///
+/// let counts = HashMap::from([("#ff6347".to_string(), 1)]);
+/// let clusters = hexvar_core::cluster_colors(&counts, 10.0);
+/// let scss = hexvar_core::generate_css_vars(&clusters, VarFormat::Scss);
+/// assert_eq!(scss, "$color-tomato: #ff6347;\n");
+/// ```
+pub fn generate_css_vars(clusters: &[Cluster], format: VarFormat) -> String {
+    generate_css_vars_with(clusters, format, &CssVarsOptions::default())
+}
+
+/// Selector of the CSS variables block written by default
+pub const DEFAULT_CSS_VARS_SELECTOR: &str = ":root";
+
+/// Where [`generate_css_vars_with`] puts the variables of a CSS file, and the
+/// case of the values. Other formats have no selectors and ignore those.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CssVarsOptions {
+    /// Selector of the block holding the variables, e.g. `:root` or `.theme-default`
+    pub selector: String,
+    /// Selector of a second block inside `@media (prefers-color-scheme: dark)`
+    pub dark_selector: Option<String>,
+    /// Values for the dark block keyed by variable name or canonical hex code;
+    /// variables missing from it repeat their light value
+    pub dark_colors: HashMap<String, String>,
+    /// Case of the hex values, in every format
+    pub case: CanonicalCase,
+    /// Occurrence counts to end each variable merging several colors with a
+    /// comment listing them, e.g. `/* merged: #1a2c3d (12), #1b2d3e (3) */`
+    pub merged_counts: Option<HashMap<String, u32>>,
+}
+
+impl Default for CssVarsOptions {
+    fn default() -> Self {
+        CssVarsOptions {
+            selector: DEFAULT_CSS_VARS_SELECTOR.to_string(),
+            dark_selector: None,
+            dark_colors: HashMap::new(),
+            case: CanonicalCase::default(),
+            merged_counts: None,
+        }
+    }
+}
+
+/// ` /* merged: ... */` listing the colors merged into `cluster` besides its
+/// canonical one, most used first, or nothing if there are none
+fn merged_comment(cluster: &Cluster, counts: &HashMap<String, u32>, case: CanonicalCase) -> String {
+    let mut merged: Vec<(&String, u32)> = cluster
+        .members
+        .iter()
+        .filter(|m| **m != cluster.hex)
+        .map(|m| (m, counts.get(m).copied().unwrap_or(0)))
+        .collect();
+    if merged.is_empty() {
+        return String::new();
+    }
+    merged.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    let list: Vec<String> = merged.iter().map(|(hex, n)| format!("{} ({})", case.apply(hex), n)).collect();
+    format!(" /* merged: {} */", list.join(", "))
+}
+
+/// Check that `selector` could be a CSS selector: not blank, and without the
+/// braces or semicolons that would break out of the block
+///
+/// ```
+/// use hexvar_core::output::validate_selector;
+///
+/// assert!(validate_selector(".theme-default").is_ok());
+/// assert!(validate_selector("html[data-theme=\"light\"]").is_ok());
+/// assert!(validate_selector(" ").is_err());
+/// assert!(validate_selector("a { color: red }").is_err());
+/// ```
+pub fn validate_selector(selector: &str) -> Result<(), String> {
+    if selector.trim().is_empty() {
+        return Err("selector is empty".to_string());
+    }
+    if let Some(c) = selector.chars().find(|c| matches!(c, '{' | '}' | ';')) {
+        return Err(format!("selector contains '{}'", c));
+    }
+    Ok(())
+}
+
+/// Like [`generate_css_vars`], with the selectors and dark-mode block in `opts`
+///
+/// ```
+/// use std::collections::HashMap;
+/// use hexvar_core::output::{generate_css_vars_with, CanonicalCase, CssVarsOptions};
+/// use hexvar_core::VarFormat;
+///
+/// let counts = HashMap::from([("#ff6347".to_string(), 1)]);
+/// let clusters = hexvar_core::cluster_colors(&counts, 10.0);
+/// let opts = CssVarsOptions {
+///     selector: "html".to_string(),
+///     dark_selector: Some("html".to_string()),
+///     dark_colors: HashMap::from([("--color-tomato".to_string(), "#aa3322".to_string())]),
+///     case: CanonicalCase::Lower,
+///     merged_counts: None,
+/// };
+/// assert_eq!(
+///     generate_css_vars_with(&clusters, VarFormat::Css, &opts),
+///     "html {\n    --color-tomato: #ff6347;\n}\n\n@media (prefers-color-scheme: dark) {\n    html {\n        --color-tomato: #aa3322;\n    }\n}\n"
+/// );
+/// let upper = CssVarsOptions { case: CanonicalCase::Upper, ..CssVarsOptions::default() };
+/// assert_eq!(generate_css_vars_with(&clusters, VarFormat::Scss, &upper), "$color-tomato: #FF6347;\n");
+///
+/// // Colors merged into a variable are listed after it; single colors get no comment
+/// let primary = hexvar_core::Cluster {
+///     hex: "#1a2b3c".to_string(),
+///     lab: hexvar_core::color::rgb_to_lab(0x1a, 0x2b, 0x3c),
+///     alpha: 255,
+///     name: "--color-primary".to_string(),
+///     members: ["#1a2b3c", "#1b2d3e", "#1a2c3d"].map(String::from).to_vec(),
+/// };
+/// let counts = HashMap::from([("#ff6347".to_string(), 1), ("#1a2b3c".to_string(), 20), ("#1a2c3d".to_string(), 12), ("#1b2d3e".to_string(), 3)]);
+/// let commented = CssVarsOptions { merged_counts: Some(counts), ..CssVarsOptions::default() };
+/// assert_eq!(
+///     generate_css_vars_with(&[primary, clusters[0].clone()], VarFormat::Css, &commented),
+///     ":root {\n    --color-primary: #1a2b3c; /* merged: #1a2c3d (12), #1b2d3e (3) */\n    --color-tomato: #ff6347;\n}\n"
+/// );
+/// ```
+pub fn generate_css_vars_with(clusters: &[Cluster], format: VarFormat, opts: &CssVarsOptions) -> String {
+    let mut out = String::new();
+    if format == VarFormat::Css {
+        out.push_str(&format!("{} {{\n", opts.selector.trim()));
+    }
+    for cluster in clusters {
+        let Cluster { hex, name: var, .. } = cluster;
+        let hex = opts.case.apply(hex);
+        let bare = var.trim_start_matches("--");
+        let line = match format {
+            VarFormat::Css => format!("    {}: {};", var, hex),
+            VarFormat::Scss => format!("${}: {};", bare, hex),
+            VarFormat::Less => format!("@{}: {};", bare, hex),
+            VarFormat::Js | VarFormat::Ts => format!("export const {} = '{}';", camel_case(var), hex),
+        };
+        out.push_str(&line);
+        if let Some(counts) = &opts.merged_counts {
+            out.push_str(&merged_comment(cluster, counts, opts.case));
+        }
+        out.push('\n');
+    }
+    if format == VarFormat::Css {
+        out.push_str("}\n");
+        if let Some(dark) = &opts.dark_selector {
+            out.push_str(&format!("\n@media (prefers-color-scheme: dark) {{\n    {} {{\n", dark.trim()));
+            for Cluster { hex, name: var, .. } in clusters {
+                let light = opts.case.apply(hex);
+                let value = opts.dark_colors.get(var).or_else(|| opts.dark_colors.get(hex)).unwrap_or(&light);
+                out.push_str(&format!("        {}: {};\n", var, value));
+            }
+            out.push_str("    }\n}\n");
+        }
+    }
+    out
+}
+
+/// Render the TypeScript declarations matching the `--format ts` output of
+/// [`generate_css_vars`], one `export declare const` per canonical cluster
+///
+/// ```
+/// use std::collections::HashMap;
+/// use hexvar_core::output::render_ts_declaration;
+///
+/// let counts = HashMap::from([("#ff6347".to_string(), 1)]);
+/// let clusters = hexvar_core::cluster_colors(&counts, 10.0);
+/// assert_eq!(render_ts_declaration(&clusters), "export declare const colorTomato: string;\n");
+/// ```
+pub fn render_ts_declaration(clusters: &[Cluster]) -> String {
+    clusters.iter().map(|c| format!("export declare const {}: string;\n", camel_case(&c.name))).collect()
+}
+
+/// Path of the declaration file written next to a TypeScript variables file,
+/// e.g. `colours.d.ts` for `colours.ts`
+///
+/// ```
+/// use hexvar_core::output::ts_declaration_path;
+///
+/// assert_eq!(ts_declaration_path("out/colours.ts"), "out/colours.d.ts");
+/// assert_eq!(ts_declaration_path("colours"), "colours.d.ts");
+/// ```
+pub fn ts_declaration_path(path: &str) -> String {
+    let stem = path.strip_suffix(".ts").or_else(|| path.strip_suffix(".js")).unwrap_or(path);
+    format!("{}.d.ts", stem)
+}
+
+/// Escape text for inclusion in HTML content or attribute values
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+const HTML_STYLE: &str = "body { font-family: system-ui, sans-serif; margin: 2rem; background: #fafafa; color: #222; }
+h1 { font-size: 1.5rem; }
+.cluster { background: #fff; border: 1px solid #ddd; border-radius: 8px; padding: 1rem; margin-bottom: 1rem; }
+.header { display: flex; align-items: center; gap: 1rem; }
+.swatch { width: 4rem; height: 4rem; border-radius: 6px; border: 1px solid #ccc; }
+.name { font-weight: 600; font-family: monospace; font-size: 1.1rem; }
+.meta { color: #666; font-family: monospace; }
+.members { display: flex; flex-wrap: wrap; gap: 0.5rem; margin-top: 0.75rem; }
+.member { display: flex; align-items: center; gap: 0.35rem; font-family: monospace; font-size: 0.85rem; }
+.member .swatch { width: 1.25rem; height: 1.25rem; border-radius: 3px; }
+";
+
+/// Render a self-contained HTML page with one swatch block per cluster,
+/// ordered from most-used to least-used.
+pub fn render_html_report(
+    clusters: &[Cluster],
+    canonical_map: &BTreeMap<String, Vec<String>>,
+    counts: &HashMap<String, u32>,
+) -> String {
+    let members_of = |hex: &String| canonical_map.get(hex).cloned().unwrap_or_default();
+    let usage = |hex: &String| -> u32 { members_of(hex).iter().filter_map(|m| counts.get(m)).sum() };
+    let mut order: Vec<usize> = (0..clusters.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(usage(&clusters[i].hex)));
+
+    let mut html = String::from("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>hexvar report</title>\n<style>\n");
+    html.push_str(HTML_STYLE);
+    html.push_str("</style>\n</head>\n<body>\n");
+    html.push_str(&format!("<h1>hexvar report: {} canonical colors</h1>\n", clusters.len()));
+    for i in order {
+        let Cluster { hex, name, .. } = &clusters[i];
+        let hex_html = html_escape(hex);
+        html.push_str("<div class=\"cluster\">\n<div class=\"header\">\n");
+        html.push_str(&format!("<div class=\"swatch\" style=\"background: {}\"></div>\n", hex_html));
+        html.push_str(&format!(
+            "<div><div class=\"name\">{}</div><div class=\"meta\">{} &middot; {} occurrences</div></div>\n",
+            html_escape(name),
+            hex_html,
+            usage(hex)
+        ));
+        html.push_str("</div>\n<div class=\"members\">\n");
+        let mut members = members_of(hex);
+        members.sort_by_key(|m| std::cmp::Reverse(counts.get(m).copied().unwrap_or(0)));
+        for member in members {
+            let member_html = html_escape(&member);
+            let count = counts.get(&member).map(|n| format!(" ({})", n)).unwrap_or_default();
+            html.push_str(&format!(
+                "<div class=\"member\"><div class=\"swatch\" style=\"background: {}\"></div>{}{}</div>\n",
+                member_html, member_html, count
+            ));
+        }
+        html.push_str("</div>\n</div>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/// ANSI true-color background block for a hex code, `width` cells wide
+fn ansi_swatch(hex: &str, width: usize) -> String {
+    let (r, g, b, _) = hex_to_rgba(hex).unwrap_or((0, 0, 0, 255));
+    format!("\x1b[48;2;{};{};{}m{}\x1b[0m", r, g, b, " ".repeat(width))
+}
+
+/// Render the `limit` most-used clusters as terminal lines, one per cluster,
+/// with its merged colors to the right. With `ansi` unset, prints the same
+/// lines without swatches (for `NO_COLOR` or non-terminal output).
+///
+/// ```
+/// use std::collections::{BTreeMap, HashMap};
+/// use hexvar_core::output::render_terminal_swatches;
+///
+/// let counts = HashMap::from([("#ff0000".to_string(), 3)]);
+/// let clusters = hexvar_core::cluster_colors(&counts, 10.0);
+/// let map = BTreeMap::from([("#ff0000".to_string(), vec!["#ff0000".to_string()])]);
+/// let plain = render_terminal_swatches(&clusters, &map, &counts, 20, false);
+/// assert_eq!(plain, "#ff0000 --color-red (3)\n");
+/// let ansi = render_terminal_swatches(&clusters, &map, &counts, 20, true);
+/// assert!(ansi.starts_with("\x1b[48;2;255;0;0m   \x1b[0m #ff0000"));
+/// ```
+pub fn render_terminal_swatches(
+    clusters: &[Cluster],
+    canonical_map: &BTreeMap<String, Vec<String>>,
+    counts: &HashMap<String, u32>,
+    limit: usize,
+    ansi: bool,
+) -> String {
+    let members_of = |hex: &String| canonical_map.get(hex).cloned().unwrap_or_default();
+    let usage = |hex: &String| -> u32 { members_of(hex).iter().filter_map(|m| counts.get(m)).sum() };
+    let mut order: Vec<usize> = (0..clusters.len()).collect();
+    order.sort_by_key(|&i| (std::cmp::Reverse(usage(&clusters[i].hex)), clusters[i].hex.clone()));
+
+    let mut out = String::new();
+    if clusters.len() > limit {
+        out.push_str(&format!("Top {} of {} canonical colors:\n", limit, clusters.len()));
+    }
+    for i in order.into_iter().take(limit) {
+        let Cluster { hex, name, .. } = &clusters[i];
+        if ansi {
+            out.push_str(&ansi_swatch(hex, 3));
+            out.push(' ');
+        }
+        out.push_str(&format!("{} {} ({})", hex, name, usage(hex)));
+        let mut members: Vec<String> = members_of(hex).into_iter().filter(|m| m != hex).collect();
+        members.sort_by_key(|m| std::cmp::Reverse(counts.get(m).copied().unwrap_or(0)));
+        if !members.is_empty() {
+            out.push(' ');
+        }
+        for member in members {
+            out.push(' ');
+            if ansi {
+                out.push_str(&ansi_swatch(&member, 1));
+            }
+            out.push_str(&member);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// A single color token in the W3C Design Tokens format
+pub struct DesignToken {
+    pub name: String,
+    pub value: String,
+    pub aliases: Vec<String>,
+}
+
+/// W3C Design Tokens document: `{ "color": { "<name>": { "$value", "$type", "$description" } } }`
+/// See https://design-tokens.github.io/community-group/format/
+pub struct DesignTokensReport {
+    pub tokens: Vec<DesignToken>,
+}
+
+impl DesignTokensReport {
+    /// Build tokens from the clusters and the merged spellings of each
+    pub fn new(clusters: &[Cluster], canonical_map: &BTreeMap<String, Vec<String>>) -> Self {
+        let tokens = clusters
+            .iter()
+            .map(|Cluster { hex, name: var, .. }| {
+                let mut aliases: Vec<String> = canonical_map
+                    .get(hex)
+                    .map(|members| members.iter().filter(|m| *m != hex).cloned().collect())
+                    .unwrap_or_default();
+                aliases.sort();
+                DesignToken {
+                    name: bare_name(var).to_string(),
+                    value: hex.clone(),
+                    aliases,
+                }
+            })
+            .collect();
+        DesignTokensReport { tokens }
+    }
+}
+
+impl Serialize for DesignToken {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("$value", &self.value)?;
+        map.serialize_entry("$type", "color")?;
+        if !self.aliases.is_empty() {
+            map.serialize_entry("$description", &format!("Merged aliases: {}", self.aliases.join(", ")))?;
+        }
+        map.end()
+    }
+}
+
+impl Serialize for DesignTokensReport {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        struct Group<'a>(&'a [DesignToken]);
+        impl Serialize for Group<'_> {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let mut map = serializer.serialize_map(Some(self.0.len()))?;
+                for token in self.0 {
+                    map.serialize_entry(&token.name, token)?;
+                }
+                map.end()
+            }
+        }
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry("color", &Group(&self.tokens))?;
+        map.end()
+    }
+}
+
+/// Renders clusters as a Tailwind `theme.colors` config module:
+/// `module.exports = { colors: { tomato: '#ff6347', ... } }`.
+/// A cluster with merged aliases becomes a nested object whose `DEFAULT` is
+/// the canonical color, with one key per alias (its hex digits).
+///
+/// ```
+/// use std::collections::HashMap;
+/// use hexvar_core::output::TailwindRenderer;
+///
+/// let counts = HashMap::from([("#ff0000".to_string(), 2), ("#fe0000".to_string(), 1)]);
+/// let clusters = hexvar_core::cluster_colors(&counts, 10.0);
+/// let js = TailwindRenderer::new(clusters).render();
+/// assert!(js.contains("DEFAULT: '#ff0000'") || js.contains("DEFAULT: '#fe0000'"));
+/// assert!(js.starts_with("module.exports = {\n  colors: {\n    red: {\n"));
+/// ```
+pub struct TailwindRenderer {
+    clusters: Vec<Cluster>,
+}
+
+impl TailwindRenderer {
+    pub fn new(clusters: Vec<Cluster>) -> Self {
+        TailwindRenderer { clusters }
+    }
+
+    /// Quote object keys that aren't valid JS identifiers, e.g. bare hex digits
+    fn js_key(key: &str) -> String {
+        if key.starts_with(|c: char| c.is_ascii_digit()) {
+            format!("'{}'", key)
+        } else {
+            key.to_string()
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::from("module.exports = {\n  colors: {\n");
+        for Cluster { hex, name, members, .. } in &self.clusters {
+            let key = Self::js_key(&camel_case(bare_name(name)));
+            let mut aliases: Vec<&String> = members.iter().filter(|m| *m != hex).collect();
+            if aliases.is_empty() {
+                out.push_str(&format!("    {}: '{}',\n", key, hex));
+                continue;
+            }
+            aliases.sort();
+            out.push_str(&format!("    {}: {{\n      DEFAULT: '{}',\n", key, hex));
+            for alias in aliases {
+                let alias_key = Self::js_key(&alias.trim_start_matches('#').to_lowercase());
+                out.push_str(&format!("      {}: '{}',\n", alias_key, alias));
+            }
+            out.push_str("    },\n");
+        }
+        out.push_str("  },\n};\n");
+        out
+    }
+}
+
+/// Render the canonical variables as a config for `postcss-custom-properties`,
+/// as a CommonJS module or, with `esm`, an ES module
+///
+/// ```
+/// use std::collections::HashMap;
+/// use hexvar_core::output::PostcssRenderer;
+///
+/// let counts = HashMap::from([("#ff6347".to_string(), 1)]);
+/// let clusters = hexvar_core::cluster_colors(&counts, 10.0);
+/// let js = PostcssRenderer::new(clusters.clone(), false).render();
+/// assert_eq!(js, "module.exports = {\n  customProperties: {\n    '--color-tomato': '#ff6347',\n  },\n};\n");
+/// let js = PostcssRenderer::new(clusters, true).render();
+/// assert!(js.starts_with("export default {\n  customProperties: {\n"));
+/// ```
+pub struct PostcssRenderer {
+    clusters: Vec<Cluster>,
+    esm: bool,
+}
+
+impl PostcssRenderer {
+    pub fn new(clusters: Vec<Cluster>, esm: bool) -> Self {
+        PostcssRenderer { clusters, esm }
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::from(if self.esm { "export default {\n" } else { "module.exports = {\n" });
+        out.push_str("  customProperties: {\n");
+        for Cluster { hex, name, .. } in &self.clusters {
+            out.push_str(&format!("    '{}': '{}',\n", name, hex));
+        }
+        out.push_str("  },\n};\n");
+        out
+    }
+}
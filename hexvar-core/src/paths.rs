@@ -0,0 +1,422 @@
+use crate::error::{HexvarError, Result};
+use crate::scanner::tailwind_config::is_tailwind_config;
+use glob::glob;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use tracing::{debug, trace};
+
+/// File extensions scanned and rewritten by default
+pub const SUPPORTED_EXTENSIONS: &[&str] =
+    &["css", "scss", "sass", "less", "styl", "vue", "astro", "svelte", "html", "svg", "js", "jsx", "ts", "tsx"];
+
+/// Common build output directories that are always skipped
+pub const OUTPUT_DIRS: &[&str] = &[
+    "node_modules", "dist", "build", "out", ".next", ".vercel", ".cache", "coverage", "target"
+];
+
+/// Project-level ignore file with gitignore syntax, always honored when present
+pub const HEXVAR_IGNORE_FILE: &str = ".hexvarignore";
+
+/// Pattern that stands for standard input instead of files, as in `hexvar scan -`
+pub const STDIN_PATTERN: &str = "-";
+
+/// Name standard input is reported under
+pub const STDIN_NAME: &str = "<stdin>";
+
+/// Order files are scanned in, so reports don't depend on the filesystem
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum FileOrder {
+    /// Alphabetical by path
+    #[default]
+    Alpha,
+    /// Most recently modified first
+    Mtime,
+    /// Largest first
+    Size,
+}
+
+/// Sort `paths` in `order`. Ties, and files whose metadata can't be read, fall
+/// back to alphabetical order so the result is always the same.
+///
+/// ```
+/// use std::path::PathBuf;
+/// use hexvar_core::paths::{sort_paths, FileOrder};
+///
+/// let dir = std::env::temp_dir().join("hexvar_sort_paths_doc");
+/// std::fs::create_dir_all(&dir).unwrap();
+/// let (small, large) = (dir.join("b.css"), dir.join("a.css"));
+/// std::fs::write(&small, "a{}").unwrap();
+/// std::fs::write(&large, "a { color: #fff }").unwrap();
+/// let mut paths = vec![small.clone(), large.clone()];
+/// sort_paths(&mut paths, FileOrder::Alpha);
+/// assert_eq!(paths, [large.clone(), small.clone()]);
+/// sort_paths(&mut paths, FileOrder::Size);
+/// assert_eq!(paths, [large, small]);
+/// ```
+pub fn sort_paths(paths: &mut [PathBuf], order: FileOrder) {
+    let metadata = |path: &Path| fs::metadata(path).ok();
+    match order {
+        FileOrder::Alpha => paths.sort_by_cached_key(|p| p.to_string_lossy().into_owned()),
+        FileOrder::Mtime => paths.sort_by_cached_key(|p| {
+            let mtime = metadata(p).and_then(|m| m.modified().ok());
+            (std::cmp::Reverse(mtime), p.to_string_lossy().into_owned())
+        }),
+        FileOrder::Size => paths.sort_by_cached_key(|p| {
+            let size = metadata(p).map_or(0, |m| m.len());
+            (std::cmp::Reverse(size), p.to_string_lossy().into_owned())
+        }),
+    }
+}
+
+/// Whether `path` is the [`STDIN_PATTERN`] placeholder for standard input
+pub fn is_stdin(path: &Path) -> bool {
+    path.as_os_str() == STDIN_PATTERN
+}
+
+/// How `path` is named in reports: its display form, or [`STDIN_NAME`]
+///
+/// ```
+/// use std::path::Path;
+/// use hexvar_core::paths::display_name;
+///
+/// assert_eq!(display_name(Path::new("src/app.css")), "src/app.css");
+/// assert_eq!(display_name(Path::new("-")), "<stdin>");
+/// ```
+pub fn display_name(path: &Path) -> String {
+    if is_stdin(path) {
+        STDIN_NAME.to_string()
+    } else {
+        path.display().to_string()
+    }
+}
+
+/// Read a collected path, taking standard input for [`STDIN_PATTERN`]
+pub fn read_source(path: &Path) -> io::Result<String> {
+    if !is_stdin(path) {
+        return fs::read_to_string(path);
+    }
+    let mut content = String::new();
+    io::stdin().read_to_string(&mut content)?;
+    Ok(content)
+}
+
+/// Read a collected path as raw bytes, taking standard input for [`STDIN_PATTERN`]
+pub fn read_source_bytes(path: &Path) -> io::Result<Vec<u8>> {
+    if !is_stdin(path) {
+        return fs::read(path);
+    }
+    let mut content = Vec::new();
+    io::stdin().read_to_end(&mut content)?;
+    Ok(content)
+}
+
+/// The bytes of a source file, read into memory or mapped from disk
+pub enum SourceBytes {
+    Read(Vec<u8>),
+    Mapped(memmap2::Mmap),
+}
+
+impl std::ops::Deref for SourceBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            SourceBytes::Read(bytes) => bytes,
+            SourceBytes::Mapped(map) => map,
+        }
+    }
+}
+
+/// Like [`read_source_bytes`], but map the file read-only instead of copying it
+/// onto the heap, which saves an allocation per file on large bundled
+/// stylesheets. Standard input and empty files, which can't be mapped, are read.
+///
+/// ```
+/// let path = std::env::temp_dir().join("hexvar-map-source.css");
+/// std::fs::write(&path, "a { color: #fff; }").unwrap();
+/// let bytes = hexvar_core::paths::map_source_bytes(&path).unwrap();
+/// assert_eq!(&bytes[..], b"a { color: #fff; }");
+/// ```
+pub fn map_source_bytes(path: &Path) -> io::Result<SourceBytes> {
+    if is_stdin(path) {
+        return read_source_bytes(path).map(SourceBytes::Read);
+    }
+    let file = fs::File::open(path)?;
+    if file.metadata()?.len() == 0 {
+        return Ok(SourceBytes::Read(Vec::new()));
+    }
+    // SAFETY: the map is only read, and dropped once the file is scanned. A file
+    // truncated by another process meanwhile can still fault, which is the
+    // tradeoff --mmap asks for.
+    unsafe { memmap2::Mmap::map(&file) }.map(SourceBytes::Mapped)
+}
+
+/// Replace the contents of `path` without ever leaving it half-written: the
+/// new contents go to a temporary file in the same directory, which is synced
+/// to disk and then renamed over `path`. The file keeps its permissions, and a
+/// symlink keeps pointing at the file it names.
+///
+/// ```
+/// let path = std::env::temp_dir().join("hexvar-write-atomic.css");
+/// std::fs::write(&path, "a { color: #fff; }").unwrap();
+/// hexvar_core::paths::write_atomic(&path, "a { color: var(--color-white); }").unwrap();
+/// assert_eq!(std::fs::read_to_string(&path).unwrap(), "a { color: var(--color-white); }");
+/// ```
+pub fn write_atomic(path: &Path, contents: &str) -> Result<()> {
+    let io_error = |e: io::Error| HexvarError::io(path, e);
+    // Replace the file a symlink points to, not the symlink itself
+    let target = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let dir = target.parent().filter(|d| !d.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let mut tmp = tempfile::NamedTempFile::new_in(dir).map_err(io_error)?;
+    tmp.write_all(contents.as_bytes()).map_err(io_error)?;
+    if let Ok(meta) = fs::metadata(&target) {
+        tmp.as_file().set_permissions(meta.permissions()).map_err(io_error)?;
+    }
+    tmp.as_file().sync_all().map_err(io_error)?;
+    tmp.persist(&target).map_err(|e| io_error(e.error))?;
+    Ok(())
+}
+
+/// Which files [`collect_paths_with`] accepts, beyond the built-in extension
+/// and output directory rules
+#[derive(Clone, Debug, Default)]
+pub struct PathFilter {
+    /// Gitignore-syntax patterns to skip, relative to the current directory
+    pub ignore: Vec<String>,
+    /// Extra extensions to accept, with or without a leading dot
+    pub extra_exts: Vec<String>,
+    /// Also honor `.gitignore` files
+    pub use_gitignore: bool,
+    /// Fail with [`HexvarError::TooManyFiles`] once more than this many paths match
+    pub max_files: Option<usize>,
+    /// Skip files whose [`path_depth`] is greater than this
+    pub max_depth: Option<usize>,
+    /// Match the patterns against only these files, relative to the current
+    /// directory, instead of expanding them against the filesystem
+    pub only: Option<Vec<PathBuf>>,
+}
+
+/// Decides whether a path is excluded by `--ignore` patterns or by the
+/// `.hexvarignore` / `.gitignore` files in its directory and every parent up
+/// to the repository root. Deeper files take precedence, so a `!negation` in
+/// `src/.gitignore` can re-include something the root file excludes.
+pub struct IgnoreMatcher {
+    cwd: PathBuf,
+    explicit: Gitignore,
+    file_names: Vec<&'static str>,
+    /// Parsed ignore files per directory, in `file_names` order
+    dirs: HashMap<PathBuf, Vec<Gitignore>>,
+}
+
+impl IgnoreMatcher {
+    /// Build a matcher for `filter` rooted at the current directory
+    pub fn new(filter: &PathFilter) -> Result<Self> {
+        let cwd = std::env::current_dir()
+            .and_then(|d| d.canonicalize())
+            .map_err(|e| HexvarError::io(".", e))?;
+        let mut builder = GitignoreBuilder::new(&cwd);
+        for pat in &filter.ignore {
+            builder
+                .add_line(None, pat)
+                .map_err(|source| HexvarError::IgnorePattern { pattern: pat.clone(), source })?;
+        }
+        let explicit = builder.build().map_err(|source| HexvarError::IgnorePattern {
+            pattern: filter.ignore.join(", "),
+            source,
+        })?;
+        let mut file_names = vec![HEXVAR_IGNORE_FILE];
+        if filter.use_gitignore {
+            file_names.push(".gitignore");
+        }
+        Ok(IgnoreMatcher { cwd, explicit, file_names, dirs: HashMap::new() })
+    }
+
+    fn matchers_for(&mut self, dir: &Path) -> &[Gitignore] {
+        let file_names = &self.file_names;
+        self.dirs.entry(dir.to_path_buf()).or_insert_with(|| {
+            file_names
+                .iter()
+                .map(|name| dir.join(name))
+                .filter(|file| file.is_file())
+                .map(|file| Gitignore::new(file).0)
+                .collect()
+        })
+    }
+
+    /// Whether `path` (relative to the current directory, or absolute) should be skipped
+    pub fn is_ignored(&mut self, path: &Path) -> bool {
+        let abs = path.canonicalize().unwrap_or_else(|_| self.cwd.join(path));
+        if abs.starts_with(self.explicit.path())
+            && self.explicit.matched_path_or_any_parents(&abs, false).is_ignore()
+        {
+            return true;
+        }
+        for dir in abs.ancestors().skip(1) {
+            for matcher in self.matchers_for(dir) {
+                match matcher.matched_path_or_any_parents(&abs, false) {
+                    Match::Ignore(_) => return true,
+                    Match::Whitelist(_) => return false,
+                    Match::None => {}
+                }
+            }
+            // Ignore files above the repository root don't apply
+            if dir.join(".git").exists() {
+                break;
+            }
+        }
+        false
+    }
+}
+
+/// Collect all files matching `patterns` with a scannable extension, skipping
+/// anything matching an ignore pattern or inside a common output directory
+pub fn collect_paths(patterns: &[String], ignore: &[String]) -> Result<Vec<PathBuf>> {
+    let filter = PathFilter { ignore: ignore.to_vec(), ..PathFilter::default() };
+    collect_paths_with(patterns, &filter)
+}
+
+/// Expand one glob pattern, keeping files with an accepted extension outside
+/// the common output directories. With `only`, the pattern is matched against
+/// those files instead, keeping the ones that still exist.
+fn expand_pattern(pat: &str, extra: &[&str], only: Option<&[PathBuf]>) -> Result<Vec<PathBuf>> {
+    let glob_error = |source| HexvarError::Glob { pattern: pat.to_string(), source };
+    let entries: Vec<PathBuf> = match only {
+        Some(files) => {
+            let pattern = glob::Pattern::new(pat.trim_start_matches("./")).map_err(glob_error)?;
+            // `*` stops at `/` here too, as it does when globbing the filesystem
+            let opts = glob::MatchOptions { require_literal_separator: true, ..glob::MatchOptions::new() };
+            // Absolute patterns are matched against absolute paths
+            let base = if Path::new(pat).is_absolute() {
+                std::env::current_dir().map_err(|e| HexvarError::io(".", e))?
+            } else {
+                PathBuf::new()
+            };
+            files.iter().map(|f| base.join(f)).filter(|f| pattern.matches_path_with(f, opts) && f.is_file()).collect()
+        }
+        None => glob(pat).map_err(glob_error)?.flatten().collect(),
+    };
+    let mut paths = Vec::new();
+    for path in entries {
+        // Always ignore anything in common output directories
+        if path.components().any(|c| {
+            let s = c.as_os_str().to_string_lossy();
+            OUTPUT_DIRS.contains(&s.as_ref())
+        }) {
+            debug!("Skipping {}: inside an output directory", path.display());
+            continue;
+        }
+        // Only include files with allowed extensions, and Tailwind configs of any flavor
+        if is_tailwind_config(&path) {
+            paths.push(path);
+            continue;
+        }
+        if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+            if !SUPPORTED_EXTENSIONS.contains(&ext) && !extra.contains(&ext) {
+                trace!("Skipping {}: unsupported extension", path.display());
+                continue;
+            }
+        } else {
+            trace!("Skipping {}: no extension", path.display());
+            continue;
+        }
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+/// `path` relative to `base`, for reports that read the same from any working
+/// directory. Relative paths are taken from `cwd`, and `.` and `..` are
+/// resolved without touching the filesystem. Paths outside `base` are returned
+/// as they are.
+///
+/// ```
+/// use std::path::Path;
+/// use hexvar_core::paths::relative_to;
+///
+/// let (base, cwd) = (Path::new("/home/ci/project"), Path::new("/home/ci/project/web"));
+/// assert_eq!(relative_to("/home/ci/project/src/app.css", base, cwd), "src/app.css");
+/// assert_eq!(relative_to("./theme.css", base, cwd), "web/theme.css");
+/// assert_eq!(relative_to("../lib/a.css", base, cwd), "lib/a.css");
+/// assert_eq!(relative_to("/tmp/other.css", base, cwd), "/tmp/other.css");
+/// ```
+pub fn relative_to(path: &str, base: &Path, cwd: &Path) -> String {
+    let mut absolute = PathBuf::new();
+    for component in cwd.join(path).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                absolute.pop();
+            }
+            other => absolute.push(other),
+        }
+    }
+    match absolute.strip_prefix(base) {
+        Ok(relative) => relative.display().to_string(),
+        Err(_) => path.to_string(),
+    }
+}
+
+/// Number of path components of `path` below `cwd`, counting the file name.
+/// `.` and `..` components don't count, and absolute paths are made relative
+/// to `cwd` first.
+///
+/// ```
+/// use std::path::Path;
+/// use hexvar_core::paths::path_depth;
+///
+/// let cwd = Path::new("/work/site");
+/// assert_eq!(path_depth(Path::new("app.css"), cwd), 1);
+/// assert_eq!(path_depth(Path::new("./src/theme/app.css"), cwd), 3);
+/// assert_eq!(path_depth(Path::new("/work/site/vendor/pkg/a.css"), cwd), 3);
+/// ```
+pub fn path_depth(path: &Path, cwd: &Path) -> usize {
+    let relative = path.strip_prefix(cwd).unwrap_or(path);
+    relative.components().filter(|c| matches!(c, Component::Normal(_))).count()
+}
+
+/// Like [`collect_paths`], with the extra extensions and ignore files in `filter`.
+/// A [`STDIN_PATTERN`] pattern is kept as a single path, read with [`read_source`].
+/// Patterns are expanded in parallel; a file matched by several patterns is
+/// collected once, in the position of the first pattern that matched it.
+pub fn collect_paths_with(patterns: &[String], filter: &PathFilter) -> Result<Vec<PathBuf>> {
+    let extra: Vec<&str> = filter.extra_exts.iter().map(|e| e.trim_start_matches('.')).collect();
+    let mut ignore = IgnoreMatcher::new(filter)?;
+    let max_depth = match filter.max_depth {
+        Some(depth) => Some((depth, std::env::current_dir().map_err(|e| HexvarError::io(".", e))?)),
+        None => None,
+    };
+    // Standard input can only be read once, so it's expanded to a single path
+    let expanded: Vec<Vec<PathBuf>> = patterns
+        .par_iter()
+        .map(|pat| if pat == STDIN_PATTERN { Ok(vec![PathBuf::from(STDIN_PATTERN)]) } else { expand_pattern(pat, &extra, filter.only.as_deref()) })
+        .collect::<Result<_>>()?;
+    let mut seen: HashSet<PathBuf> = HashSet::new();
+    let mut paths: Vec<PathBuf> = Vec::new();
+    for path in expanded.into_iter().flatten() {
+        if !seen.insert(path.clone()) {
+            continue;
+        }
+        if let Some((max_depth, cwd)) = max_depth.as_ref() {
+            if !is_stdin(&path) && path_depth(&path, cwd) > *max_depth {
+                debug!("Skipping {}: deeper than --max-depth {}", path.display(), max_depth);
+                continue;
+            }
+        }
+        // skip if matched by --ignore, .hexvarignore, or .gitignore
+        if !is_stdin(&path) && ignore.is_ignored(&path) {
+            debug!("Skipping {}: ignored", path.display());
+            continue;
+        }
+        paths.push(path);
+        if filter.max_files.is_some_and(|limit| paths.len() > limit) {
+            return Err(HexvarError::TooManyFiles { limit: paths.len() - 1 });
+        }
+    }
+    Ok(paths)
+}
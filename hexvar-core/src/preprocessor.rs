@@ -0,0 +1,126 @@
+use crate::replace::js::JS_EXTENSIONS;
+use crate::replace::LINE_COMMENT_EXTENSIONS;
+use std::ops::Range;
+use std::path::Path;
+
+/// Extensions, besides preprocessors and JS/TS, whose files hold scripts with `//` comments
+const SCRIPT_EXTENSIONS: &[&str] = &["vue", "svelte", "astro"];
+
+/// Whether `//` starts a comment in `path`. In plain CSS and HTML it doesn't,
+/// so unquoted `url(http://...)` values stay intact.
+pub fn has_line_comments(path: &Path) -> bool {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_ascii_lowercase();
+    [LINE_COMMENT_EXTENSIONS, JS_EXTENSIONS, SCRIPT_EXTENSIONS].iter().any(|exts| exts.contains(&ext.as_str()))
+}
+
+/// Where the comment scanner is
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    Code,
+    /// Inside a quoted string, with its quote character
+    Quoted(u8),
+    /// Inside an unquoted `url(...)`
+    Url,
+    /// Inside `/* ... */`, which started at the given offset
+    Block(usize),
+    /// Inside `// ...`, which started at the given offset
+    Line(usize),
+}
+
+/// Byte ranges of the `/* ... */` comments in `content`, and of `//` comments
+/// when `line_comments` is set. Comment markers inside quoted strings and
+/// unquoted `url()` values are ignored.
+///
+/// ```
+/// use hexvar_core::preprocessor::comment_ranges;
+///
+/// let css = "a { color: #fff; /* was #000 */ background: url(http://x/#a) }";
+/// let ranges = comment_ranges(css, true);
+/// assert_eq!(ranges.iter().map(|r| &css[r.clone()]).collect::<Vec<_>>(), ["/* was #000 */"]);
+///
+/// let scss = "$a: #fff; // old: #000\n$b: '//#123';";
+/// assert_eq!(&scss[comment_ranges(scss, true)[0].clone()], "// old: #000");
+/// assert!(comment_ranges(scss, false).is_empty());
+/// ```
+pub fn comment_ranges(content: &str, line_comments: bool) -> Vec<Range<usize>> {
+    let bytes = content.as_bytes();
+    let mut ranges = Vec::new();
+    let mut state = State::Code;
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        let next = bytes.get(i + 1).copied();
+        state = match state {
+            State::Code => match b {
+                b'/' if next == Some(b'*') => {
+                    i += 1;
+                    State::Block(i - 1)
+                }
+                b'/' if next == Some(b'/') && line_comments => State::Line(i),
+                b'"' | b'\'' | b'`' => State::Quoted(b),
+                b'(' if bytes[i.saturating_sub(3)..i].eq_ignore_ascii_case(b"url") && !matches!(next, Some(b'"' | b'\'')) => {
+                    State::Url
+                }
+                _ => State::Code,
+            },
+            State::Quoted(quote) => match b {
+                b'\\' => {
+                    i += 1;
+                    state
+                }
+                // Only template literals span lines; an unclosed string ends with its line
+                b'\n' if quote != b'`' => State::Code,
+                _ if b == quote => State::Code,
+                _ => state,
+            },
+            State::Url => if b == b')' || b == b'\n' { State::Code } else { state },
+            State::Block(start) => {
+                if b == b'*' && next == Some(b'/') {
+                    i += 1;
+                    ranges.push(start..i + 1);
+                    State::Code
+                } else {
+                    state
+                }
+            }
+            State::Line(start) => {
+                if b == b'\n' {
+                    ranges.push(start..i);
+                    State::Code
+                } else {
+                    state
+                }
+            }
+        };
+        i += 1;
+    }
+    match state {
+        State::Block(start) | State::Line(start) => ranges.push(start..bytes.len()),
+        _ => {}
+    }
+    ranges
+}
+
+/// `content` with every comment in `ranges` blanked out by spaces, keeping
+/// line breaks and byte offsets, so matches in the result map straight back
+/// to the original
+///
+/// ```
+/// use hexvar_core::preprocessor::{comment_ranges, strip_comments};
+///
+/// let css = "a { /* #000 */ color: #fff; }";
+/// assert_eq!(strip_comments(css, &comment_ranges(css, false)), "a {            color: #fff; }");
+/// ```
+pub fn strip_comments(content: &str, ranges: &[Range<usize>]) -> String {
+    let mut out = String::with_capacity(content.len());
+    let mut ranges = ranges.iter().peekable();
+    for (i, c) in content.char_indices() {
+        while ranges.next_if(|r| r.end <= i).is_some() {}
+        if c != '\n' && ranges.peek().is_some_and(|r| r.contains(&i)) {
+            out.extend(std::iter::repeat_n(' ', c.len_utf8()));
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
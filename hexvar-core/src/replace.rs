@@ -0,0 +1,628 @@
+use crate::error::{HexvarError, Result};
+use crate::output::{bare_name, camel_case};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+pub mod js;
+pub mod manifest;
+
+/// Extensions whose syntax allows `//` line comments
+pub const LINE_COMMENT_EXTENSIONS: &[&str] = &["scss", "sass", "less", "styl"];
+
+/// Comment syntax for `replace --annotate`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum CommentStyle {
+    /// `/* was: #1a2b3c */`, valid everywhere
+    #[default]
+    Block,
+    /// `// was: #1a2b3c`, for preprocessor files only
+    Line,
+}
+
+impl CommentStyle {
+    /// `self`, unless `path` can't hold line comments (plain `.css`, `.vue`, ...)
+    pub fn for_path(self, path: &Path) -> CommentStyle {
+        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+        if LINE_COMMENT_EXTENSIONS.contains(&ext) {
+            self
+        } else {
+            CommentStyle::Block
+        }
+    }
+}
+
+/// How `replace --var-syntax` refers to a variable in place of a hex code
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum VarSyntax {
+    /// `var(--color-x)`
+    #[default]
+    Css,
+    /// `$color-x`
+    Scss,
+    /// `@color-x`
+    Less,
+    /// `theme('colors.x')`, the key `scan --tailwind` writes the color under
+    Tailwind,
+}
+
+impl VarSyntax {
+    /// The reference to `var`, which may be written `--color-x`, `$color-x`, or `@color-x`
+    ///
+    /// ```
+    /// use hexvar_core::replace::VarSyntax;
+    ///
+    /// assert_eq!(VarSyntax::Css.reference("--color-dark-red"), "var(--color-dark-red)");
+    /// assert_eq!(VarSyntax::Scss.reference("--color-dark-red"), "$color-dark-red");
+    /// assert_eq!(VarSyntax::Less.reference("$color-dark-red"), "@color-dark-red");
+    /// assert_eq!(VarSyntax::Tailwind.reference("--color-dark-red"), "theme('colors.darkRed')");
+    /// ```
+    pub fn reference(self, var: &str) -> String {
+        let bare = var.strip_prefix("--").or_else(|| var.strip_prefix(['$', '@'])).unwrap_or(var);
+        match self {
+            VarSyntax::Css => format!("var(--{})", bare),
+            VarSyntax::Scss => format!("${}", bare),
+            VarSyntax::Less => format!("@{}", bare),
+            VarSyntax::Tailwind => format!("theme('colors.{}')", camel_case(bare_name(&format!("--{}", bare)))),
+        }
+    }
+
+    /// `hex_to_var` with each variable replaced by its reference, ready for the
+    /// `replace_*` functions, which insert anything that isn't a `--name` as written
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use hexvar_core::replace::{replace_in_content, VarSyntax};
+    ///
+    /// let map = HashMap::from([("#ff0000".to_string(), "--color-red".to_string())]);
+    /// let scss = VarSyntax::Scss.references(&map);
+    /// assert_eq!(replace_in_content("a { color: #FF0000; }", &scss), "a { color: $color-red; }");
+    /// let tailwind = VarSyntax::Tailwind.references(&map);
+    /// assert_eq!(replace_in_content("a { color: #ff0000; }", &tailwind), "a { color: theme('colors.red'); }");
+    /// ```
+    pub fn references(self, hex_to_var: &HashMap<String, String>) -> HashMap<String, String> {
+        hex_to_var.iter().map(|(hex, var)| (hex.clone(), self.reference(var))).collect()
+    }
+}
+
+/// Files `replace --check` found with hex codes that still map to a variable
+#[derive(Debug, Default, Serialize)]
+pub struct CheckReport {
+    pub files: Vec<String>,
+    pub total_unresolved: usize,
+}
+
+/// One rewrite made by `replace`: `original` at byte `offset` of the file's
+/// original content became `replacement`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Substitution {
+    pub offset: usize,
+    pub original: String,
+    pub replacement: String,
+}
+
+/// `(name, lowercase value)` of every `--name: value;` line in a CSS variables
+/// file, or `$name: value;` and `@name: value;` line in an SCSS or Less one
+///
+/// ```
+/// use hexvar_core::replace::css_var_definitions;
+///
+/// let css = ":root {\n    --color-red: #FF0000;\n}\n";
+/// assert_eq!(css_var_definitions(css), [("--color-red".to_string(), "#ff0000".to_string())]);
+/// let scss = "$color-red: #ff0000;\n@media (prefers-color-scheme: dark) {}\n@color-blue: #00f;\n";
+/// let names: Vec<_> = css_var_definitions(scss).into_iter().map(|(var, _)| var).collect();
+/// assert_eq!(names, ["$color-red", "@color-blue"]);
+/// let commented = "    --color-red: #ff0000; /* merged: #fe0000 (2) */\n";
+/// assert_eq!(css_var_definitions(commented), [("--color-red".to_string(), "#ff0000".to_string())]);
+/// ```
+pub fn css_var_definitions(css: &str) -> Vec<(String, String)> {
+    css.lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            let sigil = ["--", "$", "@"].into_iter().find(|s| line.starts_with(s))?;
+            let (var, hex) = line.split_once(':')?;
+            let ident = &var.trim()[sigil.len()..];
+            let valid = !ident.is_empty() && ident.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+            // The value ends at its `;` or a comment, like the one `scan --css-vars-comment` adds
+            let value = hex.split(';').next().unwrap_or_default().split("/*").next().unwrap_or_default();
+            valid.then(|| (var.trim().to_string(), value.trim().to_lowercase()))
+        })
+        .collect()
+}
+
+/// Build the lookup of lowercase hex (or source spelling) -> variable name from a
+/// `colours_map.json` mapping and the CSS variables file that defines each name.
+/// Fails with [`HexvarError::MissingVariable`] for a canonical hex with no variable in `css`.
+/// Every key is lowercased, whatever the case in the mapping or the variables file.
+///
+/// ```
+/// use std::collections::HashMap;
+/// use hexvar_core::replace::{build_hex_to_var, replace_in_content_counted};
+///
+/// let map = HashMap::from([("#FF0000".to_string(), vec!["#FF0000".to_string(), "#F00".to_string()])]);
+/// let hex_to_var = build_hex_to_var(&map, ":root {\n    --color-red: #FF0000;\n}\n").unwrap();
+/// assert_eq!(hex_to_var["#ff0000"], "--color-red");
+/// assert_eq!(hex_to_var["#f00"], "--color-red");
+/// let (out, count) = replace_in_content_counted("A { COLOR: #FF0000; BORDER: 1PX SOLID #F00 }", &hex_to_var);
+/// assert_eq!(out, "A { COLOR: var(--color-red); BORDER: 1PX SOLID var(--color-red) }");
+/// assert_eq!(count, 2);
+/// ```
+pub fn build_hex_to_var(map: &HashMap<String, Vec<String>>, css: &str) -> Result<HashMap<String, String>> {
+    // The first definition wins, so values in a later block such as a dark
+    // mode override never take over another color's variable
+    let mut canon_to_var: HashMap<String, String> = HashMap::new();
+    for (var, hex) in css_var_definitions(css) {
+        canon_to_var.entry(hex).or_insert(var);
+    }
+    let mut hex_to_var = HashMap::new();
+    for (canon, hexes) in map {
+        let css_hex = format!("#{}", canon.trim_start_matches('#').to_lowercase());
+        let var = canon_to_var.get(&css_hex).ok_or_else(|| HexvarError::MissingVariable { hex: canon.clone() })?;
+        for h in hexes {
+            hex_to_var.insert(h.to_lowercase(), var.clone());
+        }
+    }
+    Ok(hex_to_var)
+}
+
+/// Read the `colours_map.json` mapping and CSS variables file written by
+/// `hexvar scan --css-vars` and build the lookup used by [`replace_in_content`]
+pub fn load_hex_to_var(map_path: &Path, css_path: &Path) -> Result<HashMap<String, String>> {
+    let map_json = fs::read_to_string(map_path)
+        .map_err(|source| HexvarError::MissingMapFile { path: map_path.to_path_buf(), source })?;
+    let map: HashMap<String, Vec<String>> =
+        serde_json::from_str(&map_json).map_err(|e| HexvarError::json(map_path, e))?;
+    let css = fs::read_to_string(css_path)
+        .map_err(|source| HexvarError::MissingCssFile { path: css_path.to_path_buf(), source })?;
+    build_hex_to_var(&map, &css)
+}
+
+/// Build the lookup of lowercase hex (or source spelling) -> lowercase canonical
+/// hex from a `colours_map.json` mapping, for [`less_declarations_recorded`]
+///
+/// ```
+/// use std::collections::HashMap;
+/// use hexvar_core::replace::build_hex_to_canonical;
+///
+/// let map = HashMap::from([("#FF0000".to_string(), vec!["#F00".to_string(), "#fe0000".to_string()])]);
+/// let hex_to_canonical = build_hex_to_canonical(&map);
+/// assert_eq!(hex_to_canonical["#f00"], "#ff0000");
+/// assert_eq!(hex_to_canonical["#fe0000"], "#ff0000");
+/// assert_eq!(hex_to_canonical["#ff0000"], "#ff0000");
+/// ```
+pub fn build_hex_to_canonical(map: &HashMap<String, Vec<String>>) -> HashMap<String, String> {
+    let mut hex_to_canonical = HashMap::new();
+    for (canon, hexes) in map {
+        let css_hex = format!("#{}", canon.trim_start_matches('#').to_lowercase());
+        for h in hexes.iter().chain([canon]) {
+            hex_to_canonical.insert(h.to_lowercase(), css_hex.clone());
+        }
+    }
+    hex_to_canonical
+}
+
+/// Read the `colours_map.json` mapping and build the lookup used by
+/// [`less_declarations_recorded`]
+pub fn load_hex_to_canonical(map_path: &Path) -> Result<HashMap<String, String>> {
+    let map_json = fs::read_to_string(map_path)
+        .map_err(|source| HexvarError::MissingMapFile { path: map_path.to_path_buf(), source })?;
+    let map: HashMap<String, Vec<String>> =
+        serde_json::from_str(&map_json).map_err(|e| HexvarError::json(map_path, e))?;
+    Ok(build_hex_to_canonical(&map))
+}
+
+/// Rewrite a Less file with `rewrite`, except for the hex codes that are the
+/// whole value of an `@name: #hex;` declaration. Less variables are resolved at
+/// compile time, so those are normalized to their canonical hex instead of
+/// becoming a reference, which would leave e.g. `@color-red: @color-red;` in the
+/// file that defines the variables. Returns the new content and every
+/// substitution, with offsets into the whole file.
+///
+/// ```
+/// use std::collections::HashMap;
+/// use hexvar_core::replace::{less_declarations_recorded, property_aware_replace_recorded, VarSyntax};
+///
+/// let map = HashMap::from([("#1A2C3D".to_string(), vec!["#1a2c3d".to_string(), "#1B2C3D".to_string()])]);
+/// let hex_to_canonical = hexvar_core::replace::build_hex_to_canonical(&map);
+/// let vars = HashMap::from([("#1a2c3d".to_string(), "@color-navy".to_string()), ("#1b2c3d".to_string(), "@color-navy".to_string())]);
+/// let rewrite = |less: &str| property_aware_replace_recorded(less, &vars, &[]);
+///
+/// // A project with its palette in one file and the styles using it in another
+/// let colours = "@color-navy: #1A2C3D;\n@brand: #1B2C3D !default;\n";
+/// let (out, subs) = less_declarations_recorded(colours, &hex_to_canonical, rewrite);
+/// assert_eq!(out, "@color-navy: #1a2c3d;\n@brand: #1a2c3d !default;\n");
+/// assert_eq!((subs[1].offset, subs[1].original.as_str(), subs[1].replacement.as_str()), (30, "#1B2C3D", "#1a2c3d"));
+///
+/// let button = "@hover: #1b2c3d;\n.button { color: #1B2C3D; border: 1px solid @hover; }\n";
+/// let (out, subs) = less_declarations_recorded(button, &hex_to_canonical, rewrite);
+/// assert_eq!(out, "@hover: #1a2c3d;\n.button { color: @color-navy; border: 1px solid @hover; }\n");
+/// assert_eq!(subs.len(), 2);
+///
+/// // Canonical declarations are left as they are
+/// let (out, subs) = less_declarations_recorded("@navy: #1a2c3d;", &hex_to_canonical, rewrite);
+/// assert_eq!((out.as_str(), subs.len()), ("@navy: #1a2c3d;", 0));
+/// ```
+pub fn less_declarations_recorded<F>(
+    content: &str,
+    hex_to_canonical: &HashMap<String, String>,
+    mut rewrite: F,
+) -> (String, Vec<Substitution>)
+where
+    F: FnMut(&str) -> (String, Vec<Substitution>),
+{
+    let re = Regex::new(crate::color::HEX_PATTERN).unwrap();
+    let mut out = String::with_capacity(content.len());
+    let mut subs = Vec::new();
+    let mut last = 0;
+    for m in re.find_iter(content) {
+        let is_less_variable =
+            crate::scanner::variable_declaration(content, m.range()).is_some_and(|name| name.starts_with('@'));
+        let Some(canonical) = hex_to_canonical.get(&m.as_str().to_lowercase()).filter(|_| is_less_variable) else {
+            continue;
+        };
+        let (text, segment_subs) = rewrite(&content[last..m.start()]);
+        subs.extend(segment_subs.into_iter().map(|sub| Substitution { offset: last + sub.offset, ..sub }));
+        out.push_str(&text);
+        out.push_str(canonical);
+        if m.as_str() != canonical {
+            subs.push(Substitution { offset: m.start(), original: m.as_str().to_string(), replacement: canonical.clone() });
+        }
+        last = m.end();
+    }
+    let (text, segment_subs) = rewrite(&content[last..]);
+    subs.extend(segment_subs.into_iter().map(|sub| Substitution { offset: last + sub.offset, ..sub }));
+    out.push_str(&text);
+    (out, subs)
+}
+
+/// Replace every occurrence of a mapped hex code with `var(--name)`, except
+/// inside existing `var()` calls, whose fallbacks are kept as written
+///
+/// ```
+/// use std::collections::HashMap;
+///
+/// let map = HashMap::from([("#ff0000".to_string(), "--color-red".to_string())]);
+/// let out = hexvar_core::replace_in_content("a { color: #FF0000; }", &map);
+/// assert_eq!(out, "a { color: var(--color-red); }");
+///
+/// let css = "a { color: var(--existing, #ff0000); border: 1px solid #ff0000; }";
+/// assert_eq!(
+///     hexvar_core::replace_in_content(css, &map),
+///     "a { color: var(--existing, #ff0000); border: 1px solid var(--color-red); }"
+/// );
+/// let nested = "a { color: VAR(--a, var(--b, #ff0000)); background: calc-var(#ff0000) }";
+/// assert_eq!(
+///     hexvar_core::replace_in_content(nested, &map),
+///     "a { color: VAR(--a, var(--b, #ff0000)); background: calc-var(var(--color-red)) }"
+/// );
+///
+/// // A mapped short form is never replaced at the start of a longer, unmapped code
+/// let map = HashMap::from([("#abc".to_string(), "--color-lightsteelblue".to_string())]);
+/// assert_eq!(
+///     hexvar_core::replace_in_content("a { color: #abcdef; border-color: #ABC }", &map),
+///     "a { color: #abcdef; border-color: var(--color-lightsteelblue) }"
+/// );
+/// ```
+pub fn replace_in_content(content: &str, hex_to_var: &HashMap<String, String>) -> String {
+    replace_in_content_counted(content, hex_to_var).0
+}
+
+/// Like [`replace_in_content`], also returning the number of replacements made.
+/// Each substitution is counted once where it is made, so a color repeated on
+/// the continuation lines of a multi-line value isn't counted twice.
+///
+/// ```
+/// use std::collections::HashMap;
+/// use hexvar_core::replace::replace_in_content_counted;
+///
+/// let map = HashMap::from([("#ff0000".to_string(), "--color-red".to_string())]);
+/// let css = "a {\n  background: linear-gradient(\n    #ff0000 0%,\n    #FF0000 100%\n  );\n}";
+/// let (out, count) = replace_in_content_counted(css, &map);
+/// assert_eq!(out, "a {\n  background: linear-gradient(\n    var(--color-red) 0%,\n    var(--color-red) 100%\n  );\n}");
+/// assert_eq!(count, 2);
+/// // Only what changed is counted, not every `var(--color-red)` in the result
+/// let (_, count) = replace_in_content_counted(&format!("{}\nb {{ color: #ff0000 }}", out), &map);
+/// assert_eq!(count, 1);
+/// ```
+pub fn replace_in_content_counted(content: &str, hex_to_var: &HashMap<String, String>) -> (String, usize) {
+    replace_compiled(content, 0, &compile(hex_to_var), &mut Vec::new())
+}
+
+/// One case-insensitive regex per mapped hex, paired with its `var(--name)`
+/// replacement. Values that aren't `--name`s, like the references made by
+/// [`VarSyntax::references`], replace the hex as they are.
+fn compile(hex_to_var: &HashMap<String, String>) -> Vec<(Regex, String)> {
+    hex_to_var
+        .iter()
+        .map(|(hex, var)| {
+            let re = Regex::new(&format!(r"(?i){}", regex::escape(hex))).unwrap();
+            let replacement = if var.starts_with("--") { format!("var({})", var) } else { var.clone() };
+            (re, replacement)
+        })
+        .collect()
+}
+
+/// Apply every compiled replacement outside existing `var()` calls, pushing
+/// each one onto `subs` with offsets shifted by `base`
+fn replace_compiled(content: &str, base: usize, compiled: &[(Regex, String)], subs: &mut Vec<Substitution>) -> (String, usize) {
+    let before = subs.len();
+    let mut replaced = String::with_capacity(content.len());
+    let mut last = 0;
+    for zone in var_calls(content) {
+        replaced.push_str(&replace_all_compiled(&content[last..zone.start], base + last, compiled, subs));
+        replaced.push_str(&content[zone.clone()]);
+        last = zone.end;
+    }
+    replaced.push_str(&replace_all_compiled(&content[last..], base + last, compiled, subs));
+    (replaced, subs.len() - before)
+}
+
+/// Whether the match ending at `end` runs on into more hex digits, like the
+/// `#abc` at the start of `#abcdef`, and so isn't a whole color
+pub(crate) fn runs_on(content: &str, end: usize) -> bool {
+    content.as_bytes().get(end).is_some_and(u8::is_ascii_hexdigit)
+}
+
+/// Apply every compiled replacement in one left-to-right pass. Where two
+/// spellings match at the same place the longer one wins, and a match that
+/// [`runs_on`] is dropped, so `#abc` never eats the start of `#abcdef`.
+pub(crate) fn replace_all_compiled(
+    content: &str,
+    base: usize,
+    compiled: &[(Regex, String)],
+    subs: &mut Vec<Substitution>,
+) -> String {
+    let mut matches: Vec<(Range<usize>, &String)> = compiled
+        .iter()
+        .flat_map(|(re, var)| re.find_iter(content).map(move |m| (m.range(), var)))
+        .filter(|(range, _)| !runs_on(content, range.end))
+        .collect();
+    matches.sort_by_key(|(range, _)| (range.start, std::cmp::Reverse(range.end)));
+    let mut replaced = String::with_capacity(content.len());
+    let mut last = 0;
+    for (range, var) in matches {
+        if range.start < last {
+            continue;
+        }
+        replaced.push_str(&content[last..range.start]);
+        replaced.push_str(var);
+        subs.push(Substitution {
+            offset: base + range.start,
+            original: content[range.clone()].to_string(),
+            replacement: var.clone(),
+        });
+        last = range.end;
+    }
+    replaced.push_str(&content[last..]);
+    replaced
+}
+
+/// Byte ranges of the outermost `var(...)` calls in `content`, up to their
+/// closing parenthesis (or the end of the content if unclosed). Their fallbacks
+/// are left alone, so `var(--a, #f00)` never becomes `var(--a, var(--b))`.
+pub(crate) fn var_calls(content: &str) -> Vec<Range<usize>> {
+    let bytes = content.as_bytes();
+    let mut zones = Vec::new();
+    let mut i = 0;
+    while i + 4 <= bytes.len() {
+        let starts_call = bytes[i..i + 4].eq_ignore_ascii_case(b"var(")
+            && (i == 0 || !(bytes[i - 1].is_ascii_alphanumeric() || bytes[i - 1] == b'-' || bytes[i - 1] == b'_'));
+        if !starts_call {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        let mut depth = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'(' => depth += 1,
+                b')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+        i = (i + 1).min(bytes.len());
+        zones.push(start..i);
+    }
+    zones
+}
+
+/// Matches declarations of the given properties, or `None` to replace everywhere
+fn property_regex(properties: &[String]) -> Option<Regex> {
+    if properties.is_empty() {
+        return None;
+    }
+    let names: Vec<String> = properties.iter().map(|p| regex::escape(p.trim())).collect();
+    // The property must start a declaration, so `color` doesn't match `border-color`
+    Some(Regex::new(&format!(r"(?i)(^|[;{{\s])({})(\s*:)([^;{{}}]*)", names.join("|"))).unwrap())
+}
+
+/// Replace inside the values matched by `decl_re`, or everywhere without one
+fn replace_declarations(
+    content: &str,
+    base: usize,
+    compiled: &[(Regex, String)],
+    decl_re: Option<&Regex>,
+    subs: &mut Vec<Substitution>,
+) -> (String, usize) {
+    let Some(decl_re) = decl_re else {
+        return replace_compiled(content, base, compiled, subs);
+    };
+    let mut replacements = 0;
+    let replaced = decl_re.replace_all(content, |caps: &regex::Captures| {
+        let value = caps.get(4).unwrap();
+        let (value, count) = replace_compiled(value.as_str(), base + value.start(), compiled, subs);
+        replacements += count;
+        format!("{}{}{}{}", &caps[1], &caps[2], &caps[3], value)
+    });
+    (replaced.into_owned(), replacements)
+}
+
+/// Like [`replace_in_content`], but only inside the values of the given CSS
+/// properties (case-insensitive). With no properties, replaces everywhere.
+///
+/// ```
+/// use std::collections::HashMap;
+/// use hexvar_core::replace::property_aware_replace;
+///
+/// let map = HashMap::from([("#ff0000".to_string(), "--color-red".to_string())]);
+/// let css = "a { color: #ff0000; border-color: #ff0000; }";
+/// let out = property_aware_replace(css, &map, &["color".to_string()]);
+/// assert_eq!(out, "a { color: var(--color-red); border-color: #ff0000; }");
+///
+/// let css = "a { color: var(--existing, #ff0000); }";
+/// assert_eq!(property_aware_replace(css, &map, &["color".to_string()]), css);
+/// ```
+pub fn property_aware_replace(content: &str, hex_to_var: &HashMap<String, String>, properties: &[String]) -> String {
+    property_aware_replace_counted(content, hex_to_var, properties).0
+}
+
+/// Like [`property_aware_replace`], also returning the number of replacements made
+///
+/// ```
+/// use std::collections::HashMap;
+/// use hexvar_core::replace::property_aware_replace_counted;
+///
+/// let map = HashMap::from([("#ff0000".to_string(), "--color-red".to_string()), ("#00ff00".to_string(), "--color-lime".to_string())]);
+/// let css = "a {\n  background: linear-gradient(\n    to right,\n    #ff0000,\n    #00ff00,\n    #ff0000\n  );\n  color: #ff0000;\n}";
+/// let (out, count) = property_aware_replace_counted(css, &map, &["background".to_string()]);
+/// assert_eq!(count, 3);
+/// assert!(out.ends_with("    var(--color-red)\n  );\n  color: #ff0000;\n}"));
+/// ```
+pub fn property_aware_replace_counted(
+    content: &str,
+    hex_to_var: &HashMap<String, String>,
+    properties: &[String],
+) -> (String, usize) {
+    let (replaced, subs) = property_aware_replace_recorded(content, hex_to_var, properties);
+    (replaced, subs.len())
+}
+
+/// Like [`property_aware_replace`], also returning every substitution made
+///
+/// ```
+/// use std::collections::HashMap;
+/// use hexvar_core::replace::property_aware_replace_recorded;
+///
+/// let map = HashMap::from([("#ff0000".to_string(), "--color-red".to_string())]);
+/// let (_, subs) = property_aware_replace_recorded("a { color: #FF0000; }", &map, &[]);
+/// assert_eq!((subs[0].offset, subs[0].original.as_str()), (11, "#FF0000"));
+/// assert_eq!(subs[0].replacement, "var(--color-red)");
+/// ```
+pub fn property_aware_replace_recorded(
+    content: &str,
+    hex_to_var: &HashMap<String, String>,
+    properties: &[String],
+) -> (String, Vec<Substitution>) {
+    let mut subs = Vec::new();
+    let (replaced, _) =
+        replace_declarations(content, 0, &compile(hex_to_var), property_regex(properties).as_ref(), &mut subs);
+    (replaced, subs)
+}
+
+/// Comment recording the original spellings of a replaced declaration
+fn annotation(original: &str, style: CommentStyle) -> String {
+    match style {
+        CommentStyle::Block => format!("/* was: {} */", original),
+        CommentStyle::Line => format!("// was: {}", original),
+    }
+}
+
+/// The `var()` reference that replaces `original`, ending the declaration
+/// with a comment that records the original value
+///
+/// ```
+/// use hexvar_core::replace::{annotate_replacement, CommentStyle};
+///
+/// assert_eq!(
+///     annotate_replacement("#1A2B3C", "--color-primary", CommentStyle::Block),
+///     "var(--color-primary); /* was: #1A2B3C */"
+/// );
+/// assert_eq!(
+///     annotate_replacement("#1A2B3C", "--color-primary", CommentStyle::Line),
+///     "var(--color-primary); // was: #1A2B3C"
+/// );
+/// ```
+pub fn annotate_replacement(original: &str, var_name: &str, style: CommentStyle) -> String {
+    format!("var({}); {}", var_name, annotation(original, style))
+}
+
+/// Like [`property_aware_replace_counted`], also ending each changed declaration
+/// with a comment listing the original spellings, case preserved. Line comments
+/// fall back to block comments where they would swallow the rest of the line.
+///
+/// ```
+/// use std::collections::HashMap;
+/// use hexvar_core::replace::{annotated_replace_counted, CommentStyle};
+///
+/// let map = HashMap::from([("#1a2b3c".to_string(), "--color-primary".to_string())]);
+/// let css = "a {\n  background: #1A2B3C;\n  color: #1a2b3c\n}";
+/// let (out, count) = annotated_replace_counted(css, &map, &[], CommentStyle::Line);
+/// assert_eq!(
+///     out,
+///     "a {\n  background: var(--color-primary); // was: #1A2B3C\n  color: var(--color-primary) /* was: #1a2b3c */\n}"
+/// );
+/// assert_eq!(count, 2);
+/// ```
+pub fn annotated_replace_counted(
+    content: &str,
+    hex_to_var: &HashMap<String, String>,
+    properties: &[String],
+    style: CommentStyle,
+) -> (String, usize) {
+    let (replaced, subs) = annotated_replace_recorded(content, hex_to_var, properties, style);
+    (replaced, subs.len())
+}
+
+/// Like [`annotated_replace_counted`], also returning every substitution made.
+/// The added comments are not substitutions.
+pub fn annotated_replace_recorded(
+    content: &str,
+    hex_to_var: &HashMap<String, String>,
+    properties: &[String],
+    style: CommentStyle,
+) -> (String, Vec<Substitution>) {
+    let compiled = compile(hex_to_var);
+    let decl_re = property_regex(properties);
+    let mut out = String::with_capacity(content.len());
+    let mut subs = Vec::new();
+    let mut pos = 0;
+    // Each segment holds at most one declaration, ending at its `;` or `}`
+    for segment in content.split_inclusive([';', '{', '}']) {
+        let before = subs.len();
+        let (replaced, count) = replace_declarations(segment, pos, &compiled, decl_re.as_ref(), &mut subs);
+        pos += segment.len();
+        if count == 0 || segment.ends_with('{') {
+            out.push_str(&replaced);
+            continue;
+        }
+        let mut unique: Vec<&str> = Vec::new();
+        for sub in &subs[before..] {
+            if !unique.contains(&sub.original.as_str()) {
+                unique.push(&sub.original);
+            }
+        }
+        let original = unique.join(", ");
+        if replaced.ends_with(';') {
+            let rest_of_line = content[pos..].split('\n').next().unwrap_or_default();
+            let style = if rest_of_line.trim().is_empty() { style } else { CommentStyle::Block };
+            out.push_str(&format!("{} {}", replaced, annotation(&original, style)));
+        } else {
+            // Last declaration in a block, or at the end of the file: comment before the `}`
+            let body = replaced.strip_suffix('}').unwrap_or(&replaced);
+            let value = body.trim_end();
+            out.push_str(&format!("{} {}{}", value, annotation(&original, CommentStyle::Block), &replaced[value.len()..]));
+        }
+    }
+    (out, subs)
+}
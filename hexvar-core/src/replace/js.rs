@@ -0,0 +1,301 @@
+use super::{replace_all_compiled, Substitution};
+use crate::error::{HexvarError, Result};
+use regex::Regex;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+
+/// Extensions of JavaScript and TypeScript files, where colors live in string
+/// and template literals
+pub const JS_EXTENSIONS: &[&str] = &["js", "jsx", "ts", "tsx"];
+
+/// What `replace` writes in place of a color in a JS/TS file
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum JsStyle {
+    /// `var(--color-red)`, as in CSS (works in styled-components template literals)
+    #[default]
+    Var,
+    /// `colorRed`, imported from the design tokens file written by `scan --format js`
+    Import,
+}
+
+/// Whether `path` is a JavaScript or TypeScript file
+pub fn is_js(path: &Path) -> bool {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+    JS_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str())
+}
+
+/// Build the lookup of lowercase hex (or source spelling) -> exported constant
+/// from a `colours_map.json` mapping and a tokens file of
+/// `export const colorRed = '#ff0000';` lines.
+/// Fails with [`HexvarError::MissingVariable`] for a canonical hex with no constant.
+///
+/// ```
+/// use std::collections::HashMap;
+/// use hexvar_core::replace::js::build_hex_to_const;
+///
+/// let map = HashMap::from([("#ff0000".to_string(), vec!["#ff0000".to_string(), "#FE0000".to_string()])]);
+/// let lookup = build_hex_to_const(&map, "export const colorRed = '#ff0000';\n").unwrap();
+/// assert_eq!(lookup["#fe0000"], "colorRed");
+/// ```
+pub fn build_hex_to_const(map: &HashMap<String, Vec<String>>, tokens: &str) -> Result<HashMap<String, String>> {
+    let export_re = Regex::new(r#"export\s+const\s+([A-Za-z_$][\w$]*)\s*(?::[^=]+)?=\s*['"](#[0-9a-fA-F]+)['"]"#).unwrap();
+    let canon_to_const: HashMap<String, String> = export_re
+        .captures_iter(tokens)
+        .map(|caps| (caps[2].to_lowercase(), caps[1].to_string()))
+        .collect();
+    let mut hex_to_const = HashMap::new();
+    for (canon, hexes) in map {
+        let hex = format!("#{}", canon.trim_start_matches('#').to_lowercase());
+        let name = canon_to_const.get(&hex).ok_or_else(|| HexvarError::MissingVariable { hex: canon.clone() })?;
+        for h in hexes {
+            hex_to_const.insert(h.to_lowercase(), name.clone());
+        }
+    }
+    Ok(hex_to_const)
+}
+
+/// Read the `colours_map.json` mapping and the JS/TS tokens file and build the
+/// lookup used by [`import_replace_counted`]
+pub fn load_hex_to_const(map_path: &Path, tokens_path: &Path) -> Result<HashMap<String, String>> {
+    let map_json = fs::read_to_string(map_path)
+        .map_err(|source| HexvarError::MissingMapFile { path: map_path.to_path_buf(), source })?;
+    let map: HashMap<String, Vec<String>> =
+        serde_json::from_str(&map_json).map_err(|e| HexvarError::json(map_path, e))?;
+    let tokens = fs::read_to_string(tokens_path).map_err(|e| HexvarError::io(tokens_path, e))?;
+    build_hex_to_const(&map, &tokens)
+}
+
+/// Kinds of literal text in a JS/TS source file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Literal {
+    /// A `'...'` or `"..."` string, including its quotes
+    String,
+    /// Text of a template literal between its backticks and `${}` expressions
+    Template,
+}
+
+/// String and template literal spans of a JS/TS file, in order. Comments are
+/// skipped; regex literals aren't recognized, so a quote inside one may
+/// confuse the spans that follow on the same line.
+fn literals(content: &str) -> Vec<(Range<usize>, Literal)> {
+    let bytes = content.as_bytes();
+    let mut found = Vec::new();
+    // One entry per open template literal: the brace depth of its current `${}`,
+    // or `None` while in its text
+    let mut templates: Vec<Option<usize>> = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if let Some(None) = templates.last() {
+            let start = i;
+            while i < bytes.len() && bytes[i] != b'`' && !(bytes[i] == b'$' && bytes.get(i + 1) == Some(&b'{')) {
+                i += if bytes[i] == b'\\' { 2 } else { 1 };
+            }
+            let end = i.min(bytes.len());
+            found.push((start..end, Literal::Template));
+            if i < bytes.len() && bytes[i] == b'`' {
+                templates.pop();
+                i += 1;
+            } else if i < bytes.len() {
+                *templates.last_mut().unwrap() = Some(0);
+                i += 2;
+            }
+            continue;
+        }
+        match bytes[i] {
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                i = content[i..].find('\n').map_or(bytes.len(), |n| i + n);
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = content[i + 2..].find("*/").map_or(bytes.len(), |n| i + n + 4);
+            }
+            quote @ (b'\'' | b'"') => {
+                let start = i;
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote && bytes[i] != b'\n' {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+                i = (i + 1).min(bytes.len());
+                found.push((start..i, Literal::String));
+            }
+            b'`' => {
+                templates.push(None);
+                i += 1;
+            }
+            b'{' => {
+                if let Some(Some(depth)) = templates.last_mut() {
+                    *depth += 1;
+                }
+                i += 1;
+            }
+            b'}' => {
+                match templates.last_mut() {
+                    Some(Some(0)) => *templates.last_mut().unwrap() = None,
+                    Some(Some(depth)) => *depth -= 1,
+                    _ => {}
+                }
+                i += 1;
+            }
+            _ => i += 1,
+        }
+    }
+    found
+}
+
+/// Module specifier importing `tokens` from a file at `from`, e.g. `./colours`
+/// or `../../colours`, with the extension dropped
+///
+/// ```
+/// use std::path::Path;
+/// use hexvar_core::replace::js::module_specifier;
+///
+/// assert_eq!(module_specifier(Path::new("src/ui/Button.tsx"), Path::new("src/colours.ts")), "../colours");
+/// assert_eq!(module_specifier(Path::new("App.jsx"), Path::new("./colours.js")), "./colours");
+/// ```
+pub fn module_specifier(from: &Path, tokens: &Path) -> String {
+    let parts = |p: &Path| -> Vec<PathBuf> {
+        p.components().filter(|c| !matches!(c, Component::CurDir)).map(|c| PathBuf::from(c.as_os_str())).collect()
+    };
+    let dir = parts(from.parent().unwrap_or(Path::new("")));
+    let target = parts(&tokens.with_extension(""));
+    let shared = dir.iter().zip(&target).take_while(|(a, b)| a == b).count();
+    let mut specifier: Vec<String> = vec!["..".to_string(); dir.len() - shared];
+    if specifier.is_empty() {
+        specifier.push(".".to_string());
+    }
+    specifier.extend(target[shared..].iter().map(|p| p.display().to_string()));
+    specifier.join("/")
+}
+
+/// Replace colors in a JS/TS file with constants from the tokens module at
+/// `specifier`, adding them to its import (or a new one at the top of the file).
+/// A string literal that is exactly a color becomes the bare constant, and a
+/// color inside a template literal becomes `${constant}` (and `{constant}` as
+/// a JSX attribute value); colors in other
+/// strings, comments, and code are left alone.
+///
+/// ```
+/// use std::collections::HashMap;
+/// use hexvar_core::replace::js::import_replace_counted;
+///
+/// let lookup = HashMap::from([("#ff0000".to_string(), "colorRed".to_string())]);
+/// let js = "const a = { color: '#FF0000' };\nconst B = styled.a`color: #ff0000;`;\n";
+/// let (out, n) = import_replace_counted(js, &lookup, "./colours");
+/// assert_eq!(out, "import { colorRed } from './colours';\nconst a = { color: colorRed };\nconst B = styled.a`color: ${colorRed};`;\n");
+/// assert_eq!(n, 2);
+///
+/// let (out, _) = import_replace_counted("import { colorBlue } from './colours';\nf('#ff0000');\n", &lookup, "./colours");
+/// assert!(out.starts_with("import { colorBlue, colorRed } from './colours';"));
+///
+/// let (out, _) = import_replace_counted(r##"<Icon size={2} fill="#ff0000" />"##, &lookup, "./colours");
+/// assert!(out.ends_with("<Icon size={2} fill={colorRed} />"));
+/// ```
+pub fn import_replace_counted(content: &str, hex_to_const: &HashMap<String, String>, specifier: &str) -> (String, usize) {
+    let (replaced, subs) = import_replace_recorded(content, hex_to_const, specifier);
+    (replaced, subs.len())
+}
+
+/// Like [`import_replace_counted`], also returning every substitution made. A
+/// replaced string literal is recorded with its quotes; the added import is
+/// not a substitution.
+///
+/// ```
+/// use std::collections::HashMap;
+/// use hexvar_core::replace::js::import_replace_recorded;
+///
+/// let lookup = HashMap::from([("#ff0000".to_string(), "colorRed".to_string())]);
+/// let (_, subs) = import_replace_recorded("f('#ff0000', `#FF0000`);\n", &lookup, "./colours");
+/// assert_eq!((subs[0].offset, subs[0].original.as_str(), subs[0].replacement.as_str()), (2, "'#ff0000'", "colorRed"));
+/// assert_eq!((subs[1].offset, subs[1].original.as_str(), subs[1].replacement.as_str()), (14, "#FF0000", "${colorRed}"));
+/// ```
+pub fn import_replace_recorded(
+    content: &str,
+    hex_to_const: &HashMap<String, String>,
+    specifier: &str,
+) -> (String, Vec<Substitution>) {
+    let compiled: Vec<(Regex, String)> = hex_to_const
+        .iter()
+        .map(|(hex, name)| (Regex::new(&format!(r"(?i){}", regex::escape(hex))).unwrap(), format!("${{{}}}", name)))
+        .collect();
+    let mut used = BTreeSet::new();
+    let mut subs = Vec::new();
+    let mut out = String::with_capacity(content.len());
+    let mut last = 0;
+    for (range, kind) in literals(content) {
+        let text = &content[range.clone()];
+        let replaced = match kind {
+            Literal::String => {
+                let inner = text.get(1..text.len().saturating_sub(1)).unwrap_or_default();
+                hex_to_const.get(&inner.to_lowercase()).map(|name| {
+                    used.insert(name.clone());
+                    let replacement =
+                        if in_jsx_attribute(&content[..range.start]) { format!("{{{}}}", name) } else { name.clone() };
+                    subs.push(Substitution {
+                        offset: range.start,
+                        original: text.to_string(),
+                        replacement: replacement.clone(),
+                    });
+                    replacement
+                })
+            }
+            Literal::Template => {
+                let before = subs.len();
+                let text = replace_all_compiled(text, range.start, &compiled, &mut subs);
+                for sub in &subs[before..] {
+                    used.insert(hex_to_const[&sub.original.to_lowercase()].clone());
+                }
+                (subs.len() > before).then_some(text)
+            }
+        };
+        if let Some(replaced) = replaced {
+            out.push_str(&content[last..range.start]);
+            out.push_str(&replaced);
+            last = range.end;
+        }
+    }
+    out.push_str(&content[last..]);
+    if used.is_empty() {
+        return (out, subs);
+    }
+    (add_import(&out, &used, specifier), subs)
+}
+
+/// Whether a string starting right after `before` is a JSX attribute value like
+/// `<Icon fill="#f00"`, which needs braces around an expression
+fn in_jsx_attribute(before: &str) -> bool {
+    let Some(name) = before.strip_suffix('=') else { return false };
+    let name_start = name.trim_end_matches(|c: char| c.is_alphanumeric() || c == '-' || c == '_' || c == ':');
+    if name_start.len() == name.len() || !name_start.ends_with(char::is_whitespace) {
+        return false;
+    }
+    let Some(tag) = name_start.rfind(['<', '>', ';']) else { return false };
+    name_start[tag..].starts_with('<') && name_start[tag + 1..].starts_with(|c: char| c.is_ascii_alphabetic())
+}
+
+/// Add `names` to the named import from `specifier`, or insert a new import
+/// after any leading directives like `'use client';`
+fn add_import(content: &str, names: &BTreeSet<String>, specifier: &str) -> String {
+    let import_re = Regex::new(&format!(
+        r#"import\s*\{{([^}}]*)\}}\s*from\s*['"]{}['"]"#,
+        regex::escape(specifier)
+    ))
+    .unwrap();
+    if let Some(caps) = import_re.captures(content) {
+        let existing = caps.get(1).unwrap();
+        let mut all: BTreeSet<String> =
+            existing.as_str().split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_string).collect();
+        all.extend(names.iter().cloned());
+        let list = all.into_iter().collect::<Vec<_>>().join(", ");
+        return format!("{} {} {}", &content[..existing.start()], list, &content[existing.end()..]);
+    }
+    let import = format!(
+        "import {{ {} }} from '{}';\n",
+        names.iter().cloned().collect::<Vec<_>>().join(", "),
+        specifier
+    );
+    let directive_re = Regex::new(r#"^(?:\s*['"]use [\w ]+['"];?[^\S\n]*\n)*"#).unwrap();
+    let at = directive_re.find(content).map_or(0, |m| m.end());
+    format!("{}{}{}", &content[..at], import, &content[at..])
+}
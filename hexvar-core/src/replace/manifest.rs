@@ -0,0 +1,118 @@
+use super::Substitution;
+use crate::error::{HexvarError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+/// Default path of the manifest `replace` appends its substitutions to
+pub const DEFAULT_MANIFEST_FILE: &str = "hexvar_replace_manifest.json";
+
+/// One substitution made by `replace`, located in the file as it was before
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub file: String,
+    /// 1-based line of the original value
+    pub line: usize,
+    /// 1-based column of the original value, in characters
+    pub column: usize,
+    pub original: String,
+    pub replacement: String,
+    /// Modification time of the rewritten file, in milliseconds since the Unix
+    /// epoch. Missing from manifests written before `--incremental` existed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mtime: Option<u64>,
+}
+
+impl ManifestEntry {
+    /// Locate each of `subs`, made to `content`, as an entry for `file`
+    ///
+    /// ```
+    /// use hexvar_core::replace::Substitution;
+    /// use hexvar_core::replace::manifest::ManifestEntry;
+    ///
+    /// let content = "a {\n  color: #1a2b3c;\n}\n";
+    /// let sub = Substitution { offset: 13, original: "#1a2b3c".into(), replacement: "var(--color-primary)".into() };
+    /// let entries = ManifestEntry::from_substitutions("src/app.css", content, &[sub]);
+    /// assert_eq!((entries[0].line, entries[0].column), (2, 10));
+    /// assert_eq!(entries[0].mtime, None);
+    /// ```
+    pub fn from_substitutions(file: &str, content: &str, subs: &[Substitution]) -> Vec<ManifestEntry> {
+        subs.iter()
+            .map(|sub| {
+                let before = &content[..sub.offset];
+                let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+                ManifestEntry {
+                    file: file.to_string(),
+                    line: before.matches('\n').count() + 1,
+                    column: before[line_start..].chars().count() + 1,
+                    original: sub.original.clone(),
+                    replacement: sub.replacement.clone(),
+                    mtime: None,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Modification time of `path` in milliseconds since the Unix epoch, or `None`
+/// if it can't be read
+pub fn mtime_ms(path: &Path) -> Option<u64> {
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_millis() as u64)
+}
+
+/// What `replace --incremental` knows about the previous run from its manifest
+#[derive(Debug, Clone, Default)]
+pub struct LastRun {
+    /// When the manifest was last written
+    written: u64,
+    /// Modification time of each file right after it was last rewritten
+    mtimes: HashMap<String, u64>,
+}
+
+impl LastRun {
+    /// Read the manifest at `path`, or `None` if there is no manifest yet
+    pub fn load(path: &Path) -> Result<Option<LastRun>> {
+        let Some(written) = mtime_ms(path) else {
+            return Ok(None);
+        };
+        let mut mtimes = HashMap::new();
+        for entry in load(path)? {
+            if let Some(mtime) = entry.mtime {
+                mtimes.insert(entry.file, mtime);
+            }
+        }
+        Ok(Some(LastRun { written, mtimes }))
+    }
+
+    /// Whether `path`, recorded as `file` in the manifest, hasn't been modified
+    /// since the previous run: it predates the manifest, or still has the
+    /// modification time it had when that run rewrote it
+    pub fn is_unchanged(&self, file: &str, path: &Path) -> bool {
+        match mtime_ms(path) {
+            Some(mtime) => mtime < self.written || self.mtimes.get(file) == Some(&mtime),
+            None => false,
+        }
+    }
+}
+
+/// Read the entries recorded in the manifest at `path`, or none if it doesn't exist
+pub fn load(path: &Path) -> Result<Vec<ManifestEntry>> {
+    match fs::read_to_string(path) {
+        Ok(json) => serde_json::from_str(&json).map_err(|e| HexvarError::json(path, e)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(HexvarError::io(path, e)),
+    }
+}
+
+/// Add `entries` to the end of the manifest at `path`, keeping those of
+/// earlier runs so it records the full history of replacements
+pub fn append(path: &Path, entries: &[ManifestEntry]) -> Result<()> {
+    let mut all = load(path)?;
+    all.extend_from_slice(entries);
+    let json = serde_json::to_string_pretty(&all).map_err(|e| HexvarError::json(path, e))?;
+    fs::write(path, json).map_err(|e| HexvarError::io(path, e))
+}
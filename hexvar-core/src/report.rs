@@ -0,0 +1,485 @@
+use crate::accessibility::WcagContrast;
+use crate::cluster::{var_name_for, Cluster};
+use crate::color::hex_to_rgba;
+use crate::error::{self, HexvarError};
+use crate::matrix::NearDuplicate;
+use crate::scanner::ScanTally;
+use serde::de::{Deserializer, IgnoredAny, MapAccess, Visitor};
+use serde::ser::{SerializeMap, Serializer};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// JSON report written by `hexvar scan`: an object keyed by hex code, whose
+/// key order is kept so a sorted report serializes deterministically
+pub struct ColorReport(pub Vec<(String, ColorEntry)>);
+
+/// How JSON written by `hexvar scan` is laid out
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JsonStyle {
+    /// One entry per line, indented by this many spaces per level
+    Pretty { indent: usize },
+    /// No whitespace at all
+    Compact,
+}
+
+impl Default for JsonStyle {
+    fn default() -> Self {
+        JsonStyle::Pretty { indent: 2 }
+    }
+}
+
+impl JsonStyle {
+    /// Serialize `value` to `writer` in this style
+    pub fn to_writer<W: io::Write, T: Serialize + ?Sized>(self, writer: W, value: &T) -> serde_json::Result<()> {
+        match self {
+            JsonStyle::Compact => value.serialize(&mut serde_json::Serializer::new(writer)),
+            JsonStyle::Pretty { indent } => {
+                let indent = " ".repeat(indent);
+                let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+                value.serialize(&mut serde_json::Serializer::with_formatter(writer, formatter))
+            }
+        }
+    }
+
+    /// Serialize `value` to a string in this style
+    ///
+    /// ```
+    /// use hexvar_core::report::JsonStyle;
+    ///
+    /// let value = serde_json::json!({"#fff": {"count": 2}});
+    /// assert_eq!(JsonStyle::Compact.to_string(&value).unwrap(), r##"{"#fff":{"count":2}}"##);
+    /// assert_eq!(JsonStyle::Pretty { indent: 4 }.to_string(&value).unwrap(), "{\n    \"#fff\": {\n        \"count\": 2\n    }\n}");
+    /// ```
+    pub fn to_string<T: Serialize + ?Sized>(self, value: &T) -> serde_json::Result<String> {
+        let mut out = Vec::new();
+        self.to_writer(&mut out, value)?;
+        Ok(String::from_utf8(out).expect("serde_json writes UTF-8"))
+    }
+}
+
+/// Key of the per-file counts in a report written with `scan --per-file`,
+/// skipped when loading a report
+pub const FILES_KEY: &str = "files";
+
+/// Key of the near-duplicate canonical color pairs written by `scan --min-delta-e`
+pub const NEAR_DUPLICATES_KEY: &str = "near_duplicates";
+
+/// Key of the files `scan --encoding` couldn't decode
+pub const DECODE_ERRORS_KEY: &str = "decode_errors";
+
+/// A report followed by a `"files"` entry of path -> unique color count, most
+/// first, a `"near_duplicates"` entry, and a `"decode_errors"` list, each left
+/// out when empty
+pub struct SectionedReport<'a> {
+    pub report: &'a ColorReport,
+    pub files: Vec<(&'a str, u32)>,
+    pub near_duplicates: &'a [NearDuplicate],
+    pub decode_errors: &'a BTreeSet<String>,
+}
+
+/// Per-file counts serialized as an object in their given order
+struct FileCounts<'a>(&'a [(&'a str, u32)]);
+
+impl Serialize for FileCounts<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|(file, n)| (file, n)))
+    }
+}
+
+impl Serialize for SectionedReport<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        for (hex, entry) in &self.report.0 {
+            map.serialize_entry(hex, entry)?;
+        }
+        if !self.files.is_empty() {
+            map.serialize_entry(FILES_KEY, &FileCounts(&self.files))?;
+        }
+        if !self.near_duplicates.is_empty() {
+            map.serialize_entry(NEAR_DUPLICATES_KEY, self.near_duplicates)?;
+        }
+        if !self.decode_errors.is_empty() {
+            map.serialize_entry(DECODE_ERRORS_KEY, self.decode_errors)?;
+        }
+        map.end()
+    }
+}
+
+/// Key order of the JSON report
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+#[serde(rename_all = "kebab-case")]
+pub enum ReportSort {
+    /// Most-used colors first
+    CountDesc,
+    /// Least-used colors first
+    CountAsc,
+    /// By variable name
+    Alpha,
+    /// By numeric RGBA value
+    Hex,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct ColorEntry {
+    pub count: u32,
+    /// Original non-hex spellings (e.g. `rgb(255, 0, 0)`) normalized to this hex
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub locations: Vec<Location>,
+    /// How often the color is used in each CSS property (only recorded with `--track-properties`)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub properties: BTreeMap<String, u32>,
+    /// Contrast against white and black, for canonical colors when requested
+    #[serde(flatten, default, skip_serializing_if = "Option::is_none")]
+    pub wcag: Option<WcagContrast>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Location {
+    pub file: String,
+    /// 1-based line of the match, counted from `block_line` when it's set
+    pub line: usize,
+    pub column: usize,
+    /// Line of a Svelte or Astro component that the `<style>` block holding
+    /// the match starts on; absent for other files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_line: Option<usize>,
+    /// How the color was written, for matches that aren't hex codes (e.g. `red` or `rgb(255, 0, 0)`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spelling: Option<String>,
+    /// Color function the match was written with (`rgb`, `hsl`, `oklch`, `lch`,
+    /// `color-mix`, or `named`); absent for hex codes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_format: Option<String>,
+    /// Element and attribute of an SVG match, e.g. `<rect fill>`; elsewhere the
+    /// CSS property whose value holds it, e.g. `border`, or `gradient` inside a
+    /// `*-gradient()` function. In a Tailwind config, the keys leading to it,
+    /// e.g. `colors.brand.500`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+    /// Whether the match is inside a comment (only recorded with `--include-comments`)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub in_comment: bool,
+    /// Whether the match is one of the colors inside a `color-mix()` call
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub in_color_mix: bool,
+    /// Whether the match is the value of an SCSS `$var` or Less `@var`
+    /// declaration (only recorded with `--scss-aware`)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_variable_declaration: bool,
+    /// Kind of definition the match was read from: `tailwind_config` for the
+    /// `colors` of a Tailwind config; absent for stylesheets and scripts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+impl Location {
+    /// Line of the match in the whole file, for matches in a component's `<style>` block too
+    pub fn file_line(&self) -> usize {
+        self.block_line.map_or(self.line, |start| start + self.line - 1)
+    }
+}
+
+impl ColorReport {
+    /// Build a report from scan results, sorting each color's locations
+    pub fn from_tally(tally: &ScanTally) -> ColorReport {
+        ColorReport(
+            tally
+                .counts
+                .iter()
+                .map(|(hex, &count)| {
+                    let sources = tally.sources.get(hex).map(|s| s.iter().cloned().collect()).unwrap_or_default();
+                    let mut locations = tally.locations.get(hex).cloned().unwrap_or_default();
+                    locations.sort();
+                    let properties = tally.properties.get(hex).cloned().unwrap_or_default();
+                    (hex.clone(), ColorEntry { count, sources, locations, properties, wcag: None })
+                })
+                .collect(),
+        )
+    }
+
+    /// Load a report written by `hexvar scan --out`, or by `--yaml` for a
+    /// `.yaml` or `.yml` file
+    pub fn load(path: &Path) -> error::Result<ColorReport> {
+        let text = fs::read_to_string(path).map_err(|e| HexvarError::io(path, e))?;
+        let yaml = path.extension().is_some_and(|e| e.eq_ignore_ascii_case("yaml") || e.eq_ignore_ascii_case("yml"));
+        if yaml {
+            serde_yaml::from_str(&text).map_err(|source| HexvarError::Yaml { path: path.to_path_buf(), source })
+        } else {
+            serde_json::from_str(&text).map_err(|e| HexvarError::json(path, e))
+        }
+    }
+
+    /// The report as YAML with colors in alphabetical order. Locations are
+    /// written in flow style, one `{...}` line each, to stay readable.
+    ///
+    /// ```
+    /// use hexvar_core::ColorReport;
+    ///
+    /// let json = r##"{"#ff0000": {"count": 2, "locations": [{"file": "a.css", "line": 3, "column": 10}]}, "#00ff00": {"count": 1}}"##;
+    /// let report: ColorReport = serde_json::from_str(json).unwrap();
+    /// let yaml = report.to_yaml();
+    /// assert_eq!(
+    ///     yaml,
+    ///     "'#00ff00':\n  count: 1\n'#ff0000':\n  count: 2\n  locations:\n  - {\"file\":\"a.css\",\"line\":3,\"column\":10}\n"
+    /// );
+    /// let back: ColorReport = serde_yaml::from_str(&yaml).unwrap();
+    /// assert_eq!(back.by_hex()["#ff0000"].locations, report.by_hex()["#ff0000"].locations);
+    /// ```
+    pub fn to_yaml(&self) -> String {
+        let mut entries: Vec<&(String, ColorEntry)> = self.0.iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        if entries.is_empty() {
+            return "{}\n".to_string();
+        }
+        let mut out = String::new();
+        for (hex, entry) in entries {
+            // Keys like `#ff0000` would start a comment unquoted
+            out.push_str(serde_yaml::to_string(hex).unwrap().trim_end());
+            out.push_str(":\n");
+            let fields = ColorEntry { locations: Vec::new(), ..entry.clone() };
+            for line in serde_yaml::to_string(&fields).unwrap().lines() {
+                out.push_str("  ");
+                out.push_str(line);
+                out.push('\n');
+            }
+            if !entry.locations.is_empty() {
+                out.push_str("  locations:\n");
+                // JSON objects are valid YAML flow mappings
+                for loc in &entry.locations {
+                    out.push_str("  - ");
+                    out.push_str(&serde_json::to_string(loc).unwrap());
+                    out.push('\n');
+                }
+            }
+        }
+        out
+    }
+
+    /// Add `other`'s counts, sources, and locations to this report. Colors only
+    /// in `other` are appended with their entries unchanged.
+    ///
+    /// ```
+    /// use hexvar_core::ColorReport;
+    ///
+    /// let mut a: ColorReport = serde_json::from_str(r##"{"#ff0000": {"count": 2}}"##).unwrap();
+    /// let b: ColorReport = serde_json::from_str(r##"{"#ff0000": {"count": 3}, "#00ff00": {"count": 1}}"##).unwrap();
+    /// a.merge(&b);
+    /// let counts = a.by_hex();
+    /// assert_eq!((counts["#ff0000"].count, counts["#00ff00"].count), (5, 1));
+    /// ```
+    pub fn merge(&mut self, other: &ColorReport) {
+        let mut index: HashMap<String, usize> = self.0.iter().enumerate().map(|(i, (hex, _))| (hex.clone(), i)).collect();
+        for (hex, entry) in &other.0 {
+            let Some(&i) = index.get(hex) else {
+                index.insert(hex.clone(), self.0.len());
+                self.0.push((hex.clone(), entry.clone()));
+                continue;
+            };
+            let mine = &mut self.0[i].1;
+            mine.count += entry.count;
+            mine.sources.extend(entry.sources.iter().cloned());
+            mine.sources.sort();
+            mine.sources.dedup();
+            mine.locations.extend(entry.locations.iter().cloned());
+            mine.locations.sort();
+            for (property, n) in &entry.properties {
+                *mine.properties.entry(property.clone()).or_insert(0) += n;
+            }
+            mine.wcag = mine.wcag.or(entry.wcag);
+        }
+    }
+
+    /// Subtract `other`'s counts from this report, dropping its locations and
+    /// any color whose count reaches zero
+    ///
+    /// ```
+    /// use hexvar_core::ColorReport;
+    ///
+    /// let mut a: ColorReport = serde_json::from_str(r##"{"#ff0000": {"count": 5}, "#00ff00": {"count": 1}}"##).unwrap();
+    /// let b: ColorReport = serde_json::from_str(r##"{"#ff0000": {"count": 2}, "#00ff00": {"count": 4}}"##).unwrap();
+    /// a.subtract(&b);
+    /// assert_eq!(a.0.len(), 1);
+    /// assert_eq!(a.by_hex()["#ff0000"].count, 3);
+    /// ```
+    pub fn subtract(&mut self, other: &ColorReport) {
+        let other = other.by_hex();
+        for (hex, entry) in &mut self.0 {
+            if let Some(theirs) = other.get(hex.as_str()) {
+                entry.count = entry.count.saturating_sub(theirs.count);
+                entry.locations.retain(|loc| !theirs.locations.contains(loc));
+            }
+        }
+        self.0.retain(|(_, entry)| entry.count > 0);
+    }
+
+    /// Scan results with this report's counts, sources, and locations, e.g. to
+    /// cluster a merged report
+    pub fn to_tally(&self) -> ScanTally {
+        let mut tally = ScanTally::default();
+        for (hex, entry) in &self.0 {
+            tally.counts.insert(hex.clone(), entry.count);
+            if !entry.sources.is_empty() {
+                tally.sources.insert(hex.clone(), entry.sources.iter().cloned().collect());
+            }
+            if !entry.locations.is_empty() {
+                tally.locations.insert(hex.clone(), entry.locations.clone());
+            }
+            if !entry.properties.is_empty() {
+                tally.properties.insert(hex.clone(), entry.properties.clone());
+            }
+        }
+        tally
+    }
+
+    /// Entries keyed by hex code
+    pub fn by_hex(&self) -> HashMap<&str, &ColorEntry> {
+        self.0.iter().map(|(hex, entry)| (hex.as_str(), entry)).collect()
+    }
+
+    /// Add WCAG contrast ratings to the entry of each cluster's canonical color
+    pub fn add_wcag(&mut self, clusters: &[Cluster]) {
+        let canonical: HashMap<&str, &Cluster> = clusters.iter().map(|c| (c.hex.as_str(), c)).collect();
+        for (hex, entry) in &mut self.0 {
+            if canonical.contains_key(hex.as_str()) {
+                entry.wcag = WcagContrast::for_hex(hex);
+            }
+        }
+    }
+
+    /// Reorder entries. `Alpha` sorts by the variable name of each color's
+    /// cluster, falling back to the default name for colors in no cluster.
+    /// Ties are broken by hex code so the order is fully deterministic.
+    pub fn sort(&mut self, order: ReportSort, clusters: &[Cluster]) {
+        match order {
+            ReportSort::CountDesc => self.0.sort_by(|a, b| b.1.count.cmp(&a.1.count).then_with(|| a.0.cmp(&b.0))),
+            ReportSort::CountAsc => self.0.sort_by(|a, b| a.1.count.cmp(&b.1.count).then_with(|| a.0.cmp(&b.0))),
+            ReportSort::Alpha => {
+                let names: HashMap<&str, &str> = clusters
+                    .iter()
+                    .flat_map(|c| c.members.iter().map(move |m| (m.as_str(), c.name.as_str())))
+                    .collect();
+                self.0.sort_by_cached_key(|(hex, _)| {
+                    let name = names.get(hex.as_str()).map(|n| n.to_string()).unwrap_or_else(|| var_name_for(hex));
+                    (name, hex.clone())
+                });
+            }
+            ReportSort::Hex => self.0.sort_by_cached_key(|(hex, _)| (hex_to_rgba(hex), hex.clone())),
+        }
+    }
+}
+
+/// Write the JSON report for `tally` to `writer` one entry at a time, instead of
+/// building a [`ColorReport`] and the whole JSON text first. Only the sorted
+/// keys are held besides the tally itself. Colors are ordered by `order`, or
+/// by hex code without one; `wcag` adds contrast ratings to canonical colors.
+/// Per-file counts in the tally are written next under `"files"`, most first,
+/// and any `near_duplicates` last.
+///
+/// ```
+/// use std::collections::{HashMap, HashSet};
+/// use hexvar_core::report::{write_streaming, JsonStyle};
+/// use hexvar_core::scanner::ScanTally;
+///
+/// let tally = ScanTally { counts: HashMap::from([("#fff".to_string(), 2), ("#000".to_string(), 1)]), ..Default::default() };
+/// let mut out = Vec::new();
+/// write_streaming(&tally, None, &[], false, &[], JsonStyle::default(), &mut out).unwrap();
+/// let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+/// assert_eq!(json["#fff"]["count"], 2);
+/// assert!(String::from_utf8(out).unwrap().find("#000") < Some(10));
+/// ```
+pub fn write_streaming<W: io::Write>(
+    tally: &ScanTally,
+    order: Option<ReportSort>,
+    clusters: &[Cluster],
+    wcag: bool,
+    near_duplicates: &[NearDuplicate],
+    style: JsonStyle,
+    writer: W,
+) -> serde_json::Result<()> {
+    let mut keys = ColorReport(
+        tally
+            .counts
+            .iter()
+            .map(|(hex, &count)| (hex.clone(), ColorEntry { count, ..ColorEntry::default() }))
+            .collect(),
+    );
+    match order {
+        Some(order) => keys.sort(order, clusters),
+        None => keys.0.sort_by(|a, b| a.0.cmp(&b.0)),
+    }
+    let canonical: HashSet<&str> = if wcag { clusters.iter().map(|c| c.hex.as_str()).collect() } else { HashSet::new() };
+    let keys: Vec<(String, u32)> = keys.0.into_iter().map(|(hex, entry)| (hex, entry.count)).collect();
+    style.to_writer(writer, &StreamingReport { tally, keys, canonical, near_duplicates })
+}
+
+/// The report [`write_streaming`] writes, building each entry as it is serialized
+struct StreamingReport<'a> {
+    tally: &'a ScanTally,
+    /// Hex codes and counts in output order
+    keys: Vec<(String, u32)>,
+    /// Canonical colors to add contrast ratings to
+    canonical: HashSet<&'a str>,
+    near_duplicates: &'a [NearDuplicate],
+}
+
+impl Serialize for StreamingReport<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let tally = self.tally;
+        let mut map = serializer.serialize_map(Some(self.keys.len()))?;
+        for (hex, count) in &self.keys {
+            let sources = tally.sources.get(hex).map(|s| s.iter().cloned().collect()).unwrap_or_default();
+            let mut locations = tally.locations.get(hex).cloned().unwrap_or_default();
+            locations.sort();
+            let properties = tally.properties.get(hex).cloned().unwrap_or_default();
+            let wcag = if self.canonical.contains(hex.as_str()) { WcagContrast::for_hex(hex) } else { None };
+            map.serialize_entry(hex, &ColorEntry { count: *count, sources, locations, properties, wcag })?;
+        }
+        if !tally.files.is_empty() {
+            map.serialize_entry(FILES_KEY, &FileCounts(&tally.files_by_count()))?;
+        }
+        if !self.near_duplicates.is_empty() {
+            map.serialize_entry(NEAR_DUPLICATES_KEY, self.near_duplicates)?;
+        }
+        if !tally.decode_errors.is_empty() {
+            map.serialize_entry(DECODE_ERRORS_KEY, &tally.decode_errors)?;
+        }
+        map.end()
+    }
+}
+
+impl Serialize for ColorReport {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|(hex, entry)| (hex, entry)))
+    }
+}
+
+impl<'de> Deserialize<'de> for ColorReport {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ReportVisitor;
+        impl<'de> Visitor<'de> for ReportVisitor {
+            type Value = ColorReport;
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an object of hex codes to color entries")
+            }
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<ColorReport, A::Error> {
+                let mut entries = Vec::new();
+                while let Some(key) = map.next_key::<String>()? {
+                    if key == FILES_KEY || key == NEAR_DUPLICATES_KEY || key == DECODE_ERRORS_KEY {
+                        map.next_value::<IgnoredAny>()?;
+                        continue;
+                    }
+                    entries.push((key, map.next_value()?));
+                }
+                Ok(ColorReport(entries))
+            }
+        }
+        deserializer.deserialize_map(ReportVisitor)
+    }
+}
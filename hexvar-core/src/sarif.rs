@@ -0,0 +1,212 @@
+use crate::scanner::svg::is_css_context;
+use crate::scanner::ScanTally;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Rule reported for every raw color literal
+pub const RULE_ID: &str = "hexvar/raw-hex-color";
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// A SARIF 2.1.0 log with one run, for GitHub Code Scanning, the VS Code
+/// SARIF viewer, and other tools
+#[derive(Serialize)]
+pub struct SarifLog {
+    #[serde(rename = "$schema")]
+    schema: &'static str,
+    version: &'static str,
+    runs: Vec<Run>,
+}
+
+#[derive(Serialize)]
+struct Run {
+    tool: Tool,
+    results: Vec<SarifResult>,
+}
+
+#[derive(Serialize)]
+struct Tool {
+    driver: Driver,
+}
+
+#[derive(Serialize)]
+struct Driver {
+    name: &'static str,
+    version: &'static str,
+    rules: Vec<Rule>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Rule {
+    id: &'static str,
+    short_description: Message,
+    default_configuration: Configuration,
+}
+
+#[derive(Serialize)]
+struct Configuration {
+    level: &'static str,
+}
+
+#[derive(Serialize)]
+struct Message {
+    text: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifResult {
+    rule_id: &'static str,
+    level: &'static str,
+    message: Message,
+    locations: Vec<SarifLocation>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    fixes: Vec<Fix>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifLocation {
+    physical_location: PhysicalLocation,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PhysicalLocation {
+    artifact_location: ArtifactLocation,
+    region: Region,
+}
+
+#[derive(Serialize, Clone)]
+struct ArtifactLocation {
+    uri: String,
+}
+
+/// 1-based; `end_column` is exclusive
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+struct Region {
+    start_line: usize,
+    start_column: usize,
+    end_column: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Fix {
+    description: Message,
+    artifact_changes: Vec<ArtifactChange>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ArtifactChange {
+    artifact_location: ArtifactLocation,
+    replacements: Vec<Replacement>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Replacement {
+    deleted_region: Region,
+    inserted_content: Message,
+}
+
+impl SarifLog {
+    /// One `warning` result per recorded location in `tally` (which must have
+    /// been scanned with locations). Colors with a variable in `hex_to_var`
+    /// (see [`crate::replace::build_hex_to_var`]) get a fix replacing them with
+    /// `var()`, except in SVG presentation attributes.
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use hexvar_core::sarif::SarifLog;
+    /// use hexvar_core::scanner::{ScanOptions, Scanner};
+    /// use regex::Regex;
+    ///
+    /// let path = std::env::temp_dir().join("hexvar_sarif_doc.css");
+    /// std::fs::write(&path, "a { color: #FF0000; }").unwrap();
+    /// let opts = ScanOptions { locations: true, ..ScanOptions::default() };
+    /// let scanner = Scanner::new(Regex::new(hexvar_core::color::HEX_PATTERN).unwrap(), opts);
+    /// let tally = scanner.scan_paths(&[path], |_, _| {});
+    /// let vars = HashMap::from([("#ff0000".to_string(), "--color-red".to_string())]);
+    ///
+    /// let json = serde_json::to_value(SarifLog::new(&tally, &vars)).unwrap();
+    /// let result = &json["runs"][0]["results"][0];
+    /// assert_eq!(result["ruleId"], "hexvar/raw-hex-color");
+    /// assert_eq!(result["locations"][0]["physicalLocation"]["region"]["startColumn"], 12);
+    /// let replacement = &result["fixes"][0]["artifactChanges"][0]["replacements"][0];
+    /// assert_eq!(replacement["insertedContent"]["text"], "var(--color-red)");
+    /// ```
+    pub fn new(tally: &ScanTally, hex_to_var: &HashMap<String, String>) -> SarifLog {
+        let mut located: Vec<_> =
+            tally.locations.iter().flat_map(|(hex, locs)| locs.iter().map(move |loc| (hex, loc))).collect();
+        located.sort_by(|a, b| (&a.1.file, a.1.file_line(), a.1.column).cmp(&(&b.1.file, b.1.file_line(), b.1.column)));
+
+        let results = located
+            .into_iter()
+            .map(|(hex, loc)| {
+                let text = loc.spelling.as_deref().unwrap_or(hex);
+                let artifact = ArtifactLocation { uri: loc.file.replace('\\', "/") };
+                let region = Region {
+                    start_line: loc.file_line(),
+                    start_column: loc.column,
+                    end_column: loc.column + text.chars().count(),
+                };
+                let var = hex_to_var
+                    .get(&text.to_lowercase())
+                    .or_else(|| hex_to_var.get(&hex.to_lowercase()))
+                    // Only SVG contexts, which start with `<`, can rule out var()
+                    .filter(|_| loc.context.as_deref().is_none_or(|c| !c.starts_with('<') || is_css_context(c)))
+                    // A resolved color-mix() overlaps the fixes for the colors inside it
+                    .filter(|_| loc.source_format.as_deref() != Some("color-mix"));
+                let fixes = var
+                    .map(|var| Fix {
+                        description: Message { text: format!("Replace {} with var({})", text, var) },
+                        artifact_changes: vec![ArtifactChange {
+                            artifact_location: artifact.clone(),
+                            replacements: vec![Replacement {
+                                deleted_region: region,
+                                inserted_content: Message { text: format!("var({})", var) },
+                            }],
+                        }],
+                    })
+                    .into_iter()
+                    .collect();
+                let message = match var {
+                    Some(var) => format!("Raw color {} can use the CSS variable {}", text, var),
+                    None => format!("Raw color {}", text),
+                };
+                SarifResult {
+                    rule_id: RULE_ID,
+                    level: "warning",
+                    message: Message { text: message },
+                    locations: vec![SarifLocation { physical_location: PhysicalLocation { artifact_location: artifact, region } }],
+                    fixes,
+                }
+            })
+            .collect();
+
+        SarifLog {
+            schema: SCHEMA,
+            version: "2.1.0",
+            runs: vec![Run {
+                tool: Tool {
+                    driver: Driver {
+                        name: "hexvar",
+                        version: env!("CARGO_PKG_VERSION"),
+                        rules: vec![Rule {
+                            id: RULE_ID,
+                            short_description: Message {
+                                text: "Color literal used instead of a CSS variable".to_string(),
+                            },
+                            default_configuration: Configuration { level: "warning" },
+                        }],
+                    },
+                },
+                results,
+            }],
+        }
+    }
+}
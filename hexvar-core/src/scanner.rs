@@ -0,0 +1,921 @@
+use crate::cache::{file_stamp, CacheEntry, ScanCache, CACHE_VERSION};
+use crate::color::{
+    hsl_to_hex, lch_to_hex, named_color_to_hex, oklch_to_hex, rgb_to_hex, DECLARATION_PATTERN, HSL_PATTERN, LCH_PATTERN,
+    OKLCH_PATTERN, RGB_PATTERN,
+};
+use crate::color_mix_resolver::{self, color_mix_ranges};
+use crate::error::{HexvarError, Result};
+use crate::encoding::SourceEncoding;
+use crate::paths::{display_name, is_stdin, map_source_bytes, read_source_bytes, SourceBytes, STDIN_NAME};
+use crate::preprocessor::{comment_ranges, has_line_comments, strip_comments};
+use crate::report::Location;
+use rayon::prelude::*;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::{debug, trace};
+
+pub mod component;
+pub mod svg;
+pub mod tailwind_config;
+
+/// Scan results for a set of files: counts keyed by hex, plus optional detail
+#[derive(Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScanTally {
+    pub counts: HashMap<String, u32>,
+    /// Original non-hex spellings (e.g. `rgb(255, 0, 0)` or `hsl(0, 100%, 50%)`) for each normalized hex
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub sources: HashMap<String, BTreeSet<String>>,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub locations: HashMap<String, Vec<Location>>,
+    /// Number of unique colors in each file, when scanned with [`ScanOptions::per_file`]
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub files: HashMap<String, u32>,
+    /// The colors counted in [`ScanTally::files`], so the counts stay right when
+    /// [`ScanTally::retain`] drops colors
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub file_colors: HashMap<String, BTreeSet<String>>,
+    /// SCSS and Less variables (e.g. `$brand-blue`) declared with each color as
+    /// their value, when scanned with [`ScanOptions::scss_aware`]
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub variables: HashMap<String, BTreeSet<String>>,
+    /// How often each color is used in each CSS property, e.g. `border: 3`, when
+    /// scanned with [`ScanOptions::track_properties`]
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub properties: HashMap<String, BTreeMap<String, u32>>,
+    /// Files that aren't valid in [`ScanOptions::encoding`], and so weren't scanned
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    pub decode_errors: BTreeSet<String>,
+}
+
+impl ScanTally {
+    /// Combine two tallies, summing counts
+    pub fn merge(mut self, other: ScanTally) -> ScanTally {
+        self.counts = merge_counts(self.counts, other.counts);
+        for (hex, srcs) in other.sources {
+            self.sources.entry(hex).or_default().extend(srcs);
+        }
+        for (hex, locs) in other.locations {
+            self.locations.entry(hex).or_default().extend(locs);
+        }
+        for (hex, vars) in other.variables {
+            self.variables.entry(hex).or_default().extend(vars);
+        }
+        for (hex, props) in other.properties {
+            let mine = self.properties.entry(hex).or_default();
+            for (property, n) in props {
+                *mine.entry(property).or_insert(0) += n;
+            }
+        }
+        self.files = merge_counts(self.files, other.files);
+        for (file, colors) in other.file_colors {
+            self.file_colors.entry(file).or_default().extend(colors);
+        }
+        self.decode_errors.extend(other.decode_errors);
+        self
+    }
+
+    /// Add another tally's results into this one
+    pub fn add(&mut self, other: &ScanTally) {
+        for (hex, n) in &other.counts {
+            *self.counts.entry(hex.clone()).or_insert(0) += n;
+        }
+        for (hex, srcs) in &other.sources {
+            self.sources.entry(hex.clone()).or_default().extend(srcs.iter().cloned());
+        }
+        for (hex, locs) in &other.locations {
+            self.locations.entry(hex.clone()).or_default().extend(locs.iter().cloned());
+        }
+        for (file, n) in &other.files {
+            *self.files.entry(file.clone()).or_insert(0) += n;
+        }
+        for (file, colors) in &other.file_colors {
+            self.file_colors.entry(file.clone()).or_default().extend(colors.iter().cloned());
+        }
+        for (hex, vars) in &other.variables {
+            self.variables.entry(hex.clone()).or_default().extend(vars.iter().cloned());
+        }
+        for (hex, props) in &other.properties {
+            let mine = self.properties.entry(hex.clone()).or_default();
+            for (property, n) in props {
+                *mine.entry(property.clone()).or_insert(0) += n;
+            }
+        }
+        self.decode_errors.extend(other.decode_errors.iter().cloned());
+    }
+
+    /// Rewrite every file path in the tally with `rename`, e.g. to make them
+    /// relative to a base directory
+    pub fn map_paths<F: Fn(&str) -> String>(&mut self, rename: F) {
+        for loc in self.locations.values_mut().flatten() {
+            loc.file = rename(&loc.file);
+        }
+        self.files = std::mem::take(&mut self.files).into_iter().map(|(file, n)| (rename(&file), n)).collect();
+        self.file_colors =
+            std::mem::take(&mut self.file_colors).into_iter().map(|(file, colors)| (rename(&file), colors)).collect();
+        self.decode_errors = std::mem::take(&mut self.decode_errors).iter().map(|file| rename(file)).collect();
+    }
+
+    /// Total occurrences of every color
+    pub fn total(&self) -> u32 {
+        self.counts.values().sum()
+    }
+
+    /// Files by their number of unique colors, most first (ties by path)
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use hexvar_core::scanner::ScanTally;
+    ///
+    /// let files = HashMap::from([("a.css".to_string(), 2), ("b.css".to_string(), 5), ("c.css".to_string(), 2)]);
+    /// let tally = ScanTally { files, ..ScanTally::default() };
+    /// assert_eq!(tally.files_by_count(), [("b.css", 5), ("a.css", 2), ("c.css", 2)]);
+    /// ```
+    pub fn files_by_count(&self) -> Vec<(&str, u32)> {
+        let mut files: Vec<(&str, u32)> = self.files.iter().map(|(file, &n)| (file.as_str(), n)).collect();
+        files.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        files
+    }
+
+    /// Keep only colors for which `keep(hex, count)` holds, returning how many were removed.
+    /// Files left without colors are dropped from [`ScanTally::files`], and the rest recounted.
+    ///
+    /// ```
+    /// use hexvar_core::color::HEX_PATTERN;
+    /// use hexvar_core::scanner::{ScanOptions, Scanner};
+    /// use regex::Regex;
+    ///
+    /// let scanner = Scanner::new(Regex::new(HEX_PATTERN).unwrap(), ScanOptions { per_file: true, ..ScanOptions::default() });
+    /// let mut tally = scanner.scan_str("a { color: #fff; border-color: #000 }", "a.css");
+    /// tally.add(&scanner.scan_str("b { color: #fff }", "b.css"));
+    /// assert_eq!(tally.retain(|hex, _| hex != "#ffffff"), 1);
+    /// assert_eq!(tally.files_by_count(), [("a.css", 1)]);
+    /// ```
+    pub fn retain<F: FnMut(&str, u32) -> bool>(&mut self, mut keep: F) -> usize {
+        let before = self.counts.len();
+        self.counts.retain(|hex, count| keep(hex, *count));
+        let counts = &self.counts;
+        self.sources.retain(|hex, _| counts.contains_key(hex));
+        self.locations.retain(|hex, _| counts.contains_key(hex));
+        self.variables.retain(|hex, _| counts.contains_key(hex));
+        self.properties.retain(|hex, _| counts.contains_key(hex));
+        for colors in self.file_colors.values_mut() {
+            colors.retain(|hex| counts.contains_key(hex));
+        }
+        self.file_colors.retain(|_, colors| !colors.is_empty());
+        let file_colors = &self.file_colors;
+        self.files.retain(|file, _| file_colors.contains_key(file));
+        for (file, n) in &mut self.files {
+            *n = file_colors[file].len() as u32;
+        }
+        before - self.counts.len()
+    }
+}
+
+/// What [`Scanner::scan_paths`] reports to its callback for each file it finishes
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FileScanned {
+    /// Colors matched in the file
+    pub matches: u32,
+    /// Time spent reading and scanning the file, or checking it against the cache
+    pub elapsed: Duration,
+}
+
+/// What `Scanner` looks for besides plain hex codes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScanOptions {
+    /// Record the file, line, and column of every match
+    pub locations: bool,
+    /// Also count CSS color keywords like `red` in declaration values
+    #[serde(default)]
+    pub named_colors: bool,
+    /// Also count matches inside `/* */` and `//` comments, marking their locations
+    #[serde(default)]
+    pub comments: bool,
+    /// Record how many unique colors each file contains
+    #[serde(default)]
+    pub per_file: bool,
+    /// Leave out colors inside `*-gradient()` functions, which rarely make good
+    /// single variables since they come in pairs
+    #[serde(default)]
+    pub skip_gradients: bool,
+    /// What happens to the alpha byte of 8-digit hex codes
+    #[serde(default)]
+    pub alpha: AlphaMode,
+    /// Also count the color each `color-mix()` of two hex codes mixes to
+    #[serde(default)]
+    pub resolve_color_mix: bool,
+    /// Also count matches inside the quoted strings of `content` declarations,
+    /// which are text rather than colors
+    #[serde(default)]
+    pub content_strings: bool,
+    /// Recognize SCSS `$var: #hex;` and Less `@var: #hex;` declarations, recording
+    /// the variable in [`ScanTally::variables`] and marking the location
+    #[serde(default)]
+    pub scss_aware: bool,
+    /// How files are decoded before they are scanned
+    #[serde(default)]
+    pub encoding: SourceEncoding,
+    /// Count the CSS properties each color is used in, in [`ScanTally::properties`]
+    #[serde(default)]
+    pub track_properties: bool,
+}
+
+/// How [`normalize_hex_with`] treats the alpha byte of 8-digit hex codes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AlphaMode {
+    /// Count `#rrggbbaa` as its own color
+    #[default]
+    Keep,
+    /// Drop the alpha byte when it is `ff`, so `#1a2b3cff` counts as `#1a2b3c`
+    Opaque,
+    /// Drop the alpha byte of every 8-digit code, even semi-transparent ones
+    Strip,
+}
+
+/// Hex, `rgb()`, `hsl()`, `oklch()` and `lch()` scanner used by the `scan` command
+pub struct Scanner {
+    hex_re: Regex,
+    rgb_re: Regex,
+    hsl_re: Regex,
+    oklch_re: Regex,
+    lch_re: Regex,
+    decl_re: Regex,
+    word_re: Regex,
+    opts: ScanOptions,
+    mmap: bool,
+}
+
+impl Scanner {
+    pub fn new(hex_re: Regex, opts: ScanOptions) -> Scanner {
+        Scanner {
+            hex_re,
+            rgb_re: Regex::new(RGB_PATTERN).unwrap(),
+            hsl_re: Regex::new(HSL_PATTERN).unwrap(),
+            oklch_re: Regex::new(OKLCH_PATTERN).unwrap(),
+            lch_re: Regex::new(LCH_PATTERN).unwrap(),
+            decl_re: Regex::new(DECLARATION_PATTERN).unwrap(),
+            // Identifier-like tokens, so `.red` or `--color-red` never match as `red`
+            word_re: Regex::new(r"[\w.#-]+").unwrap(),
+            opts,
+            mmap: false,
+        }
+    }
+
+    /// Memory-map files instead of reading them. Results are the same either
+    /// way, so this isn't one of the [`ScanOptions`] a cache is tied to.
+    pub fn with_mmap(mut self, mmap: bool) -> Scanner {
+        self.mmap = mmap;
+        self
+    }
+
+    fn read(&self, path: &Path) -> std::io::Result<SourceBytes> {
+        if self.mmap {
+            map_source_bytes(path)
+        } else {
+            read_source_bytes(path).map(SourceBytes::Read)
+        }
+    }
+
+    /// Scan `content` as if it were read from `file`, whose extension decides
+    /// how it's read, e.g. as SVG or with `//` comments. Locations in a
+    /// component's `<style>` block count lines from the block's start.
+    ///
+    /// ```
+    /// use hexvar_core::color::HEX_PATTERN;
+    /// use hexvar_core::scanner::{ScanOptions, Scanner};
+    /// use regex::Regex;
+    ///
+    /// let scanner = Scanner::new(Regex::new(HEX_PATTERN).unwrap(), ScanOptions::default());
+    /// let tally = scanner.scan_str("a { color: #FFF; } // #000", "app.scss");
+    /// assert_eq!(tally.counts.keys().collect::<Vec<_>>(), ["#ffffff"]);
+    ///
+    /// let opts = ScanOptions { locations: true, ..ScanOptions::default() };
+    /// let scanner = Scanner::new(Regex::new(HEX_PATTERN).unwrap(), opts);
+    /// let svelte = "<h1>Hi</h1>\n\n<style>\n  h1 {\n    color: #f00;\n  }\n</style>";
+    /// let loc = &scanner.scan_str(svelte, "App.svelte").locations["#ff0000"][0];
+    /// assert_eq!((loc.line, loc.block_line, loc.file_line()), (3, Some(3), 5));
+    /// ```
+    pub fn scan_str(&self, content: &str, file: &str) -> ScanTally {
+        let mut tally = ScanTally::default();
+        self.scan_into(&mut tally, content, file);
+        tally
+    }
+
+    /// Scan files in parallel, each worker counting into a local tally.
+    /// `on_file` is called once each file is done (or found unreadable), e.g.
+    /// to advance a progress bar.
+    pub fn scan_paths<F>(&self, paths: &[PathBuf], on_file: F) -> ScanTally
+    where
+        F: Fn(&Path, FileScanned) + Sync,
+    {
+        paths
+            .par_iter()
+            .fold(ScanTally::default, |mut local, path| {
+                let start = Instant::now();
+                let before = local.total();
+                match self.read(path) {
+                    Ok(bytes) => self.scan_bytes(&mut local, &bytes, &display_name(path)),
+                    Err(e) => debug!("Skipping {}: {}", path.display(), e),
+                }
+                on_file(path, FileScanned { matches: local.total() - before, elapsed: start.elapsed() });
+                local
+            })
+            .reduce(ScanTally::default, ScanTally::merge)
+    }
+
+    /// Like [`Scanner::scan_paths`], but reuse results from `cache` for files whose
+    /// mtime and size are unchanged. Returns the tally and the updated cache,
+    /// which never holds standard input.
+    pub fn scan_paths_cached<F>(&self, paths: &[PathBuf], cache: &ScanCache, on_file: F) -> (ScanTally, ScanCache)
+    where
+        F: Fn(&Path, FileScanned) + Sync,
+    {
+        let previous = cache.by_path();
+        let files: Vec<CacheEntry> = paths
+            .par_iter()
+            .filter_map(|path| {
+                let start = Instant::now();
+                let entry = self.scan_cached(path, &previous);
+                let matches = entry.as_ref().map_or(0, |e| e.tally.total());
+                on_file(path, FileScanned { matches, elapsed: start.elapsed() });
+                entry
+            })
+            .collect();
+        let mut total = ScanTally::default();
+        for entry in &files {
+            total.add(&entry.tally);
+        }
+        let files = files.into_iter().filter(|entry| entry.path != STDIN_NAME).collect();
+        (total, ScanCache { version: CACHE_VERSION, options: self.opts, files })
+    }
+
+    /// The cache entry for `path`: the previous one if the file is unchanged,
+    /// else a fresh scan. `None` if the file can't be read.
+    fn scan_cached(&self, path: &Path, previous: &HashMap<&str, &CacheEntry>) -> Option<CacheEntry> {
+        let (mtime, size) = if is_stdin(path) { (0, 0) } else { file_stamp(path)? };
+        let key = display_name(path);
+        if let Some(entry) = previous.get(key.as_str()) {
+            if entry.mtime == mtime && entry.size == size {
+                trace!("Reusing cached results for {}", key);
+                return Some((*entry).clone());
+            }
+        }
+        let bytes = self.read(path).inspect_err(|e| debug!("Skipping {}: {}", path.display(), e)).ok()?;
+        let mut tally = ScanTally::default();
+        self.scan_bytes(&mut tally, &bytes, &key);
+        Some(CacheEntry { path: key, mtime, size, tally })
+    }
+
+    /// Decode a file's bytes with [`ScanOptions::encoding`] and scan them, or
+    /// record the file in [`ScanTally::decode_errors`]
+    fn scan_bytes(&self, local: &mut ScanTally, bytes: &[u8], file: &str) {
+        match self.opts.encoding.decode(bytes) {
+            Ok(content) => self.scan_into(local, &content, file),
+            Err(encoding) => {
+                debug!("Skipping {}: not valid {}", file, encoding.name());
+                local.decode_errors.insert(file.to_string());
+            }
+        }
+    }
+
+    fn scan_into(&self, local: &mut ScanTally, content: &str, file: &str) {
+        if !self.opts.per_file {
+            return self.scan_document(local, content, file);
+        }
+        let mut tally = ScanTally::default();
+        self.scan_document(&mut tally, content, file);
+        if !tally.counts.is_empty() {
+            tally.files.insert(file.to_string(), tally.counts.len() as u32);
+            tally.file_colors.insert(file.to_string(), tally.counts.keys().cloned().collect());
+        }
+        *local = std::mem::take(local).merge(tally);
+    }
+
+    fn scan_document(&self, local: &mut ScanTally, content: &str, file: &str) {
+        let starts = if self.opts.locations { line_starts(content) } else { Vec::new() };
+        let doc = Document { content, starts: &starts, file };
+        if svg::is_svg(Path::new(file)) {
+            for fragment in svg::fragments(content) {
+                self.scan_fragment(local, &doc, fragment.range.clone(), Some(&fragment), None);
+            }
+        } else if tailwind_config::is_tailwind_config(Path::new(file)) {
+            self.scan_tailwind_config(local, &doc);
+        } else if component::is_component(Path::new(file)) {
+            for block in component::style_blocks(content) {
+                self.scan_fragment(local, &doc, block.range, None, Some(block.line));
+            }
+        } else {
+            self.scan_fragment(local, &doc, 0..content.len(), None, None);
+        }
+    }
+
+    /// Count the hex codes in the `colors` objects of a Tailwind config, which
+    /// define the palette rather than use it, so nothing else in the file is read
+    fn scan_tailwind_config(&self, local: &mut ScanTally, doc: &Document) {
+        for color in tailwind_config::config_colors(doc.content) {
+            let spelling = &doc.content[color.range.clone()];
+            let hex = normalize_hex_with(spelling, self.opts.alpha);
+            *local.counts.entry(hex.clone()).or_insert(0) += 1;
+            if self.opts.locations {
+                let (line, column) = line_col(doc.content, doc.starts, color.range.start);
+                local.locations.entry(hex.clone()).or_default().push(Location {
+                    file: doc.file.to_string(),
+                    line,
+                    column,
+                    block_line: None,
+                    spelling: None,
+                    source_format: None,
+                    context: Some(color.path),
+                    in_comment: false,
+                    in_color_mix: false,
+                    is_variable_declaration: false,
+                    source: Some(tailwind_config::TAILWIND_CONFIG_SOURCE.to_string()),
+                });
+            }
+            if hex.len() != spelling.len() {
+                local.sources.entry(hex).or_default().insert(spelling.to_ascii_lowercase());
+            }
+        }
+    }
+
+    /// Scan `range` of a document. Named colors count anywhere in an SVG
+    /// presentation attribute, and in declaration values everywhere else.
+    /// Lines are counted from `block_line`, the line a component's `<style>`
+    /// block starts on, when it's given.
+    fn scan_fragment(
+        &self,
+        local: &mut ScanTally,
+        doc: &Document,
+        range: Range<usize>,
+        fragment: Option<&svg::Fragment>,
+        block_line: Option<usize>,
+    ) {
+        let base = range.start;
+        let original = &doc.content[range];
+        let comments = match fragment {
+            Some(f) if !f.kind.is_css() => Vec::new(),
+            Some(_) => comment_ranges(original, false),
+            None => comment_ranges(original, has_line_comments(Path::new(doc.file))),
+        };
+        let stripped;
+        let text = if self.opts.comments || comments.is_empty() {
+            original
+        } else {
+            stripped = strip_comments(original, &comments);
+            &stripped
+        };
+        let mixes = if self.opts.locations || self.opts.resolve_color_mix { color_mix_ranges(text) } else { Vec::new() };
+        let declarations = self.opts.scss_aware && fragment.is_none() && has_variable_declarations(Path::new(doc.file));
+        let skip = |offset: usize| {
+            (self.opts.skip_gradients && declaration_context(text, offset).as_deref() == Some("gradient"))
+                || (!self.opts.content_strings && in_content_string(text, offset))
+        };
+        // `source` is the spelling and function name of a match that isn't a hex code
+        let record = |local: &mut ScanTally, hex: &str, range: Range<usize>, source: Option<(&str, &str)>| {
+            let offset = range.start;
+            let variable = if declarations { variable_declaration(text, range) } else { None };
+            if let Some(name) = variable {
+                local.variables.entry(hex.to_string()).or_default().insert(name.to_string());
+            }
+            // SVG presentation attributes aren't declarations
+            let in_css = fragment.is_none_or(|f| f.kind.is_css());
+            if let Some(property) = declaration_property(text, offset).filter(|_| self.opts.track_properties && in_css) {
+                *local.properties.entry(hex.to_string()).or_default().entry(property).or_insert(0) += 1;
+            }
+            if self.opts.locations {
+                let (line, column) = line_col(doc.content, doc.starts, base + offset);
+                local.locations.entry(hex.to_string()).or_default().push(Location {
+                    file: doc.file.to_string(),
+                    line: block_line.map_or(line, |start| line - start + 1),
+                    column,
+                    block_line,
+                    spelling: source.map(|(spelling, _)| spelling.to_string()),
+                    source_format: source.map(|(_, format)| format.to_string()),
+                    context: match fragment {
+                        Some(f) => Some(f.context.clone()),
+                        None => declaration_context(text, offset),
+                    },
+                    in_comment: comments.iter().any(|r| r.contains(&offset)),
+                    // The call itself starts a mix range but isn't inside it
+                    in_color_mix: source.is_none_or(|(_, format)| format != "color-mix")
+                        && mixes.iter().any(|r| r.contains(&offset)),
+                    is_variable_declaration: variable.is_some(),
+                    source: None,
+                });
+            }
+        };
+        let record_function = |local: &mut ScanTally, hex: String, caps: &regex::Captures, format: &str| {
+            if skip(caps.get(0).unwrap().start()) {
+                return;
+            }
+            *local.counts.entry(hex.clone()).or_insert(0) += 1;
+            record(local, &hex, caps.get(0).unwrap().range(), Some((&caps[0], format)));
+            local.sources.entry(hex).or_default().insert(caps[0].to_string());
+        };
+        for m in self.hex_re.find_iter(text) {
+            if skip(m.start()) {
+                continue;
+            }
+            let hex = normalize_hex_with(m.as_str(), self.opts.alpha);
+            *local.counts.entry(hex.clone()).or_insert(0) += 1;
+            record(local, &hex, m.range(), None);
+            // Short forms are kept as sources so `replace` still finds them,
+            // lowercased like the key since `replace` matches either case
+            if hex.len() != m.len() {
+                local.sources.entry(hex).or_default().insert(m.as_str().to_ascii_lowercase());
+            }
+        }
+        for caps in self.rgb_re.captures_iter(text) {
+            if let Some(hex) = rgb_to_hex(&caps[1], &caps[2], &caps[3]) {
+                record_function(local, hex, &caps, "rgb");
+            }
+        }
+        for caps in self.hsl_re.captures_iter(text) {
+            let value = |i: usize| caps[i].parse::<f32>().unwrap_or(0.0);
+            let hex = hsl_to_hex(value(1), value(2) / 100.0, value(3) / 100.0);
+            record_function(local, hex, &caps, "hsl");
+        }
+        // Percentages scale to the CSS reference ranges: 100% lightness is 1 in
+        // OKLCH and 100 in LCH, and 100% chroma is 0.4 and 150
+        let lch_values = |caps: &regex::Captures, l_scale: f32, c_scale: f32| {
+            let value = |i: usize| caps[i].parse::<f32>().unwrap_or(0.0);
+            let scaled = |i: usize, scale: f32| if caps[i + 1].is_empty() { value(i) } else { value(i) / 100.0 * scale };
+            (scaled(1, l_scale), scaled(3, c_scale), value(5))
+        };
+        for caps in self.oklch_re.captures_iter(text) {
+            let (l, c, h) = lch_values(&caps, 1.0, 0.4);
+            record_function(local, oklch_to_hex(l, c, h), &caps, "oklch");
+        }
+        for caps in self.lch_re.captures_iter(text) {
+            let (l, c, h) = lch_values(&caps, 100.0, 150.0);
+            record_function(local, lch_to_hex(l, c, h), &caps, "lch");
+        }
+        if self.opts.resolve_color_mix {
+            for range in &mixes {
+                if skip(range.start) {
+                    continue;
+                }
+                let call = &text[range.clone()];
+                let Some(hex) = color_mix_resolver::resolve(call) else { continue };
+                // Not added to `sources`: `replace` keeps the call and rewrites the colors inside it
+                *local.counts.entry(hex.clone()).or_insert(0) += 1;
+                record(local, &hex, range.clone(), Some((call, "color-mix")));
+            }
+        }
+        if self.opts.named_colors {
+            let values: Vec<(usize, &str)> = if fragment.is_some_and(|f| !f.kind.is_css()) {
+                vec![(0, text)]
+            } else {
+                self.decl_re
+                    .captures_iter(text)
+                    .map(|caps| caps.get(1).unwrap())
+                    .map(|value| (value.start(), value.as_str()))
+                    .collect()
+            };
+            for (start, value) in values {
+                for word in self.word_re.find_iter(value) {
+                    // Keywords are only recorded in locations, not `sources`, so `replace`
+                    // never rewrites bare words that happen to be color names
+                    let Some(hex) = named_color_to_hex(word.as_str()) else { continue };
+                    if skip(start + word.start()) {
+                        continue;
+                    }
+                    *local.counts.entry(hex.to_string()).or_insert(0) += 1;
+                    record(local, hex, start + word.start()..start + word.end(), Some((word.as_str(), "named")));
+                }
+            }
+        }
+    }
+}
+
+/// The key a hex code is counted under: lowercase, with 3- and 4-digit short
+/// forms expanded to 6 and 8 digits. Anything else is only lowercased.
+///
+/// ```
+/// use hexvar_core::scanner::normalize_hex;
+///
+/// assert_eq!(normalize_hex("#abc"), "#aabbcc");
+/// assert_eq!(normalize_hex("#ABC"), "#aabbcc");
+/// assert_eq!(normalize_hex("#aBc8"), "#aabbcc88");
+/// assert_eq!(normalize_hex("#AABBCC"), "#aabbcc");
+/// assert_eq!(normalize_hex("#aabbcc"), "#aabbcc");
+/// assert_eq!(normalize_hex("#AABBCC80"), "#aabbcc80");
+/// assert_eq!(normalize_hex("#000"), "#000000");
+/// assert_eq!(normalize_hex("#FFFF"), "#ffffffff");
+/// // Not a 3, 4, 6, or 8 digit hex code: only lowercased
+/// assert_eq!(normalize_hex("#ABCDE"), "#abcde");
+/// assert_eq!(normalize_hex("#12"), "#12");
+/// assert_eq!(normalize_hex("#ggg"), "#ggg");
+/// assert_eq!(normalize_hex("abc"), "abc");
+/// assert_eq!(normalize_hex(""), "");
+/// ```
+pub fn normalize_hex(hex: &str) -> String {
+    normalize_hex_with(hex, AlphaMode::Keep)
+}
+
+/// Like [`normalize_hex`], then drops the alpha byte of an 8-digit result as
+/// `alpha` says
+///
+/// ```
+/// use hexvar_core::scanner::{normalize_hex_with, AlphaMode};
+///
+/// assert_eq!(normalize_hex_with("#1A2B3CFF", AlphaMode::Opaque), "#1a2b3c");
+/// assert_eq!(normalize_hex_with("#abcf", AlphaMode::Opaque), "#aabbcc");
+/// assert_eq!(normalize_hex_with("#1a2b3c80", AlphaMode::Opaque), "#1a2b3c80");
+/// assert_eq!(normalize_hex_with("#1a2b3c80", AlphaMode::Strip), "#1a2b3c");
+/// assert_eq!(normalize_hex_with("#1a2b3cff", AlphaMode::Keep), "#1a2b3cff");
+/// ```
+pub fn normalize_hex_with(hex: &str, alpha: AlphaMode) -> String {
+    let mut hex = expand_hex(hex);
+    if hex.len() == 9 && hex.as_bytes()[1..].iter().all(u8::is_ascii_hexdigit) {
+        let strip = match alpha {
+            AlphaMode::Keep => false,
+            AlphaMode::Opaque => hex.ends_with("ff"),
+            AlphaMode::Strip => true,
+        };
+        if strip {
+            hex.truncate(7);
+        }
+    }
+    hex
+}
+
+/// Lowercase `hex` and expand a 3- or 4-digit short form
+fn expand_hex(hex: &str) -> String {
+    let lower = hex.to_ascii_lowercase();
+    let Some(digits) = lower.strip_prefix('#') else { return lower };
+    if !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return lower;
+    }
+    match digits.len() {
+        3 | 4 => {
+            let mut expanded = String::with_capacity(1 + digits.len() * 2);
+            expanded.push('#');
+            for c in digits.chars() {
+                expanded.push(c);
+                expanded.push(c);
+            }
+            expanded
+        }
+        _ => lower,
+    }
+}
+
+/// Normalized hex codes assigned to custom properties in the `:root { }` blocks
+/// of a CSS variables file, i.e. the colors that are already tokens
+///
+/// ```
+/// use hexvar_core::scanner::root_var_colors;
+///
+/// let css = ":root {\n  --color-primary: #1A2B3C;\n  /* --old: #000; */\n  --gap: 4px;\n}\na { color: #fff; --local: #abc; }";
+/// assert_eq!(root_var_colors(css).into_iter().collect::<Vec<_>>(), ["#1a2b3c"]);
+/// ```
+pub fn root_var_colors(css: &str) -> BTreeSet<String> {
+    let css = strip_comments(css, &comment_ranges(css, false));
+    let root_re = Regex::new(r":root\b[^{]*\{([^}]*)\}").unwrap();
+    let hex_re = Regex::new(crate::color::HEX_PATTERN).unwrap();
+    let mut colors = BTreeSet::new();
+    for block in root_re.captures_iter(&css) {
+        for decl in block[1].split(';') {
+            let Some((_, value)) = decl.trim().strip_prefix("--").and_then(|d| d.split_once(':')) else { continue };
+            colors.extend(hex_re.find_iter(value).map(|m| normalize_hex(m.as_str())));
+        }
+    }
+    colors
+}
+
+/// Where in a CSS declaration the match at `offset` sits: `gradient` inside a
+/// `*-gradient()` function, else the lowercase name of the property whose
+/// value it is (`color`, `border`, `box-shadow`, ...). `None` outside any
+/// declaration.
+///
+/// ```
+/// use hexvar_core::scanner::declaration_context;
+///
+/// let css = "a { border: 1px solid #f00; background: linear-gradient(#fff, rgb(0 0 0 / 50%) 40%, #000) }";
+/// assert_eq!(declaration_context(css, css.find("#f00").unwrap()).as_deref(), Some("border"));
+/// assert_eq!(declaration_context(css, css.find("#000").unwrap()).as_deref(), Some("gradient"));
+/// assert_eq!(declaration_context("#f00", 0), None);
+/// ```
+pub fn declaration_context(text: &str, offset: usize) -> Option<String> {
+    let (property, value) = declaration_at(text, offset)?;
+    // Names of the function calls still open at `offset`
+    let mut open: Vec<&str> = Vec::new();
+    for (i, c) in value.char_indices() {
+        match c {
+            '(' => {
+                let name_start = value[..i].rfind(|c: char| !(c.is_ascii_alphanumeric() || c == '-')).map_or(0, |j| j + 1);
+                open.push(&value[name_start..i]);
+            }
+            ')' => {
+                open.pop();
+            }
+            _ => {}
+        }
+    }
+    if open.iter().any(|name| name.to_ascii_lowercase().ends_with("gradient")) {
+        return Some("gradient".to_string());
+    }
+    Some(property.to_ascii_lowercase())
+}
+
+/// The lowercase name of the CSS property whose value holds the match at
+/// `offset`, even inside a function like `linear-gradient()`. `None` outside
+/// any declaration.
+///
+/// ```
+/// use hexvar_core::scanner::declaration_property;
+///
+/// let css = "a { Border: 1px solid #f00; background: linear-gradient(#fff, #000); --brand: #123 }";
+/// assert_eq!(declaration_property(css, css.find("#f00").unwrap()).as_deref(), Some("border"));
+/// assert_eq!(declaration_property(css, css.find("#000").unwrap()).as_deref(), Some("background"));
+/// assert_eq!(declaration_property(css, css.find("#123").unwrap()).as_deref(), Some("--brand"));
+/// assert_eq!(declaration_property("a:hover { } #f00", 15), None);
+/// ```
+pub fn declaration_property(text: &str, offset: usize) -> Option<String> {
+    declaration_at(text, offset).map(|(property, _)| property.to_ascii_lowercase())
+}
+
+/// The property of the declaration `offset` is in, and its value up to `offset`
+fn declaration_at(text: &str, offset: usize) -> Option<(&str, &str)> {
+    let start = text[..offset].rfind([';', '{', '}']).map_or(0, |i| i + 1);
+    let (property, value) = text[start..offset].split_once(':')?;
+    let property = property.trim();
+    let valid = property.trim_start_matches('-').starts_with(|c: char| c.is_ascii_alphabetic())
+        && property.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    valid.then_some((property, value))
+}
+
+/// Whether `offset` is inside a quoted string in the value of a `content`
+/// declaration, like the `#1` in `content: "#1"`
+///
+/// ```
+/// use hexvar_core::scanner::in_content_string;
+///
+/// let css = r##"a::before { content: "#ff0000"; color: #ff0000 } b::after { content: '\'' #fff }"##;
+/// assert!(in_content_string(css, css.find("#ff0000").unwrap()));
+/// assert!(!in_content_string(css, css.rfind("#ff0000").unwrap()));
+/// // The escaped quote doesn't end the string, but the one after it does
+/// assert!(!in_content_string(css, css.find("#fff ").unwrap()));
+/// ```
+pub fn in_content_string(text: &str, offset: usize) -> bool {
+    let start = text[..offset].rfind([';', '{', '}']).map_or(0, |i| i + 1);
+    let Some((property, value)) = text[start..offset].split_once(':') else {
+        return false;
+    };
+    if !property.trim().eq_ignore_ascii_case("content") {
+        return false;
+    }
+    let mut quote = None;
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(_), '\\') => {
+                chars.next();
+            }
+            (Some(q), c) if c == q => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            _ => {}
+        }
+    }
+    quote.is_some()
+}
+
+/// Extensions of the preprocessors whose `$var` or `@var` declarations
+/// [`ScanOptions::scss_aware`] recognizes
+const VARIABLE_EXTENSIONS: &[&str] = &["scss", "sass", "less"];
+
+/// Whether `path` is an SCSS, Sass, or Less file
+fn has_variable_declarations(path: &Path) -> bool {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_ascii_lowercase();
+    VARIABLE_EXTENSIONS.contains(&ext.as_str())
+}
+
+/// The SCSS `$name` or Less `@name` variable that `text[range]` is the whole
+/// value of, like `#1a2b3c` in `$brand-blue: #1a2b3c !default;`
+///
+/// ```
+/// use hexvar_core::scanner::variable_declaration;
+///
+/// let scss = "$brand-blue: #1a2b3c !default;\n@accent: #f00;\na { color: #1a2b3c; border: 1px solid #f00 }";
+/// let at = |hex: &str| scss.find(hex).unwrap()..scss.find(hex).unwrap() + hex.len();
+/// assert_eq!(variable_declaration(scss, at("#1a2b3c")), Some("$brand-blue"));
+/// assert_eq!(variable_declaration(scss, at("#f00")), Some("@accent"));
+/// let later = scss.rfind("#1a2b3c").unwrap();
+/// assert_eq!(variable_declaration(scss, later..later + 7), None);
+/// // A color that's only part of the value isn't the variable's own color
+/// let shadow = "$shadow: 0 1px #000;";
+/// assert_eq!(variable_declaration(shadow, 15..19), None);
+/// ```
+pub fn variable_declaration(text: &str, range: Range<usize>) -> Option<&str> {
+    let start = text[..range.start].rfind([';', '{', '}', '\n']).map_or(0, |i| i + 1);
+    let (name, before) = text[start..range.start].split_once(':')?;
+    let name = name.trim();
+    let ident = name.strip_prefix(['$', '@'])?;
+    if ident.is_empty() || !ident.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_') || !before.trim().is_empty() {
+        return None;
+    }
+    let end = text[range.end..].find([';', '}', '\n']).map_or(text.len(), |i| range.end + i);
+    let flags_only = text[range.end..end].split_whitespace().all(|flag| matches!(flag, "!default" | "!global"));
+    flags_only.then_some(name)
+}
+
+/// A file being scanned, for resolving match offsets to locations
+struct Document<'a> {
+    content: &'a str,
+    starts: &'a [usize],
+    file: &'a str,
+}
+
+/// Count every match of `re` across `paths`, reading files in parallel.
+/// Unreadable files are skipped.
+///
+/// ```
+/// use hexvar_core::color::HEX_PATTERN;
+/// use regex::Regex;
+///
+/// let path = std::env::temp_dir().join("hexvar_scan_paths_doc.css");
+/// std::fs::write(&path, "a { color: #fff; border-color: #fff; }").unwrap();
+/// let counts = hexvar_core::scan_paths(&[path], &Regex::new(HEX_PATTERN).unwrap());
+/// assert_eq!(counts["#fff"], 2);
+/// ```
+pub fn scan_paths(paths: &[PathBuf], re: &Regex) -> HashMap<String, u32> {
+    paths
+        .par_iter()
+        .filter_map(|path| scan_file(path, re).ok())
+        .reduce(HashMap::new, merge_counts)
+}
+
+/// Count every match of `re` in an in-memory string
+///
+/// ```
+/// use hexvar_core::color::HEX_PATTERN;
+/// use hexvar_core::scanner::scan_content;
+/// use regex::Regex;
+///
+/// let counts = scan_content("a { color: #fff; border: 1px solid #fff; background: #000 }", &Regex::new(HEX_PATTERN).unwrap());
+/// assert_eq!((counts["#fff"], counts["#000"]), (2, 1));
+/// ```
+pub fn scan_content(content: &str, re: &Regex) -> HashMap<String, u32> {
+    let mut counts = HashMap::new();
+    for m in re.find_iter(content) {
+        *counts.entry(m.as_str().to_string()).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Read a file and count every match of `re` in it
+///
+/// ```
+/// use hexvar_core::color::HEX_PATTERN;
+/// use hexvar_core::scanner::scan_file;
+/// use regex::Regex;
+///
+/// let re = Regex::new(HEX_PATTERN).unwrap();
+/// let path = std::env::temp_dir().join("hexvar_scan_file_doc.css");
+/// std::fs::write(&path, "a { color: #f00; }").unwrap();
+/// assert_eq!(scan_file(&path, &re).unwrap()["#f00"], 1);
+/// assert!(scan_file(&std::env::temp_dir().join("hexvar_scan_file_missing.css"), &re).is_err());
+/// ```
+pub fn scan_file(path: &Path, re: &Regex) -> Result<HashMap<String, u32>> {
+    let content = fs::read_to_string(path).map_err(|e| HexvarError::io(path, e))?;
+    Ok(scan_content(&content, re))
+}
+
+/// Combine two count maps by summing the counts of matching keys
+///
+/// ```
+/// use std::collections::HashMap;
+/// use hexvar_core::scanner::merge_counts;
+///
+/// let a = HashMap::from([("#fff".to_string(), 2), ("#000".to_string(), 1)]);
+/// let b = HashMap::from([("#fff".to_string(), 3), ("#f00".to_string(), 4)]);
+/// let merged = merge_counts(a, b);
+/// assert_eq!((merged["#fff"], merged["#000"], merged["#f00"]), (5, 1, 4));
+/// ```
+pub fn merge_counts(mut a: HashMap<String, u32>, b: HashMap<String, u32>) -> HashMap<String, u32> {
+    for (hex, n) in b {
+        *a.entry(hex).or_insert(0) += n;
+    }
+    a
+}
+
+/// Byte offsets at which each line of `content` starts
+fn line_starts(content: &str) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(content.match_indices('\n').map(|(i, _)| i + 1))
+        .collect()
+}
+
+/// Resolve a byte offset to a 1-based (line, column) pair, counting columns in chars
+fn line_col(content: &str, starts: &[usize], offset: usize) -> (usize, usize) {
+    let line = starts.partition_point(|&s| s <= offset) - 1;
+    let column = content[starts[line]..offset].chars().count() + 1;
+    (line + 1, column)
+}
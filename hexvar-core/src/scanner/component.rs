@@ -0,0 +1,99 @@
+use crate::replace::Substitution;
+use regex::Regex;
+use std::ops::Range;
+use std::path::Path;
+
+/// Extensions of component files whose colors are only read from `<style>` blocks
+pub const COMPONENT_EXTENSIONS: &[&str] = &["svelte", "astro"];
+
+/// Whether `path` is a Svelte or Astro component
+pub fn is_component(path: &Path) -> bool {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_ascii_lowercase();
+    COMPONENT_EXTENSIONS.contains(&ext.as_str())
+}
+
+/// The contents of a `<style>` block in a component
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StyleBlock {
+    /// Byte range of the block's contents, between its tags
+    pub range: Range<usize>,
+    /// 1-based line of the component that the contents start on
+    pub line: usize,
+}
+
+/// The contents of the `<style>` blocks in a component, e.g. `<style>` or
+/// `<style lang="scss">`, in document order. The markup and scripts around
+/// them, where `var()` isn't valid, are left out.
+///
+/// ```
+/// use hexvar_core::scanner::component::style_blocks;
+///
+/// let svelte = "<script>let c = '#000';</script>\n<p style=\"color: #111\">#222</p>\n<style lang=\"scss\">\n  p { color: #f00; }\n</style>";
+/// let blocks = style_blocks(svelte);
+/// assert_eq!(&svelte[blocks[0].range.clone()], "\n  p { color: #f00; }\n");
+/// assert_eq!(blocks[0].line, 3);
+/// assert!(style_blocks("<styles>#fff</styles>").is_empty());
+/// ```
+pub fn style_blocks(content: &str) -> Vec<StyleBlock> {
+    let re = Regex::new(r"(?is)<style(?:\s[^>]*)?>(.*?)</style\s*>").unwrap();
+    let mut line = 1;
+    let mut counted = 0;
+    re.captures_iter(content)
+        .map(|caps| {
+            let range = caps.get(1).unwrap().range();
+            line += content[counted..range.start].matches('\n').count();
+            counted = range.start;
+            StyleBlock { range, line }
+        })
+        .collect()
+}
+
+/// Rewrite the `<style>` blocks of a component with `rewrite`, leaving the
+/// markup and scripts untouched. Returns the new content and the sum of the
+/// counts `rewrite` reports.
+///
+/// ```
+/// use hexvar_core::scanner::component::rewrite_styles;
+///
+/// let astro = "<h1 data-c=\"#f00\">Hi</h1>\n<style>h1 { color: #f00 }</style>";
+/// let (out, n) = rewrite_styles(astro, |css| (css.replace("#f00", "var(--red)"), 1));
+/// assert_eq!(out, "<h1 data-c=\"#f00\">Hi</h1>\n<style>h1 { color: var(--red) }</style>");
+/// assert_eq!(n, 1);
+/// ```
+pub fn rewrite_styles<F>(content: &str, mut rewrite: F) -> (String, usize)
+where
+    F: FnMut(&str) -> (String, usize),
+{
+    let mut out = String::with_capacity(content.len());
+    let mut last = 0;
+    let mut total = 0;
+    for StyleBlock { range, .. } in style_blocks(content) {
+        let (text, n) = rewrite(&content[range.clone()]);
+        out.push_str(&content[last..range.start]);
+        out.push_str(&text);
+        last = range.end;
+        total += n;
+    }
+    out.push_str(&content[last..]);
+    (out, total)
+}
+
+/// Like [`rewrite_styles`], for a rewrite that records its substitutions,
+/// which are returned with offsets into the whole component
+pub fn rewrite_styles_recorded<F>(content: &str, mut rewrite: F) -> (String, Vec<Substitution>)
+where
+    F: FnMut(&str) -> (String, Vec<Substitution>),
+{
+    let mut out = String::with_capacity(content.len());
+    let mut subs = Vec::new();
+    let mut last = 0;
+    for StyleBlock { range, .. } in style_blocks(content) {
+        let (text, block_subs) = rewrite(&content[range.clone()]);
+        subs.extend(block_subs.into_iter().map(|sub| Substitution { offset: range.start + sub.offset, ..sub }));
+        out.push_str(&content[last..range.start]);
+        out.push_str(&text);
+        last = range.end;
+    }
+    out.push_str(&content[last..]);
+    (out, subs)
+}
@@ -0,0 +1,180 @@
+use crate::replace::Substitution;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::ops::Range;
+use std::path::Path;
+
+/// Presentation attributes whose values are colors
+pub const COLOR_ATTRIBUTES: &[&str] =
+    &["fill", "stroke", "color", "stop-color", "flood-color", "lighting-color", "solid-color"];
+
+/// Where in an SVG document a fragment came from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FragmentKind {
+    /// A presentation attribute like `fill="#fff"`, where `var()` is invalid
+    Presentation,
+    /// A `style="..."` attribute
+    StyleAttribute,
+    /// The CSS inside a `<style>` element
+    StyleElement,
+}
+
+impl FragmentKind {
+    /// Whether the fragment is CSS, so colors in it can become `var()`
+    pub fn is_css(self) -> bool {
+        self != FragmentKind::Presentation
+    }
+}
+
+/// A span of an SVG document that may contain colors
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fragment {
+    /// Byte range of the raw attribute value or element text
+    pub range: Range<usize>,
+    pub kind: FragmentKind,
+    /// Element and attribute, e.g. `<rect fill>` or `<style>`
+    pub context: String,
+}
+
+/// Whether a [`Fragment::context`] names a CSS fragment rather than a presentation attribute
+pub fn is_css_context(context: &str) -> bool {
+    context == "<style>" || context.ends_with(" style>")
+}
+
+/// Whether `path` is an SVG file
+pub fn is_svg(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e.eq_ignore_ascii_case("svg"))
+}
+
+/// Byte offset of `part` within `content`, if it is borrowed from it
+fn offset_in(content: &str, part: &str) -> Option<usize> {
+    let base = content.as_ptr() as usize;
+    let start = part.as_ptr() as usize;
+    (start >= base && start + part.len() <= base + content.len()).then(|| start - base)
+}
+
+/// Color attributes, `style` attributes, and `<style>` contents of an SVG
+/// document, in document order. Parsing stops at the first XML error, keeping
+/// the fragments found so far.
+///
+/// ```
+/// use hexvar_core::scanner::svg::{fragments, FragmentKind};
+///
+/// let svg = r##"<svg><style>.a { fill: #00f; }</style><rect id="ff0000" fill="#f00" style="stroke: #0f0"/></svg>"##;
+/// let found = fragments(svg);
+/// let spans: Vec<_> = found.iter().map(|f| (&svg[f.range.clone()], f.kind, f.context.as_str())).collect();
+/// assert_eq!(spans, [
+///     (".a { fill: #00f; }", FragmentKind::StyleElement, "<style>"),
+///     ("#f00", FragmentKind::Presentation, "<rect fill>"),
+///     ("stroke: #0f0", FragmentKind::StyleAttribute, "<rect style>"),
+/// ]);
+/// ```
+pub fn fragments(content: &str) -> Vec<Fragment> {
+    let mut reader = Reader::from_str(content);
+    reader.config_mut().trim_text(false);
+    let mut found = Vec::new();
+    let mut in_style = false;
+    loop {
+        let event = match reader.read_event() {
+            Ok(Event::Eof) | Err(_) => break,
+            Ok(event) => event,
+        };
+        match event {
+            Event::Start(ref tag) | Event::Empty(ref tag) => {
+                let element = tag.name().as_ref().to_string();
+                in_style = matches!(event, Event::Start(_)) && element == "style";
+                for attr in tag.attributes().with_checks(false).flatten() {
+                    let name = attr.key.as_ref().to_string();
+                    let kind = if name == "style" {
+                        FragmentKind::StyleAttribute
+                    } else if COLOR_ATTRIBUTES.contains(&name.as_str()) {
+                        FragmentKind::Presentation
+                    } else {
+                        continue;
+                    };
+                    let Some(start) = offset_in(content, &attr.value) else { continue };
+                    found.push(Fragment {
+                        range: start..start + attr.value.len(),
+                        kind,
+                        context: format!("<{} {}>", element, name),
+                    });
+                }
+            }
+            Event::End(_) => in_style = false,
+            Event::Text(ref text) if in_style => push_style(&mut found, content, &text[..]),
+            Event::CData(ref text) if in_style => push_style(&mut found, content, &text[..]),
+            _ => {}
+        }
+    }
+    found
+}
+
+fn push_style(found: &mut Vec<Fragment>, content: &str, text: &str) {
+    if let Some(start) = offset_in(content, text) {
+        found.push(Fragment {
+            range: start..start + text.len(),
+            kind: FragmentKind::StyleElement,
+            context: "<style>".to_string(),
+        });
+    }
+}
+
+/// Rewrite the CSS fragments of an SVG document with `rewrite`, leaving
+/// presentation attributes and everything else untouched. Returns the new
+/// content and the sum of the counts `rewrite` reports.
+///
+/// ```
+/// use hexvar_core::scanner::svg::rewrite_css;
+///
+/// let svg = r##"<rect fill="#f00" style="fill: #f00"/>"##;
+/// let (out, n) = rewrite_css(svg, |css| (css.replace("#f00", "var(--red)"), 1));
+/// assert_eq!(out, r##"<rect fill="#f00" style="fill: var(--red)"/>"##);
+/// assert_eq!(n, 1);
+/// ```
+pub fn rewrite_css<F>(content: &str, rewrite: F) -> (String, usize)
+where
+    F: FnMut(&str) -> (String, usize),
+{
+    rewrite_fragments(content, FragmentKind::is_css, rewrite)
+}
+
+/// Like [`rewrite_css`], for a rewrite that records its substitutions, which
+/// are returned with offsets into the whole document
+pub fn rewrite_css_recorded<F>(content: &str, mut rewrite: F) -> (String, Vec<Substitution>)
+where
+    F: FnMut(&str) -> (String, Vec<Substitution>),
+{
+    let mut out = String::with_capacity(content.len());
+    let mut subs = Vec::new();
+    let mut last = 0;
+    for fragment in fragments(content).into_iter().filter(|f| f.kind.is_css()) {
+        let (text, fragment_subs) = rewrite(&content[fragment.range.clone()]);
+        let start = fragment.range.start;
+        subs.extend(fragment_subs.into_iter().map(|sub| Substitution { offset: start + sub.offset, ..sub }));
+        out.push_str(&content[last..start]);
+        out.push_str(&text);
+        last = fragment.range.end;
+    }
+    out.push_str(&content[last..]);
+    (out, subs)
+}
+
+/// Rewrite every fragment whose kind passes `keep`
+pub fn rewrite_fragments<K, F>(content: &str, keep: K, mut rewrite: F) -> (String, usize)
+where
+    K: Fn(FragmentKind) -> bool,
+    F: FnMut(&str) -> (String, usize),
+{
+    let mut out = String::with_capacity(content.len());
+    let mut last = 0;
+    let mut total = 0;
+    for fragment in fragments(content).into_iter().filter(|f| keep(f.kind)) {
+        let (text, n) = rewrite(&content[fragment.range.clone()]);
+        out.push_str(&content[last..fragment.range.start]);
+        out.push_str(&text);
+        last = fragment.range.end;
+        total += n;
+    }
+    out.push_str(&content[last..]);
+    (out, total)
+}
@@ -0,0 +1,140 @@
+use crate::color::hex_to_rgba;
+use regex::Regex;
+use std::ops::Range;
+use std::path::Path;
+
+/// Extensions a `tailwind.config.*` file can have
+pub const TAILWIND_CONFIG_EXTENSIONS: &[&str] = &["js", "cjs", "mjs", "ts", "cts", "mts"];
+
+/// [`Location::source`](crate::report::Location::source) of colors read from a Tailwind config
+pub const TAILWIND_CONFIG_SOURCE: &str = "tailwind_config";
+
+/// Whether `path` is a Tailwind config file, e.g. `tailwind.config.js`
+///
+/// ```
+/// use std::path::Path;
+/// use hexvar_core::scanner::tailwind_config::is_tailwind_config;
+///
+/// assert!(is_tailwind_config(Path::new("web/tailwind.config.ts")));
+/// assert!(is_tailwind_config(Path::new("tailwind.config.cjs")));
+/// assert!(!is_tailwind_config(Path::new("tailwind.js")));
+/// assert!(!is_tailwind_config(Path::new("tailwind.config.json")));
+/// ```
+pub fn is_tailwind_config(path: &Path) -> bool {
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or_default().to_ascii_lowercase();
+    path.file_stem().is_some_and(|stem| stem == "tailwind.config") && TAILWIND_CONFIG_EXTENSIONS.contains(&ext.as_str())
+}
+
+/// A hex code in the `colors` of a Tailwind config
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConfigColor {
+    /// Byte range of the hex code, without its quotes
+    pub range: Range<usize>,
+    /// Keys leading to it from the `colors` object, e.g. `colors.brand.500`
+    pub path: String,
+}
+
+/// The quoted hex codes that are values in a `colors: { ... }` object of a
+/// Tailwind config, such as `theme.colors` or `theme.extend.colors`. This is a
+/// heuristic rather than a JS parser: colors built by function calls or spread
+/// from imported objects aren't found, and hex codes anywhere else in the file
+/// are left out.
+///
+/// ```
+/// use hexvar_core::scanner::tailwind_config::config_colors;
+///
+/// let config = r##"module.exports = {
+///   content: ['#not-a-color'],
+///   theme: { extend: { colors: {
+///     brand: { 500: '#1A2B3C', 'light': "#fff" }, // was #000
+///     accent: '#f00',
+///   } } },
+/// }"##;
+/// let found: Vec<(&str, String)> = config_colors(config).into_iter().map(|c| (&config[c.range], c.path)).collect();
+/// assert_eq!(found, [
+///     ("#1A2B3C", "colors.brand.500".to_string()),
+///     ("#fff", "colors.brand.light".to_string()),
+///     ("#f00", "colors.accent".to_string()),
+/// ]);
+/// ```
+pub fn config_colors(content: &str) -> Vec<ConfigColor> {
+    let re = Regex::new(r"\bcolors\s*:\s*\{").unwrap();
+    let mut found = Vec::new();
+    let mut end = 0;
+    for m in re.find_iter(content) {
+        // A `colors` key nested in an object already walked
+        if m.start() < end {
+            continue;
+        }
+        end = walk_colors(content, m.end(), &mut found);
+    }
+    found
+}
+
+/// Collect the hex values of the object whose body starts at `start`, returning
+/// the offset just past its closing brace
+fn walk_colors(content: &str, start: usize, found: &mut Vec<ConfigColor>) -> usize {
+    let bytes = content.as_bytes();
+    let mut keys = vec!["colors".to_string()];
+    let mut key: Option<String> = None;
+    let mut in_value = false;
+    let mut i = start;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'{' => {
+                keys.push(key.take().unwrap_or_default());
+                in_value = false;
+            }
+            b'}' => {
+                keys.pop();
+                if keys.is_empty() {
+                    return i + 1;
+                }
+                key = None;
+                in_value = false;
+            }
+            b',' => {
+                key = None;
+                in_value = false;
+            }
+            b':' => in_value = true,
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                i = content[i..].find('\n').map_or(bytes.len(), |n| i + n);
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = content[i + 2..].find("*/").map_or(bytes.len(), |n| i + n + 4);
+                continue;
+            }
+            quote @ (b'"' | b'\'' | b'`') => {
+                let mut close = i + 1;
+                while close < bytes.len() && bytes[close] != quote {
+                    close += if bytes[close] == b'\\' { 2 } else { 1 };
+                }
+                let text = &content[i + 1..close.min(bytes.len())];
+                if !in_value {
+                    key = Some(text.to_string());
+                } else if text.strip_prefix('#').and_then(hex_to_rgba).is_some() {
+                    let mut path = keys.join(".");
+                    if let Some(key) = &key {
+                        path = format!("{}.{}", path, key);
+                    }
+                    found.push(ConfigColor { range: i + 1..close, path });
+                }
+                i = close + 1;
+                continue;
+            }
+            b if !in_value && (b.is_ascii_alphanumeric() || b == b'_' || b == b'$' || b == b'-') => {
+                let len = content[i..]
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '$' || c == '-'))
+                    .unwrap_or(bytes.len() - i);
+                key = Some(content[i..i + len].to_string());
+                i += len;
+                continue;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    bytes.len()
+}
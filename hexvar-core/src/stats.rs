@@ -0,0 +1,141 @@
+use crate::cluster::Cluster;
+use crate::color::{hex_to_rgba, rgb_to_lab};
+use palette::color_difference::DeltaE;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+pub mod health;
+
+/// Mean, median, and standard deviation of one value across colors,
+/// weighted by how often each color occurs
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Distribution {
+    pub mean: f32,
+    pub median: f32,
+    pub std_dev: f32,
+}
+
+impl Distribution {
+    /// Summarize `(value, weight)` pairs, or `None` if the weights sum to zero
+    ///
+    /// ```
+    /// use hexvar_core::stats::Distribution;
+    ///
+    /// let d = Distribution::weighted(&[(0.0, 1), (10.0, 3)]).unwrap();
+    /// assert_eq!((d.mean, d.median), (7.5, 10.0));
+    /// assert!((d.std_dev - 4.330).abs() < 1e-3);
+    /// assert!(Distribution::weighted(&[]).is_none());
+    /// ```
+    pub fn weighted(values: &[(f32, u32)]) -> Option<Distribution> {
+        let total: f64 = values.iter().map(|&(_, w)| w as f64).sum();
+        if total == 0.0 {
+            return None;
+        }
+        let mean = values.iter().map(|&(v, w)| v as f64 * w as f64).sum::<f64>() / total;
+        let variance = values.iter().map(|&(v, w)| (v as f64 - mean).powi(2) * w as f64).sum::<f64>() / total;
+        let mut sorted = values.to_vec();
+        sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+        // The first value that at least half of the total weight is at or below
+        let mut seen = 0.0;
+        let median = sorted
+            .iter()
+            .find(|&&(_, w)| {
+                seen += w as f64;
+                seen * 2.0 >= total
+            })
+            .map_or(0.0, |&(v, _)| v);
+        Some(Distribution { mean: mean as f32, median, std_dev: variance.sqrt() as f32 })
+    }
+}
+
+/// Two canonical colors and the Delta E (CIE76) between them
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ColorPair {
+    pub a: String,
+    pub b: String,
+    pub delta_e: f32,
+}
+
+/// Palette statistics printed by `scan --stats`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PaletteStats {
+    pub red: Distribution,
+    pub green: Distribution,
+    pub blue: Distribution,
+    /// CIE L* lightness, 0 to 100
+    pub lightness: Distribution,
+    /// Canonical colors furthest apart, if there are at least two
+    pub most_distinct: Option<ColorPair>,
+    /// Canonical colors closest together, if there are at least two
+    pub most_similar: Option<ColorPair>,
+}
+
+impl PaletteStats {
+    /// Channel and lightness statistics over the unique colors in `counts`,
+    /// weighted by occurrence count, and the extreme pairs among `clusters`.
+    /// `None` if there are no colors.
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use hexvar_core::stats::PaletteStats;
+    ///
+    /// let counts = HashMap::from([("#000000".to_string(), 1), ("#ffffff".to_string(), 1), ("#fefefe".to_string(), 2)]);
+    /// let clusters = hexvar_core::cluster_colors(&counts, 0.0);
+    /// let stats = PaletteStats::new(&counts, &clusters).unwrap();
+    /// assert_eq!(stats.red.median, 254.0);
+    /// let distinct = stats.most_distinct.unwrap();
+    /// assert_eq!((distinct.a.as_str(), distinct.b.as_str(), distinct.delta_e.round()), ("#000000", "#ffffff", 100.0));
+    /// let similar = stats.most_similar.unwrap();
+    /// assert_eq!((similar.a.as_str(), similar.b.as_str()), ("#fefefe", "#ffffff"));
+    /// ```
+    pub fn new(counts: &HashMap<String, u32>, clusters: &[Cluster]) -> Option<PaletteStats> {
+        let colors: Vec<((u8, u8, u8), u32)> = counts
+            .iter()
+            .filter_map(|(hex, &n)| hex_to_rgba(hex).map(|(r, g, b, _)| ((r, g, b), n)))
+            .collect();
+        let channel = |f: fn(&(u8, u8, u8)) -> u8| -> Option<Distribution> {
+            let values: Vec<(f32, u32)> = colors.iter().map(|(rgb, n)| (f(rgb) as f32, *n)).collect();
+            Distribution::weighted(&values)
+        };
+        let lightness: Vec<(f32, u32)> = colors.iter().map(|&((r, g, b), n)| (rgb_to_lab(r, g, b).l, n)).collect();
+
+        // Sorted so ties between pairs resolve the same way on every run
+        let mut canonical: Vec<&Cluster> = clusters.iter().collect();
+        canonical.sort_by(|a, b| a.hex.cmp(&b.hex));
+        let mut pairs = Vec::new();
+        for (i, a) in canonical.iter().enumerate() {
+            for b in &canonical[i + 1..] {
+                pairs.push(ColorPair { a: a.hex.clone(), b: b.hex.clone(), delta_e: a.lab.delta_e(b.lab) });
+            }
+        }
+        let most_distinct = pairs.iter().rev().max_by(|x, y| x.delta_e.total_cmp(&y.delta_e)).cloned();
+        let most_similar = pairs.iter().min_by(|x, y| x.delta_e.total_cmp(&y.delta_e)).cloned();
+
+        Some(PaletteStats {
+            red: channel(|rgb| rgb.0)?,
+            green: channel(|rgb| rgb.1)?,
+            blue: channel(|rgb| rgb.2)?,
+            lightness: Distribution::weighted(&lightness)?,
+            most_distinct,
+            most_similar,
+        })
+    }
+
+    /// The statistics as the lines printed after the scan summary
+    pub fn render_text(&self) -> String {
+        let mut out = String::from("==== PALETTE STATS ====\n");
+        for (label, d) in [("Red", &self.red), ("Green", &self.green), ("Blue", &self.blue), ("Lightness", &self.lightness)] {
+            writeln!(out, "{:<11}mean {:>6.1}  median {:>6.1}  std dev {:>6.1}", format!("{}:", label), d.mean, d.median, d.std_dev)
+                .unwrap();
+        }
+        if let Some(pair) = &self.most_distinct {
+            writeln!(out, "Most distinct pair: {} / {} (Delta E {:.1})", pair.a, pair.b, pair.delta_e).unwrap();
+        }
+        if let Some(pair) = &self.most_similar {
+            writeln!(out, "Most similar pair:  {} / {} (Delta E {:.1})", pair.a, pair.b, pair.delta_e).unwrap();
+        }
+        out.push_str("=======================");
+        out
+    }
+}
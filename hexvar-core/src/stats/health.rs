@@ -0,0 +1,132 @@
+use crate::cluster::{cluster_colors, cluster_usage, Cluster};
+use crate::color::NEUTRAL_CHROMA;
+use crate::output::{generate_css_vars, VarFormat};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+
+/// Delta E thresholds `hexvar stats` reports the distinct color count at
+pub const HEALTH_THRESHOLDS: [f32; 4] = [3.0, 5.0, 10.0, 20.0];
+
+/// Width in degrees of the hue wheel segments counted for hue coverage
+const HUE_BUCKET_DEGREES: usize = 10;
+
+/// Canonical colors left after clustering at one Delta E threshold
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ThresholdCount {
+    pub delta_e: f32,
+    pub colors: usize,
+}
+
+/// One of the clusters that absorbed the most colors
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MergedCluster {
+    pub hex: String,
+    pub name: String,
+    /// Colors merged into it, including the canonical one
+    pub members: usize,
+    /// Total occurrences of those colors
+    pub count: u32,
+}
+
+/// Palette health metrics printed by `hexvar stats`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PaletteHealth {
+    pub unique_colors: usize,
+    pub total_occurrences: u32,
+    pub distinct_at: Vec<ThresholdCount>,
+    /// Fraction of the 10-degree hue wheel segments holding at least one
+    /// chromatic (non-neutral) color, 0 to 1
+    pub hue_coverage: f32,
+    /// Bytes the CSS variables file shrinks by when similar colors are merged
+    /// at `delta_e` instead of getting one variable each
+    pub css_var_savings: usize,
+    /// Unique colors per occurrence, 0 to 1; lower means colors are reused more
+    pub redundancy_index: f32,
+    /// Delta E the savings and most-merged clusters are computed at
+    pub delta_e: f32,
+    /// The five clusters at `delta_e` that merged the most colors
+    pub most_merged: Vec<MergedCluster>,
+}
+
+impl PaletteHealth {
+    /// Metrics over the colors in `counts`, clustering at `delta_e` for the
+    /// variable savings and most-merged clusters
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use hexvar_core::stats::health::PaletteHealth;
+    ///
+    /// let counts = HashMap::from([("#ff0000".to_string(), 3), ("#fe0000".to_string(), 1), ("#0000ff".to_string(), 4)]);
+    /// let health = PaletteHealth::new(&counts, 10.0);
+    /// assert_eq!((health.unique_colors, health.total_occurrences), (3, 8));
+    /// assert_eq!(health.distinct_at[2].colors, 2);
+    /// assert_eq!(health.redundancy_index, 0.375);
+    /// assert_eq!(health.hue_coverage, 2.0 / 36.0);
+    /// assert_eq!((health.most_merged[0].members, health.most_merged[0].count), (2, 4));
+    /// assert!(health.css_var_savings > 0);
+    /// ```
+    pub fn new(counts: &HashMap<String, u32>, delta_e: f32) -> PaletteHealth {
+        let total_occurrences: u32 = counts.values().sum();
+        let distinct_at = HEALTH_THRESHOLDS
+            .iter()
+            .map(|&t| ThresholdCount { delta_e: t, colors: cluster_colors(counts, t).len() })
+            .collect();
+
+        let unmerged = cluster_colors(counts, 0.0);
+        let buckets: BTreeSet<usize> = unmerged
+            .iter()
+            .filter(|c| c.lab.a.hypot(c.lab.b) >= NEUTRAL_CHROMA)
+            .map(|c| c.lab.b.atan2(c.lab.a).to_degrees().rem_euclid(360.0) as usize / HUE_BUCKET_DEGREES)
+            .collect();
+        let hue_coverage = buckets.len() as f32 / (360 / HUE_BUCKET_DEGREES) as f32;
+
+        let clusters = cluster_colors(counts, delta_e);
+        let vars_size = |clusters: &[Cluster]| generate_css_vars(clusters, VarFormat::Css).len();
+        let css_var_savings = vars_size(&unmerged).saturating_sub(vars_size(&clusters));
+
+        let mut most_merged: Vec<MergedCluster> = clusters
+            .iter()
+            .map(|c| MergedCluster {
+                hex: c.hex.clone(),
+                name: c.name.clone(),
+                members: c.members.len(),
+                count: cluster_usage(c, counts),
+            })
+            .collect();
+        most_merged.sort_by(|a, b| b.members.cmp(&a.members).then(b.count.cmp(&a.count)).then(a.hex.cmp(&b.hex)));
+        most_merged.truncate(5);
+
+        PaletteHealth {
+            unique_colors: counts.len(),
+            total_occurrences,
+            distinct_at,
+            hue_coverage,
+            css_var_savings,
+            redundancy_index: if total_occurrences == 0 { 0.0 } else { counts.len() as f32 / total_occurrences as f32 },
+            delta_e,
+            most_merged,
+        }
+    }
+
+    /// The metrics as the lines printed by `hexvar stats`
+    pub fn render_text(&self) -> String {
+        let mut out = String::from("==== PALETTE HEALTH ====\n");
+        writeln!(out, "Unique colors:     {} ({} occurrences)", self.unique_colors, self.total_occurrences).unwrap();
+        let distinct: Vec<String> =
+            self.distinct_at.iter().map(|t| format!("{} at Delta E {}", t.colors, t.delta_e)).collect();
+        writeln!(out, "Distinct colors:   {}", distinct.join(", ")).unwrap();
+        writeln!(out, "Hue coverage:      {:.0}% of the hue wheel", self.hue_coverage * 100.0).unwrap();
+        writeln!(out, "Variable savings:  {} bytes of CSS variables at Delta E {}", self.css_var_savings, self.delta_e)
+            .unwrap();
+        writeln!(out, "Redundancy index:  {:.2} (unique colors / occurrences)", self.redundancy_index).unwrap();
+        if !self.most_merged.is_empty() {
+            writeln!(out, "Most merged clusters at Delta E {}:", self.delta_e).unwrap();
+            for c in &self.most_merged {
+                writeln!(out, "  {} {:<24} {} colors, {} occurrences", c.hex, c.name, c.members, c.count).unwrap();
+            }
+        }
+        out.push_str("========================");
+        out
+    }
+}
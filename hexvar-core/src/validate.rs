@@ -0,0 +1,165 @@
+use crate::error::{HexvarError, Result};
+use crate::paths::{display_name, read_source};
+use crate::replace::{css_var_definitions, runs_on, var_calls};
+use crate::scanner::{component, svg};
+use rayon::prelude::*;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// A mapped color still written literally in a source file
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct RawColor {
+    pub file: String,
+    pub line: usize,
+    /// The color as written, e.g. `#FF0000` or `rgb(255, 0, 0)`
+    pub spelling: String,
+}
+
+/// Problems found by `hexvar validate`, one list per kind of drift between
+/// the mapping, the CSS variables file, and the sources
+#[derive(Debug, Default, Serialize)]
+pub struct ValidationReport {
+    /// Canonical colors in the mapping that no variable in the CSS file is set to
+    pub missing_variables: Vec<String>,
+    /// Variables in the CSS file whose value isn't a color in the mapping
+    pub unmapped_variables: Vec<String>,
+    /// Mapped colors that `replace` would still rewrite
+    pub raw_colors: Vec<RawColor>,
+}
+
+impl ValidationReport {
+    /// Check a `colours_map.json` mapping and the CSS variables file defining
+    /// its names against each other, and `paths` against both
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use hexvar_core::validate::ValidationReport;
+    ///
+    /// let path = std::env::temp_dir().join("hexvar_validate_doc.css");
+    /// std::fs::write(&path, "a { color: var(--color-red, #f00); }\nb { color: #F00; }\nc { color: #f00abc; }\n").unwrap();
+    /// let map = HashMap::from([
+    ///     ("#ff0000".to_string(), vec!["#ff0000".to_string(), "#f00".to_string()]),
+    ///     ("#0000ff".to_string(), vec!["#0000ff".to_string()]),
+    /// ]);
+    /// let css = ":root {\n    --color-red: #ff0000;\n    --color-old: #123456;\n}\n";
+    /// let report = ValidationReport::new(&map, css, &[path]);
+    /// assert_eq!(report.missing_variables, ["#0000ff"]);
+    /// assert_eq!(report.unmapped_variables, ["--color-old"]);
+    /// assert_eq!(report.raw_colors.len(), 1);
+    /// assert_eq!((report.raw_colors[0].line, report.raw_colors[0].spelling.as_str()), (2, "#F00"));
+    /// assert!(!report.is_clean());
+    /// ```
+    pub fn new(map: &HashMap<String, Vec<String>>, css: &str, paths: &[PathBuf]) -> ValidationReport {
+        let canonical = |hex: &str| format!("#{}", hex.trim_start_matches('#').to_lowercase());
+        let vars = css_var_definitions(css);
+        let var_values: BTreeSet<&str> = vars.iter().map(|(_, value)| value.as_str()).collect();
+        let mut missing_variables: Vec<String> =
+            map.keys().filter(|hex| !var_values.contains(canonical(hex).as_str())).cloned().collect();
+        missing_variables.sort();
+
+        let mapped: BTreeSet<String> =
+            map.iter().flat_map(|(canon, hexes)| hexes.iter().map(|h| h.to_lowercase()).chain([canonical(canon)])).collect();
+        let unmapped_variables =
+            vars.iter().filter(|(_, value)| !mapped.contains(value)).map(|(var, _)| var.clone()).collect();
+
+        let mut raw_colors: Vec<RawColor> = match spelling_regex(&mapped) {
+            Some(re) => paths.par_iter().flat_map_iter(|path| raw_colors_in(path, &re)).collect(),
+            None => Vec::new(),
+        };
+        raw_colors.sort();
+        ValidationReport { missing_variables, unmapped_variables, raw_colors }
+    }
+
+    /// Read the mapping and CSS variables file, then check them as in [`ValidationReport::new`]
+    pub fn load(map_path: &Path, css_path: &Path, paths: &[PathBuf]) -> Result<ValidationReport> {
+        let map_json = fs::read_to_string(map_path)
+            .map_err(|source| HexvarError::MissingMapFile { path: map_path.to_path_buf(), source })?;
+        let map: HashMap<String, Vec<String>> =
+            serde_json::from_str(&map_json).map_err(|e| HexvarError::json(map_path, e))?;
+        let css = fs::read_to_string(css_path)
+            .map_err(|source| HexvarError::MissingCssFile { path: css_path.to_path_buf(), source })?;
+        Ok(ValidationReport::new(&map, &css, paths))
+    }
+
+    /// Whether every check passed
+    pub fn is_clean(&self) -> bool {
+        self.missing_variables.is_empty() && self.unmapped_variables.is_empty() && self.raw_colors.is_empty()
+    }
+
+    /// The report as text, one section per kind of problem found
+    pub fn render_text(&self) -> String {
+        let mut out = String::new();
+        if !self.missing_variables.is_empty() {
+            writeln!(out, "Canonical colors with no variable in the CSS file:").unwrap();
+            for hex in &self.missing_variables {
+                writeln!(out, "  {}", hex).unwrap();
+            }
+        }
+        if !self.unmapped_variables.is_empty() {
+            writeln!(out, "Variables with no color in the mapping:").unwrap();
+            for var in &self.unmapped_variables {
+                writeln!(out, "  {}", var).unwrap();
+            }
+        }
+        if !self.raw_colors.is_empty() {
+            writeln!(out, "Mapped colors still written literally (run `hexvar replace`):").unwrap();
+            for raw in &self.raw_colors {
+                writeln!(out, "  {}:{}: {}", raw.file, raw.line, raw.spelling).unwrap();
+            }
+        }
+        if self.is_clean() {
+            writeln!(out, "All checks passed").unwrap();
+        }
+        out
+    }
+}
+
+/// One case-insensitive regex matching any mapped spelling, longest first so
+/// `#aabbcc` is never reported as `#aab`. Matches that [`runs_on`] into an
+/// unmapped code are dropped by [`raw_colors_in`].
+fn spelling_regex(spellings: &BTreeSet<String>) -> Option<Regex> {
+    if spellings.is_empty() {
+        return None;
+    }
+    let mut spellings: Vec<&String> = spellings.iter().collect();
+    spellings.sort_by_key(|s| std::cmp::Reverse(s.len()));
+    let alternatives: Vec<String> = spellings.iter().map(|s| regex::escape(s)).collect();
+    Some(Regex::new(&format!("(?i){}", alternatives.join("|"))).unwrap())
+}
+
+/// Matches of `re` in the parts of `path` that `replace` rewrites: the whole
+/// file, or only the CSS of an SVG or component, outside `var()` fallbacks. Unreadable files
+/// have none.
+fn raw_colors_in(path: &Path, re: &Regex) -> Vec<RawColor> {
+    let Ok(content) = read_source(path) else { return Vec::new() };
+    let ranges: Vec<Range<usize>> = if svg::is_svg(path) {
+        svg::fragments(&content).into_iter().filter(|f| f.kind.is_css()).map(|f| f.range).collect()
+    } else if component::is_component(path) {
+        component::style_blocks(&content).into_iter().map(|b| b.range).collect()
+    } else {
+        std::iter::once(0..content.len()).collect()
+    };
+    let fallbacks = var_calls(&content);
+    let file = display_name(path);
+    ranges
+        .into_iter()
+        .flat_map(|range| {
+            let text = &content[range.clone()];
+            re.find_iter(text)
+                .filter(|m| !runs_on(text, m.end()))
+                .map(move |m| (range.start + m.start(), m.as_str()))
+                .collect::<Vec<_>>()
+        })
+        .filter(|(offset, _)| !fallbacks.iter().any(|zone| zone.contains(offset)))
+        .map(|(offset, spelling)| RawColor {
+            file: file.clone(),
+            line: content[..offset].matches('\n').count() + 1,
+            spelling: spelling.to_string(),
+        })
+        .collect()
+}
@@ -0,0 +1,115 @@
+//! WebAssembly bindings for `hexvar-core`, built with `wasm-pack` into the
+//! `hexvar-wasm` npm package. See `README.npm.md` for the JS API.
+
+use hexvar_core::cluster::cluster_usage;
+use hexvar_core::color::HEX_PATTERN;
+use hexvar_core::encoding::SourceEncoding;
+use hexvar_core::scanner::{normalize_hex, AlphaMode, ScanOptions, Scanner};
+use hexvar_core::{cluster_colors, ColorReport, ReportSort};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+
+/// What `alpha` in the scan config does to 8-digit hex codes
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Alpha {
+    #[default]
+    Keep,
+    Opaque,
+    Strip,
+}
+
+/// Options of `scan()`, all optional, in the casing JS callers expect
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct ScanConfig {
+    /// Name the content is reported under; its extension decides how it's read
+    filename: Option<String>,
+    locations: bool,
+    named_colors: bool,
+    comments: bool,
+    skip_gradients: bool,
+    content_strings: bool,
+    resolve_color_mix: bool,
+    scss_aware: bool,
+    track_properties: bool,
+    alpha: Alpha,
+}
+
+impl ScanConfig {
+    fn scan_options(&self) -> ScanOptions {
+        ScanOptions {
+            locations: self.locations,
+            named_colors: self.named_colors,
+            comments: self.comments,
+            per_file: false,
+            skip_gradients: self.skip_gradients,
+            alpha: match self.alpha {
+                Alpha::Keep => AlphaMode::Keep,
+                Alpha::Opaque => AlphaMode::Opaque,
+                Alpha::Strip => AlphaMode::Strip,
+            },
+            resolve_color_mix: self.resolve_color_mix,
+            content_strings: self.content_strings,
+            scss_aware: self.scss_aware,
+            // Content arrives as a JS string, already decoded
+            encoding: SourceEncoding::default(),
+            track_properties: self.track_properties,
+        }
+    }
+}
+
+/// One canonical color returned by `cluster()`
+#[derive(Serialize)]
+struct ClusterResult {
+    hex: String,
+    name: String,
+    members: Vec<String>,
+    count: u32,
+}
+
+/// Plain objects rather than `Map`s, so results look like `hexvar scan --out`
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, JsError> {
+    value.serialize(&serde_wasm_bindgen::Serializer::json_compatible()).map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// Scan one stylesheet (or SVG, Vue, JS, ... file) and return the report
+/// `hexvar scan --out` would write for it, most-used colors first
+#[wasm_bindgen]
+pub fn scan(content: &str, config: JsValue) -> Result<JsValue, JsError> {
+    let config: ScanConfig = if config.is_undefined() || config.is_null() {
+        ScanConfig::default()
+    } else {
+        serde_wasm_bindgen::from_value(config).map_err(|e| JsError::new(&format!("Invalid scan config: {}", e)))?
+    };
+    let scanner = Scanner::new(Regex::new(HEX_PATTERN).unwrap(), config.scan_options());
+    let tally = scanner.scan_str(content, config.filename.as_deref().unwrap_or("input.css"));
+    let mut report = ColorReport::from_tally(&tally);
+    report.sort(ReportSort::CountDesc, &[]);
+    to_js(&report)
+}
+
+/// Merge the colors of a `{ "#hex": count }` object whose Delta E is below
+/// `delta_e` and return the canonical colors, most-used first
+#[wasm_bindgen]
+pub fn cluster(counts: JsValue, delta_e: f64) -> Result<JsValue, JsError> {
+    let raw: HashMap<String, u32> =
+        serde_wasm_bindgen::from_value(counts).map_err(|e| JsError::new(&format!("Invalid counts: {}", e)))?;
+    let mut counts: HashMap<String, u32> = HashMap::new();
+    for (hex, n) in raw {
+        *counts.entry(normalize_hex(&hex)).or_insert(0) += n;
+    }
+    let mut clusters: Vec<ClusterResult> = cluster_colors(&counts, delta_e as f32)
+        .into_iter()
+        .map(|c| {
+            let count = cluster_usage(&c, &counts);
+            let mut members = c.members;
+            members.sort();
+            ClusterResult { hex: c.hex, name: c.name, members, count }
+        })
+        .collect();
+    clusters.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.hex.cmp(&b.hex)));
+    to_js(&clusters)
+}
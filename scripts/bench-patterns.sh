@@ -0,0 +1,50 @@
+#!/usr/bin/env bash
+# Compare glob expansion time for many patterns on one thread and on all cores.
+#
+#   scripts/bench-patterns.sh [PATTERNS] [FILES_PER_DIR]
+#
+# Generates PATTERNS (default 1000) directories of FILES_PER_DIR (default 200)
+# CSS files, passes one glob per directory plus a catch-all glob that overlaps
+# every one of them, and times `hexvar scan --dry-run-paths` with
+# RAYON_NUM_THREADS=1 and with the default thread count.
+set -euo pipefail
+
+patterns=${1:-1000}
+per_dir=${2:-200}
+root=$(cd "$(dirname "$0")/.." && pwd)
+cargo build --release --quiet --manifest-path "$root/Cargo.toml"
+hexvar="$root/target/release/hexvar"
+
+work=$(mktemp -d)
+trap 'rm -rf "$work"' EXIT
+python3 - "$work" "$patterns" "$per_dir" <<'PY'
+import os, sys
+root, patterns, per_dir = sys.argv[1], int(sys.argv[2]), int(sys.argv[3])
+for i in range(patterns):
+    os.makedirs(f"{root}/src/d{i}")
+    for j in range(per_dir):
+        with open(f"{root}/src/d{i}/f{j}.css", "w") as f:
+            f.write(".a { color: #123456; }\n")
+PY
+
+cd "$work"
+args=()
+for ((i = 0; i < patterns; i++)); do
+    args+=("src/d$i/*.css")
+done
+args+=("src/**/*.css")
+
+# Wall-clock seconds of running the given command
+elapsed() {
+    python3 -c 'import subprocess, sys, time
+start = time.perf_counter()
+subprocess.run(sys.argv[1:], check=True, stdout=subprocess.DEVNULL)
+print(f"{time.perf_counter() - start:.2f}")' "$@"
+}
+
+single=$(RAYON_NUM_THREADS=1 elapsed "$hexvar" scan "${args[@]}" --dry-run-paths)
+parallel=$(elapsed "$hexvar" scan "${args[@]}" --dry-run-paths)
+listed=$("$hexvar" scan "${args[@]}" --dry-run-paths | wc -l)
+echo "patterns: $((patterns + 1)), files listed: $listed"
+echo "one thread: ${single}s"
+echo "all cores:  ${parallel}s"
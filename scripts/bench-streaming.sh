@@ -0,0 +1,41 @@
+#!/usr/bin/env bash
+# Compare peak RSS of `hexvar scan --locations` with and without --streaming.
+#
+#   scripts/bench-streaming.sh [FILES] [COLORS_PER_FILE]
+#
+# Generates FILES (default 50000) CSS files in a temporary directory, scans
+# them both ways with a release build, and prints the peak RSS of each run.
+set -euo pipefail
+
+files=${1:-50000}
+per_file=${2:-20}
+root=$(cd "$(dirname "$0")/.." && pwd)
+cargo build --release --quiet --manifest-path "$root/Cargo.toml"
+hexvar="$root/target/release/hexvar"
+
+work=$(mktemp -d)
+trap 'rm -rf "$work"' EXIT
+mkdir -p "$work/src"
+python3 - "$work/src" "$files" "$per_file" <<'PY'
+import random, sys
+dir, files, per_file = sys.argv[1], int(sys.argv[2]), int(sys.argv[3])
+random.seed(1)
+for i in range(files):
+    with open(f"{dir}/f{i}.css", "w") as f:
+        for j in range(per_file):
+            f.write(f".c{j} {{ color: #{random.randrange(0x1000000):06x}; }}\n")
+PY
+
+# Peak RSS in KiB of running the given command
+peak_rss() {
+    python3 -c 'import resource, subprocess, sys
+subprocess.run(sys.argv[1:], check=True, stdout=subprocess.DEVNULL)
+print(resource.getrusage(resource.RUSAGE_CHILDREN).ru_maxrss)' "$@"
+}
+
+cd "$work"
+buffered=$(peak_rss "$hexvar" scan "src/*.css" --locations --no-cache --delta-e 0 --out report.json)
+streaming=$(peak_rss "$hexvar" scan "src/*.css" --locations --no-cache --delta-e 0 --out report.json --streaming)
+echo "files: $files, colors per file: $per_file"
+echo "peak RSS without --streaming: $((buffered / 1024)) MiB"
+echo "peak RSS with --streaming:    $((streaming / 1024)) MiB"
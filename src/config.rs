@@ -0,0 +1,117 @@
+use crate::scan::DEFAULT_CSS_VARS_FILE;
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use hexvar_core::VarFormat;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Config file names looked up in each directory, in priority order
+pub const CONFIG_FILES: &[&str] = &["hexvar.toml", ".hexvarrc"];
+
+/// Settings `hexvar init` asks for, written out as a commented config file
+pub struct StarterConfig {
+    pub patterns: Vec<String>,
+    pub ignore: Vec<String>,
+    pub delta_e: f32,
+    pub format: VarFormat,
+    pub css_vars: String,
+    /// `None` prints the JSON report to stdout
+    pub out: Option<String>,
+}
+
+impl Default for StarterConfig {
+    fn default() -> Self {
+        StarterConfig {
+            patterns: vec!["src/**/*".to_string()],
+            ignore: vec!["node_modules".to_string(), "dist".to_string()],
+            delta_e: 10.0,
+            format: VarFormat::Css,
+            css_vars: DEFAULT_CSS_VARS_FILE.to_string(),
+            out: None,
+        }
+    }
+}
+
+impl StarterConfig {
+    /// Render as TOML, with a comment explaining each setting
+    pub fn render(&self) -> String {
+        let string = |s: &str| toml::Value::String(s.to_string()).to_string();
+        let list = |items: &[String]| format!("[{}]", items.iter().map(|i| string(i)).collect::<Vec<_>>().join(", "));
+        let format = self.format.to_possible_value().map(|v| v.get_name().to_string()).unwrap_or_default();
+        let out = match &self.out {
+            Some(out) => format!("out = {}", string(out)),
+            None => format!("# out = {}", string("colours.json")),
+        };
+        format!(
+            r#"# hexvar project configuration.
+# Command-line flags always take precedence over values set here.
+
+# Glob patterns to scan
+patterns = {patterns}
+
+# Patterns or directories to ignore
+ignore = {ignore}
+
+# Delta E threshold for merging similar colors (0 disables clustering)
+delta_e = {delta_e:?}
+
+# Output file for deduplicated variables
+css_vars = {css_vars}
+
+# Syntax of the variables file: css, scss, less, js, or ts
+format = {format}
+
+# Output file for the JSON report (omit to print to stdout)
+{out}
+"#,
+            patterns = list(&self.patterns),
+            ignore = list(&self.ignore),
+            delta_e = self.delta_e,
+            css_vars = string(&self.css_vars),
+            format = string(&format),
+            out = out,
+        )
+    }
+}
+
+/// Project-level defaults for the `scan` command, read from `hexvar.toml` or `.hexvarrc`
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub patterns: Vec<String>,
+    pub ignore: Vec<String>,
+    pub delta_e: Option<f32>,
+    pub css_vars: Option<String>,
+    pub out: Option<String>,
+    pub format: Option<VarFormat>,
+}
+
+impl Config {
+    /// Walk from the current directory upward and load the first config file found.
+    /// Returns the default (empty) config when there is none.
+    pub fn discover() -> Result<Config> {
+        let Ok(cwd) = std::env::current_dir() else {
+            return Ok(Config::default());
+        };
+        match find_config_file(&cwd) {
+            Some(path) => Config::load(&path),
+            None => Ok(Config::default()),
+        }
+    }
+
+    /// Parse a config file
+    pub fn load(path: &Path) -> Result<Config> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("Invalid config file {}", path.display()))
+    }
+}
+
+/// Find the nearest config file in `start` or any of its ancestors
+pub fn find_config_file(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .flat_map(|dir| CONFIG_FILES.iter().map(move |name| dir.join(name)))
+        .find(|path| path.is_file())
+}
@@ -0,0 +1,88 @@
+use crate::config::{StarterConfig, CONFIG_FILES};
+use anyhow::{bail, Context, Result};
+use clap::{Args, ValueEnum};
+use dialoguer::{Input, Select};
+use hexvar_core::VarFormat;
+use std::fs;
+use std::io::IsTerminal;
+use std::path::Path;
+use tracing::info;
+
+/// Flags for the `init` command
+#[derive(Args)]
+pub struct InitArgs {
+    /// Write the default config without prompting
+    #[arg(long)]
+    pub defaults: bool,
+    /// Overwrite an existing hexvar.toml
+    #[arg(long)]
+    pub force: bool,
+}
+
+/// Split a comma-separated answer into trimmed, non-empty items
+fn split_list(answer: &str) -> Vec<String> {
+    answer.split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_string).collect()
+}
+
+/// Ask for each setting, offering the defaults
+fn prompt() -> Result<StarterConfig> {
+    let defaults = StarterConfig::default();
+    let patterns: String = Input::new()
+        .with_prompt("Glob patterns to scan (comma-separated)")
+        .default(defaults.patterns.join(", "))
+        .interact_text()?;
+    let ignore: String = Input::new()
+        .with_prompt("Directories or patterns to ignore (comma-separated)")
+        .default(defaults.ignore.join(", "))
+        .allow_empty(true)
+        .interact_text()?;
+    let delta_e: f32 = Input::new()
+        .with_prompt("Delta E threshold for merging similar colors (0 disables clustering)")
+        .default(defaults.delta_e)
+        .validate_with(|d: &f32| if *d >= 0.0 { Ok(()) } else { Err("must be 0 or more") })
+        .interact_text()?;
+    let formats = VarFormat::value_variants();
+    let names: Vec<String> =
+        formats.iter().filter_map(|f| f.to_possible_value()).map(|v| v.get_name().to_string()).collect();
+    let format = Select::new()
+        .with_prompt("Syntax of the variables file")
+        .items(&names)
+        .default(formats.iter().position(|f| *f == defaults.format).unwrap_or(0))
+        .interact()?;
+    let css_vars: String = Input::new()
+        .with_prompt("Output file for the variables")
+        .default(defaults.css_vars)
+        .interact_text()?;
+    let out: String = Input::new()
+        .with_prompt("Output file for the JSON report (empty for stdout)")
+        .allow_empty(true)
+        .interact_text()?;
+    Ok(StarterConfig {
+        patterns: split_list(&patterns),
+        ignore: split_list(&ignore),
+        delta_e,
+        format: formats[format],
+        css_vars,
+        out: Some(out.trim().to_string()).filter(|o| !o.is_empty()),
+    })
+}
+
+/// Run the `init` command: ask for settings (or take the defaults) and write hexvar.toml
+pub fn run(args: &InitArgs) -> Result<()> {
+    let path = CONFIG_FILES[0];
+    if Path::new(path).exists() && !args.force {
+        bail!("{} already exists; pass --force to overwrite it", path);
+    }
+    let config = if args.defaults {
+        StarterConfig::default()
+    } else {
+        if !std::io::stdin().is_terminal() {
+            bail!("stdin is not a terminal; pass --defaults to write the default config");
+        }
+        prompt().context("Failed to read answers")?
+    };
+    fs::write(path, config.render()).with_context(|| format!("Failed to write {}", path))?;
+    info!("Wrote config to {}", path);
+    info!("Run `hexvar scan` to scan {} with it", config.patterns.join(", "));
+    Ok(())
+}
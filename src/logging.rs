@@ -0,0 +1,55 @@
+use std::fmt;
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::writer::MakeWriterExt;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::EnvFilter;
+
+/// Formats events as plain console output: `info!` as the bare message,
+/// warnings and errors with a `Warning:`/`Error:` prefix, and debug and trace
+/// messages with their level and module
+struct ConsoleFormat;
+
+impl<S, N> FormatEvent<S, N> for ConsoleFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(&self, ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> fmt::Result {
+        let meta = event.metadata();
+        match *meta.level() {
+            Level::ERROR => write!(writer, "Error: ")?,
+            Level::WARN => write!(writer, "Warning: ")?,
+            Level::INFO => {}
+            level => write!(writer, "[{} {}] ", level, meta.target())?,
+        }
+        ctx.field_format().format_fields(writer.by_ref(), event)?;
+        writeln!(writer)
+    }
+}
+
+/// Install the global subscriber: `info` and above by default, `debug` with
+/// `verbose`, and only warnings and errors with `quiet`. `RUST_LOG`, when set,
+/// overrides both. Info messages go to stdout, unless `stdout_is_output` because
+/// the command writes its result there, and everything else to stderr.
+pub fn init(verbose: bool, quiet: bool, stdout_is_output: bool) {
+    let level = match (verbose, quiet) {
+        (true, _) => Level::DEBUG,
+        (_, true) => Level::WARN,
+        _ => Level::INFO,
+    };
+    // Dependencies stay at `warn` so --verbose only adds hexvar's own diagnostics
+    let level = level.as_str().to_ascii_lowercase();
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(format!("warn,hexvar={0},hexvar_core={0}", level)));
+    let writer = std::io::stdout
+        .with_filter(move |meta| !stdout_is_output && *meta.level() == Level::INFO)
+        .or_else(std::io::stderr);
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(writer)
+        .with_ansi(false)
+        .event_format(ConsoleFormat)
+        .init();
+}
@@ -1,373 +1,787 @@
-use clap::{Parser, Subcommand};
-use glob::glob;
-
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum, ValueHint};
+use dialoguer::theme::Theme;
+use dialoguer::Confirm;
+use hexvar_core::color::HEX_PATTERN;
+use hexvar_core::cluster::DistanceMetric;
+use hexvar_core::explain::{Explanation, ThresholdSource};
+use hexvar_core::matrix::{self, DistanceMatrix};
+use hexvar_core::normalize::normalize_content;
+use hexvar_core::paths::{self, collect_paths_with};
+use hexvar_core::replace::js::{self, JsStyle};
+use hexvar_core::scanner::{component, svg};
+use hexvar_core::stats::health::PaletteHealth;
+use hexvar_core::validate::ValidationReport;
+use hexvar_core::{backup, convert, diff, replace, ColorReport};
 use regex::Regex;
-use serde::Serialize;
-use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
-use indicatif::{ProgressBar, ProgressStyle};
-mod css_color_names;
+use std::io::Write;
+use std::path::Path;
+use tracing::{debug, error, info};
+mod config;
+mod init;
+mod logging;
+mod merge;
+mod scan;
+mod watch;
 
 /// Scan CSS/SCSS files for unique Hex colors and output JSON report
 #[derive(Subcommand)]
 enum Commands {
     /// Scan and report hex colors (existing logic)
-    Scan {
-        /// Output CSS file with variables for each hex code
-        #[arg(long, value_name = "FILE")]
-        css_vars: Option<String>,
+    Scan(scan::ScanArgs),
+    /// Watch matched files and re-run the scan whenever they change
+    Watch(scan::ScanArgs),
+    /// Replace hex codes in files with CSS variables using colours_map.json
+    Replace {
         /// Glob patterns to include (e.g., "src/**/*.css")
-        #[arg(value_name = "GLOB", required = true)]
+        #[arg(value_name = "GLOB", value_hint = ValueHint::AnyPath, required = true)]
         patterns: Vec<String>,
-        /// Glob patterns or directories to ignore
-        #[arg(short, long, value_name = "IGNORE")]
-        ignore: Vec<String>,
-        /// Output file for JSON report (default: stdout)
-        #[arg(short, long, value_name = "FILE")]
-        out: Option<String>,
+        #[command(flatten)]
+        filter: scan::FilterArgs,
+        /// Print a unified diff of the changes without writing any files.
+        /// Exits with code 1 if any file would be modified.
+        #[arg(long, conflicts_with = "check")]
+        dry_run: bool,
+        /// List files that still contain replaceable hex codes without writing anything.
+        /// Exits with code 1 if any are found, for CI.
+        #[arg(long)]
+        check: bool,
+        /// Output format for --check
+        #[arg(long, value_enum, default_value_t = CheckFormat::Text, requires = "check")]
+        format: CheckFormat,
+        /// Only replace hex codes in the values of this CSS property (repeatable),
+        /// e.g. --only-property color --only-property background-color
+        #[arg(long, value_name = "PROPERTY")]
+        only_property: Vec<String>,
+        /// CSS variables file written by `scan --css-vars` to read variable names from
+        #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath, default_value = scan::DEFAULT_CSS_VARS_FILE)]
+        css_vars: String,
+        /// End each rewritten declaration with a comment recording the original value,
+        /// e.g. `color: var(--color-red); /* was: #FF0000 */`
+        #[arg(long)]
+        annotate: bool,
+        /// Comment syntax for --annotate. `line` comments are only written to
+        /// SCSS, Sass, Less, and Stylus files; other files always get `block`
+        #[arg(long, value_enum, default_value_t = replace::CommentStyle::Block, requires = "annotate")]
+        comment_style: replace::CommentStyle,
+        /// What colors in JS/TS files become: `var(--color-x)`, or a constant
+        /// imported from the tokens file written by `scan --format js`
+        #[arg(long, value_enum, default_value_t = JsStyle::Var)]
+        js_style: JsStyle,
+        /// How replaced colors refer to their variable: `var(--color-x)`, `$color-x`,
+        /// `@color-x`, or `theme('colors.x')`. The --css-vars file may define the
+        /// names as `--color-x`, `$color-x`, or `@color-x`
+        #[arg(long, value_enum, default_value_t = replace::VarSyntax::Css)]
+        var_syntax: replace::VarSyntax,
+        /// In Less files, normalize the hex code of each `@name: #hex;` declaration to
+        /// its canonical color instead of replacing it with a reference
+        #[arg(long)]
+        update_less_declarations: bool,
+        /// JS/TS tokens file to import constants from with --js-style import
+        #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath, default_value = scan::DEFAULT_TOKENS_FILE)]
+        tokens_file: String,
+        /// Copy each file to <path><SUFFIX> before rewriting it [default suffix: .bak]
+        #[arg(long, value_name = "SUFFIX", num_args = 0..=1, require_equals = true, default_missing_value = backup::DEFAULT_BACKUP_SUFFIX, conflicts_with_all = ["dry_run", "check"])]
+        backup: Option<String>,
+        /// JSON file to append a record of every substitution to
+        #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath, default_value = replace::manifest::DEFAULT_MANIFEST_FILE)]
+        manifest: String,
+        /// Skip files not modified since the manifest was last written.
+        /// Without a manifest from an earlier run, every file is processed
+        #[arg(long, conflicts_with_all = ["dry_run", "check"])]
+        incremental: bool,
+        /// Also write the changes to every file as one unified diff, to review or to
+        /// apply elsewhere with `patch -p0 < FILE` [default file: hexvar_diff.patch]
+        #[arg(long, value_name = "FILE", num_args = 0..=1, require_equals = true, default_missing_value = DEFAULT_PATCH_FILE, conflicts_with_all = ["dry_run", "check"])]
+        diff: Option<String>,
+        /// Show the diff of each file and ask before rewriting it: `y` writes it,
+        /// `n` (the default) skips it, and `q` stops before the remaining files
+        #[arg(long, conflicts_with_all = ["dry_run", "check"])]
+        interactive: bool,
     },
-    /// Replace hex codes in files with CSS variables using colours_map.json
-    Replace {
+    /// Restore files backed up by `replace --backup` and remove the backups
+    Restore {
+        /// Glob patterns of original files to restore (e.g., "src/**/*.css")
+        #[arg(value_name = "GLOB", value_hint = ValueHint::AnyPath, required = true)]
+        patterns: Vec<String>,
+    },
+    /// Report CSS variables defined in the vars file but never referenced via var()
+    Audit {
+        /// Glob patterns of source files to check for var() references
+        #[arg(value_name = "GLOB", value_hint = ValueHint::AnyPath, required = true)]
+        patterns: Vec<String>,
+        #[command(flatten)]
+        filter: scan::FilterArgs,
+        /// CSS variables file to audit
+        #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath, default_value = scan::DEFAULT_CSS_VARS_FILE)]
+        css_vars: String,
+        /// Exit with code 1 if any variable is unused (for CI)
+        #[arg(long)]
+        error_on_unused: bool,
+    },
+    /// Check that the mapping, the CSS variables file, and the sources agree: every
+    /// canonical color has a variable, every variable is in the mapping, and no
+    /// mapped color is left for `replace`. Exits with code 1 on any problem.
+    Validate {
+        /// Glob patterns of source files that should use variables only
+        #[arg(value_name = "GLOB", value_hint = ValueHint::AnyPath, required = true)]
+        patterns: Vec<String>,
+        #[command(flatten)]
+        filter: scan::FilterArgs,
+        /// CSS variables file written by `scan --css-vars`
+        #[arg(
+            long,
+            visible_alias = "css",
+            value_name = "FILE",
+            value_hint = ValueHint::FilePath,
+            default_value = scan::DEFAULT_CSS_VARS_FILE
+        )]
+        css_vars: String,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = CheckFormat::Text)]
+        format: CheckFormat,
+    },
+    /// Compare two scan JSON reports and list added, removed, and changed colors
+    Diff {
+        /// Earlier scan report
+        #[arg(value_name = "BEFORE", value_hint = ValueHint::FilePath)]
+        before: String,
+        /// Later scan report
+        #[arg(value_name = "AFTER", value_hint = ValueHint::FilePath)]
+        after: String,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = DiffFormat::Json)]
+        format: DiffFormat,
+        /// Suppress entries whose count changed by less than N
+        #[arg(long, value_name = "N", default_value_t = 0)]
+        threshold: u32,
+    },
+    /// Print the Delta E distance between every pair of canonical colors in a scan report
+    Matrix {
+        /// Scan report to cluster
+        #[arg(value_name = "REPORT", value_hint = ValueHint::FilePath)]
+        report: String,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = MatrixFormat::Csv)]
+        format: MatrixFormat,
+        /// Only list the pairs closer than this Delta E, closest first, instead of the full matrix
+        #[arg(long, value_name = "T")]
+        threshold: Option<f32>,
+        /// Delta E threshold for merging similar colors into canonical ones first (0 disables clustering)
+        #[arg(long, default_value_t = 10.0, value_parser = scan::parse_delta_e)]
+        delta_e: f32,
+    },
+    /// Print palette health metrics for a scan report, without rescanning the sources
+    Stats {
+        /// Scan report to analyze
+        #[arg(value_name = "REPORT", value_hint = ValueHint::FilePath)]
+        report: String,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = CheckFormat::Text)]
+        format: CheckFormat,
+        /// Delta E threshold for the variable savings and most-merged clusters
+        #[arg(long, default_value_t = 10.0, value_parser = scan::parse_delta_e)]
+        delta_e: f32,
+    },
+    /// Show why two colors were or were not merged by the last scan: their Lab
+    /// coordinates, the Delta E between them, and the cluster each is in
+    Explain {
+        /// First hex code, e.g. "#1a2b3c"
+        #[arg(value_name = "HEX")]
+        a: String,
+        /// Second hex code
+        #[arg(value_name = "HEX")]
+        b: String,
+        /// CSS variables file written by `scan --css-vars`, to name the clusters
+        #[arg(
+            long,
+            visible_alias = "css",
+            value_name = "FILE",
+            value_hint = ValueHint::FilePath,
+            default_value = scan::DEFAULT_CSS_VARS_FILE
+        )]
+        css_vars: String,
+        /// Delta E threshold the scan merged colors at [default: delta_e in hexvar.toml, or 10]
+        #[arg(long, value_parser = scan::parse_delta_e)]
+        delta_e: Option<f32>,
+        /// Color difference formula the scan measured Delta E with
+        #[arg(long, value_enum, default_value_t)]
+        color_space: DistanceMetric,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = CheckFormat::Text)]
+        format: CheckFormat,
+    },
+    /// Combine scan reports, e.g. one per package in a monorepo, by summing (or subtracting) counts
+    Merge(merge::MergeArgs),
+    /// Normalize hex code casing and expand short forms in place, without introducing variables
+    Convert {
         /// Glob patterns to include (e.g., "src/**/*.css")
-        #[arg(value_name = "GLOB", required = true)]
+        #[arg(value_name = "GLOB", value_hint = ValueHint::AnyPath, required = true)]
         patterns: Vec<String>,
-        /// Glob patterns or directories to ignore
-        #[arg(short, long, value_name = "IGNORE")]
-        ignore: Vec<String>,
+        #[command(flatten)]
+        filter: scan::FilterArgs,
+        /// Rewrite hex digits in uppercase
+        #[arg(long, conflicts_with = "lowercase")]
+        uppercase: bool,
+        /// Rewrite hex digits in lowercase
+        #[arg(long)]
+        lowercase: bool,
+        /// Expand #rgb to #rrggbb and #rgba to #rrggbbaa
+        #[arg(long)]
+        expand_short: bool,
+        /// Print a unified diff of the changes without writing any files.
+        /// Exits with code 1 if any file would be modified.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Rewrite the letter case of hex codes in place, and nothing else: unlike
+    /// `convert`, short forms are never expanded
+    #[command(group = clap::ArgGroup::new("case").required(true).args(["lowercase", "uppercase"]))]
+    Normalize {
+        /// Glob patterns to include (e.g., "src/**/*.css")
+        #[arg(value_name = "GLOB", value_hint = ValueHint::AnyPath, required = true)]
+        patterns: Vec<String>,
+        #[command(flatten)]
+        filter: scan::FilterArgs,
+        /// Rewrite hex digits in lowercase
+        #[arg(long)]
+        lowercase: bool,
+        /// Rewrite hex digits in uppercase
+        #[arg(long)]
+        uppercase: bool,
+        /// Print a unified diff of the changes without writing any files.
+        /// Exits with code 1 if any file would be modified.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Create a hexvar.toml in the current directory, prompting for each setting
+    Init(init::InitArgs),
+    /// Print a shell completion script to stdout
+    #[command(after_help = COMPLETIONS_HELP)]
+    Completions {
+        /// Shell to generate completions for
+        #[arg(value_enum)]
+        shell: CompletionShell,
     },
 }
 
+impl Commands {
+    /// Whether the command rewrites standard input to stdout, as `replace -` does
+    fn pipes_stdin(&self) -> bool {
+        let reads_stdin = |patterns: &[String]| patterns.iter().any(|p| p == paths::STDIN_PATTERN);
+        match self {
+            Commands::Replace { patterns, dry_run, check, .. } => !dry_run && !check && reads_stdin(patterns),
+            Commands::Convert { patterns, dry_run, .. } => !dry_run && reads_stdin(patterns),
+            Commands::Normalize { patterns, dry_run, .. } => !dry_run && reads_stdin(patterns),
+            _ => false,
+        }
+    }
+}
+
+/// Prompt theme for `replace --interactive`, whose confirmations can also be quit with `q`
+struct QuitTheme;
+
+impl Theme for QuitTheme {
+    fn format_confirm_prompt(&self, f: &mut dyn std::fmt::Write, prompt: &str, _default: Option<bool>) -> std::fmt::Result {
+        write!(f, "{} [y/N/q] ", prompt)
+    }
+}
+
+const COMPLETIONS_HELP: &str = "\
+Installation:
+  bash:        hexvar completions bash > ~/.local/share/bash-completion/completions/hexvar
+  zsh:         hexvar completions zsh > ~/.zfunc/_hexvar
+               then add `fpath+=~/.zfunc; autoload -Uz compinit; compinit` to ~/.zshrc
+  fish:        hexvar completions fish > ~/.config/fish/completions/hexvar.fish
+  powershell:  hexvar completions powershell >> $PROFILE
+
+Restart the shell (or source the file) to load the completions.";
+
+/// Shells `hexvar completions` can generate scripts for
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum CompletionShell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+}
+
+impl From<CompletionShell> for clap_complete::Shell {
+    fn from(shell: CompletionShell) -> Self {
+        match shell {
+            CompletionShell::Bash => clap_complete::Shell::Bash,
+            CompletionShell::Zsh => clap_complete::Shell::Zsh,
+            CompletionShell::Fish => clap_complete::Shell::Fish,
+            CompletionShell::Powershell => clap_complete::Shell::PowerShell,
+        }
+    }
+}
+
 #[derive(Parser)]
 #[command(name = "hexvar")]
 #[command(about = "Scan stylesheets for hex colors", long_about = None)]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    #[command(flatten)]
+    output: scan::OutputArgs,
+    /// Also print debug diagnostics, e.g. why files were skipped or colors merged
+    #[arg(short, long, global = true, conflicts_with = "quiet")]
+    verbose: bool,
+    /// Only print warnings and errors (and the requested output, like JSON reports)
+    #[arg(short, long, global = true)]
+    quiet: bool,
 }
 
-#[derive(Serialize)]
-struct ColorReport(HashMap<String, u32>);
+/// Output style for `hexvar diff`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum DiffFormat {
+    Json,
+    Table,
+}
 
-fn main() {
-    let cli = Cli::parse();
-    match &cli.command {
-        Commands::Scan { patterns, css_vars, out, ignore } => {
-            // Regex to match 8, 6, or 3 digit hex codes (longest first, not 4)
-            let re = Regex::new(r"#(?:[0-9a-fA-F]{8}|[0-9a-fA-F]{6}|[0-9a-fA-F]{3})").unwrap();
+/// Output style for `hexvar matrix`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum MatrixFormat {
+    Csv,
+    Json,
+}
 
-            // Collect all file paths matching patterns (ignoring ignores)
-            let mut paths: Vec<PathBuf> = Vec::new();
-            // Default file extensions to scan
-            let default_exts = ["css", "scss", "sass", "vue", "astro", "svelte"];
-            let _use_default_exts = patterns.len() == 1 && patterns[0] == "/**/*";
+/// Output style for `hexvar replace --check`, `hexvar validate`, and `hexvar stats`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum CheckFormat {
+    Text,
+    Json,
+}
 
-            for pat in patterns {
-                for entry in glob(pat).expect("Invalid glob pattern") {
-                    if let Ok(path) = entry {
-                        // skip if matches any ignore pattern
-                        if ignore.iter().any(|ig| path.to_string_lossy().contains(ig)) {
-                            continue;
-                        }
-                        // Always ignore anything in common output directories
-                        const OUTPUT_DIRS: &[&str] = &[
-                            "node_modules", "dist", "build", "out", ".next", ".vercel", ".cache", "coverage", "target"
-                        ];
-                        if path.components().any(|c| {
-                            let s = c.as_os_str().to_string_lossy();
-                            OUTPUT_DIRS.contains(&s.as_ref())
-                        }) {
-                            continue;
-                        }
-                        // Only include files with allowed extensions
-                        if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-                            if !default_exts.contains(&ext) {
-                                continue;
-                            }
-                        } else {
-                            continue;
-                        }
-                        paths.push(path);
-                    }
-                }
+/// Rewrite the hex codes in every file matching `patterns` in place with
+/// `rewrite`, which returns new text and how many codes it changed, or print
+/// the diff with `dry_run`. `verb` is the command's name and its past tense
+/// for the log. Only the CSS inside SVG files is rewritten.
+fn rewrite_hex_codes(
+    patterns: &[String],
+    filter: &scan::FilterArgs,
+    dry_run: bool,
+    verb: (&str, &str),
+    rewrite: impl Fn(&str) -> (String, usize),
+) -> Result<()> {
+    let mut files_changed = 0;
+    for path in collect_paths_with(patterns, &filter.path_filter())? {
+        let content = match paths::read_source(&path) {
+            Ok(content) => content,
+            Err(e) => {
+                debug!("Skipping {}: {}", paths::display_name(&path), e);
+                continue;
             }
-            // Set up progress bar
-            let file_count = paths.len();
-            let pb = ProgressBar::new(file_count as u64);
-            pb.set_style(ProgressStyle::with_template("{spinner} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} {msg}")
-                .unwrap()
-                .progress_chars("|/-\\ "));
+        };
+        let rewritten = if svg::is_svg(&path) {
+            svg::rewrite_fragments(&content, |_| true, &rewrite).0
+        } else if component::is_component(&path) {
+            component::rewrite_styles(&content, &rewrite).0
+        } else {
+            rewrite(&content).0
+        };
+        let stdin = paths::is_stdin(&path);
+        if stdin && !dry_run {
+            print!("{}", rewritten);
+        }
+        if rewritten == content {
+            continue;
+        }
+        files_changed += 1;
+        if dry_run {
+            let name = paths::display_name(&path);
+            let diff = similar::TextDiff::from_lines(&content, &rewritten);
+            print!("{}", diff.unified_diff().header(&name, &name));
+            continue;
+        }
+        if stdin {
+            continue;
+        }
+        paths::write_atomic(&path, &rewritten).with_context(|| format!("Failed to write {}", path.display()))?;
+        info!("{} hex codes in {}", verb.1, path.display());
+    }
+    if dry_run {
+        info!("Would {} hex codes in {} files", verb.0, files_changed);
+        if files_changed > 0 {
+            std::process::exit(1);
+        }
+    } else {
+        info!("{} hex codes in {} files", verb.1, files_changed);
+    }
+    Ok(())
+}
 
-            // Scan files and count hex codes (sequential, for progress bar UX)
-            let mut counts: HashMap<String, u32> = HashMap::new();
-            for path in &paths {
-                let fullpath = path.display().to_string();
-                pb.set_message(fullpath);
-                pb.inc(1);
-                if let Ok(content) = fs::read_to_string(path) {
-                    for m in re.find_iter(&content) {
-                        let hex = m.as_str().to_string();
-                        *counts.entry(hex).or_insert(0) += 1;
-                    }
-                }
-            }
-            pb.finish_and_clear();
+/// Whether `path` is a Less stylesheet
+fn is_less(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("less"))
+}
 
-            let total: u32 = counts.values().sum();
-            let unique = counts.len();
+/// Patch file written by `replace --diff` without a file name
+const DEFAULT_PATCH_FILE: &str = "hexvar_diff.patch";
 
-            println!("\n==== HEXVAR SUMMARY ====");
-            if unique == 0 {
-                println!("No hex codes found in {} files.", file_count);
-            } else {
-                println!("Files scanned:      {}", file_count);
-                println!("Unique hex codes:   {}", unique);
-                println!("Total occurrences:  {}", total);
-            }
-            println!("=======================\n");
+/// Write a generated file and report it on the console
+fn write_output(path: &str, contents: &str, what: &str) -> Result<()> {
+    fs::write(path, contents).with_context(|| format!("Failed to write {} file {}", what, path))?;
+    info!("Wrote {} to {}", what, path);
+    Ok(())
+}
 
-            // If requested, generate CSS variables file
-            if let Some(css_path) = &css_vars {
-                use std::io::Write;
-                use palette::{Srgb, Lab, FromColor};
-                use palette::color_difference::DeltaE;
-                let mut css = String::from(":root {\n");
-                let delta_e_threshold = 10.0;
-                let mut clusters: Vec<(String, Lab)> = Vec::new(); // (canonical hex, Lab)
-                let mut hex_to_canonical: std::collections::HashMap<String, String> = std::collections::HashMap::new();
-                // Precompute LAB for all hexes
-                let mut hex_lab: std::collections::HashMap<&String, Lab> = std::collections::HashMap::new();
-                for hex in counts.keys() {
-                    let rgb = hex.trim_start_matches('#');
-                    let (r, g, b) = match rgb.len() {
-                        3 => {
-                            let r = u8::from_str_radix(&rgb[0..1].repeat(2), 16).unwrap_or(0);
-                            let g = u8::from_str_radix(&rgb[1..2].repeat(2), 16).unwrap_or(0);
-                            let b = u8::from_str_radix(&rgb[2..3].repeat(2), 16).unwrap_or(0);
-                            (r, g, b)
-                        },
-                        6 => {
-                            let r = u8::from_str_radix(&rgb[0..2], 16).unwrap_or(0);
-                            let g = u8::from_str_radix(&rgb[2..4], 16).unwrap_or(0);
-                            let b = u8::from_str_radix(&rgb[4..6], 16).unwrap_or(0);
-                            (r, g, b)
-                        },
-                        _ => { continue; }
-                    };
-                    let lab: Lab = Lab::from_color(Srgb::new(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0));
-                    hex_lab.insert(hex, lab);
-                }
-                // Clustering
-                for hex in counts.keys() {
-                    if let Some(&lab) = hex_lab.get(hex) {
-                        let mut canonical: Option<String> = None;
-                        for (canon_hex, canon_lab) in &clusters {
-                            if lab.delta_e(*canon_lab) < delta_e_threshold {
-                                canonical = Some(canon_hex.clone());
-                                break;
-                            }
-                        }
-                        if let Some(canon_hex) = canonical {
-                            hex_to_canonical.insert(hex.clone(), canon_hex);
-                        } else {
-                            clusters.push((hex.clone(), lab));
-                            hex_to_canonical.insert(hex.clone(), hex.clone());
-                        }
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    logging::init(cli.verbose, cli.quiet, cli.command.pipes_stdin());
+    match &cli.command {
+        Commands::Scan(args) => scan::run(args, &cli.output)?,
+        Commands::Watch(args) => watch::run(args, &cli.output)?,
+        Commands::Replace {
+            patterns,
+            filter,
+            dry_run,
+            check,
+            format,
+            backup,
+            only_property,
+            css_vars,
+            annotate,
+            comment_style,
+            js_style,
+            var_syntax,
+            update_less_declarations,
+            tokens_file,
+            manifest: replace_manifest,
+            incremental,
+            diff: patch_file,
+            interactive,
+        } => {
+            if *interactive && patterns.iter().any(|p| p == paths::STDIN_PATTERN) {
+                bail!("--interactive can't prompt while reading from standard input");
+            }
+            let map_path = cli.output.map_path();
+            let css_path = cli.output.path(css_vars);
+            let tokens_path = cli.output.path(tokens_file);
+            let hex_to_const = match js_style {
+                JsStyle::Import => Some(js::load_hex_to_const(Path::new(&map_path), Path::new(&tokens_path))?),
+                JsStyle::Var => None,
+            };
+            // Built strictly from the CSS variables file, once a file needs it
+            let mut hex_to_var = None;
+            let mut hex_to_canonical = None;
+            // For each file matching glob
+            let mut total_replacements = 0;
+            let mut files_changed = 0;
+            let mut check_report = replace::CheckReport::default();
+            let manifest_path = Path::new(backup::BACKUP_MANIFEST_FILE);
+            let mut manifest = backup::BackupManifest::load(manifest_path).unwrap_or_default();
+            let mut entries = Vec::new();
+            let replace_manifest = cli.output.path(replace_manifest);
+            let last_run =
+                if *incremental { replace::manifest::LastRun::load(Path::new(&replace_manifest))? } else { None };
+            if *incremental && last_run.is_none() {
+                debug!("No manifest at {}, processing every file", replace_manifest);
+            }
+            let mut skipped = 0;
+            let mut patch = String::new();
+            let (mut accepted, mut declined, mut aborted) = (0, 0, 0);
+            let paths = collect_paths_with(patterns, &filter.path_filter())?;
+            // Set once `q` is answered; the files after it are still rewritten in
+            // memory, but only to count the ones that would have changed
+            let mut quit = false;
+            for path in paths {
+                if let Some(last_run) = &last_run {
+                    if !paths::is_stdin(&path) && last_run.is_unchanged(&path.display().to_string(), &path) {
+                        debug!("Skipping {}: not modified since the last run", path.display());
+                        skipped += 1;
+                        continue;
                     }
                 }
-                // Output CSS vars for canonical colors only
-                for (canon_hex, _) in &clusters {
-                    // Try to find a CSS color name for this hex
-                    let mut var = None;
-                    for (name, css_hex) in css_color_names::CSS_COLOR_NAMES.iter() {
-                        if css_hex.eq_ignore_ascii_case(canon_hex) {
-                            var = Some(format!("--color-{}", name.replace('_', "-")));
-                            break;
-                        }
+                let content = match paths::read_source(&path) {
+                    Ok(content) => content,
+                    Err(e) => {
+                        debug!("Skipping {}: {}", paths::display_name(&path), e);
+                        continue;
                     }
-                    // If no exact match, find closest CSS color by Euclidean RGB distance
-                    let var = var.unwrap_or_else(|| {
-                        fn hex_to_rgb(hex: &str) -> Option<(u8, u8, u8)> {
-                            let hex = hex.trim_start_matches('#');
-                            match hex.len() {
-                                3 => {
-                                    let r = u8::from_str_radix(&hex[0..1].repeat(2), 16).ok()?;
-                                    let g = u8::from_str_radix(&hex[1..2].repeat(2), 16).ok()?;
-                                    let b = u8::from_str_radix(&hex[2..3].repeat(2), 16).ok()?;
-                                    Some((r, g, b))
-                                }
-                                6 => {
-                                    let r = u8::from_str_radix(&hex[0..2], 16).ok()?;
-                                    let g = u8::from_str_radix(&hex[2..4], 16).ok()?;
-                                    let b = u8::from_str_radix(&hex[4..6], 16).ok()?;
-                                    Some((r, g, b))
-                                }
-                                _ => None
-                            }
+                };
+                let (replaced, subs) = match &hex_to_const {
+                    Some(hex_to_const) if js::is_js(&path) => {
+                        let specifier = js::module_specifier(&path, Path::new(&tokens_path));
+                        js::import_replace_recorded(&content, hex_to_const, &specifier)
+                    }
+                    _ => {
+                        if hex_to_var.is_none() {
+                            let vars = replace::load_hex_to_var(Path::new(&map_path), Path::new(&css_path))?;
+                            hex_to_var = Some(var_syntax.references(&vars));
                         }
-                        let (r, g, b) = match hex_to_rgb(canon_hex) {
-                            Some(rgb) => rgb,
-                            None => return format!("--color-{}", canon_hex.trim_start_matches('#').to_lowercase()),
+                        let hex_to_var = hex_to_var.as_ref().unwrap();
+                        let rewrite = |css: &str| {
+                            if *annotate {
+                                replace::annotated_replace_recorded(css, hex_to_var, only_property, comment_style.for_path(&path))
+                            } else {
+                                replace::property_aware_replace_recorded(css, hex_to_var, only_property)
+                            }
                         };
-                        let mut min_dist = u32::MAX;
-                        let mut closest = None;
-                        for (name, css_hex) in css_color_names::CSS_COLOR_NAMES.iter() {
-                            if let Some((cr, cg, cb)) = hex_to_rgb(css_hex) {
-                                let dist = (r as i32 - cr as i32).pow(2) as u32
-                                 + (g as i32 - cg as i32).pow(2) as u32
-                                 + (b as i32 - cb as i32).pow(2) as u32;
-                                if dist < min_dist {
-                                    min_dist = dist;
-                                    closest = Some(name);
-                                }
+                        // SVG presentation attributes and component markup can't hold var(),
+                        // so only rewrite their CSS
+                        if svg::is_svg(&path) {
+                            svg::rewrite_css_recorded(&content, rewrite)
+                        } else if component::is_component(&path) {
+                            component::rewrite_styles_recorded(&content, rewrite)
+                        } else if *update_less_declarations && is_less(&path) {
+                            if hex_to_canonical.is_none() {
+                                hex_to_canonical = Some(replace::load_hex_to_canonical(Path::new(&map_path))?);
                             }
-                        }
-                        if let Some(name) = closest {
-                            format!("--color-{}", name.replace('_', "-"))
+                            replace::less_declarations_recorded(&content, hex_to_canonical.as_ref().unwrap(), rewrite)
                         } else {
-                            format!("--color-{}", canon_hex.trim_start_matches('#').to_lowercase())
-                        }
-                    });
-                    css.push_str(&format!("    {}: {};", var, canon_hex));
-                    css.push('\n');
-                }
-                css.push_str("}\n");
-                // Build canonical_map for reporting
-                let mut canonical_map: std::collections::HashMap<&String, Vec<&String>> = std::collections::HashMap::new();
-                for (hex, canon) in &hex_to_canonical {
-                    canonical_map.entry(canon).or_default().push(hex);
-                }
-                // Output the mapping of canonical hex -> all merged hexes
-                let map_path = "colours_map.json";
-                match std::fs::File::create(map_path) {
-                    Ok(mut file) => {
-                        if let Err(e) = serde_json::to_writer_pretty(&mut file, &canonical_map) {
-                            eprintln!("Failed to write mapping file {}: {}", map_path, e);
-                        } else {
-                            println!("Wrote canonical color mapping to {}", map_path);
+                            rewrite(&content)
                         }
                     }
-                    Err(e) => eprintln!("Failed to create mapping file {}: {}", map_path, e),
+                };
+                let file_replacements = subs.len();
+                let stdin = paths::is_stdin(&path);
+                if stdin && !*check && !*dry_run {
+                    // Standard input is written back out whole, changed or not
+                    print!("{}", replaced);
                 }
-                // CLI output about optimization
-                let unique_hexes = counts.len();
-                let canonical_count = canonical_map.len();
-                println!(
-                    "Optimization: Reduced {unique_hexes} unique hex codes to {canonical_count} canonical CSS variables using perceptual color clustering (Delta E < {delta_e}).\nSee colours_map.json for mappings.",
-                    unique_hexes = unique_hexes,
-                    canonical_count = canonical_count,
-                    delta_e = delta_e_threshold
-                );
-                match std::fs::File::create(css_path) {
-                    Ok(mut file) => {
-                        if let Err(e) = file.write_all(css.as_bytes()) {
-                            eprintln!("Failed to write CSS vars file {}: {}", css_path, e);
-                        } else {
-                            println!("Wrote CSS variables to {}", css_path);
+                if replaced != content {
+                    if quit {
+                        aborted += 1;
+                        continue;
+                    }
+                    if *interactive {
+                        let name = paths::display_name(&path);
+                        let diff = similar::TextDiff::from_lines(&content, &replaced);
+                        print!("{}", diff.unified_diff().header(&name, &name));
+                        let answer = Confirm::with_theme(&QuitTheme)
+                            .with_prompt(format!("Replace in {}?", name))
+                            .default(false)
+                            .interact_opt()
+                            .context("--interactive needs a terminal to prompt on")?;
+                        match answer {
+                            Some(true) => accepted += 1,
+                            Some(false) => {
+                                declined += 1;
+                                continue;
+                            }
+                            None => {
+                                // This file and every one after it are left as they are
+                                quit = true;
+                                aborted += 1;
+                                continue;
+                            }
                         }
                     }
-                    Err(e) => eprintln!("Failed to create CSS vars file {}: {}", css_path, e),
+                    files_changed += 1;
+                    total_replacements += file_replacements;
+                    if *check {
+                        check_report.files.push(paths::display_name(&path));
+                        check_report.total_unresolved += file_replacements;
+                        continue;
+                    }
+                    if *dry_run {
+                        let name = paths::display_name(&path);
+                        let diff = similar::TextDiff::from_lines(&content, &replaced);
+                        print!("{}", diff.unified_diff().header(&name, &name));
+                        continue;
+                    }
+                    if stdin {
+                        info!("Replaced {} hex codes in {}", file_replacements, paths::STDIN_NAME);
+                        continue;
+                    }
+                    if patch_file.is_some() {
+                        let name = paths::display_name(&path);
+                        let diff = similar::TextDiff::from_lines(&content, &replaced);
+                        patch.push_str(&diff.unified_diff().header(&name, &name).to_string());
+                    }
+                    if let Some(suffix) = backup {
+                        manifest.backup(&path, suffix).with_context(|| format!("Failed to back up {}", path.display()))?;
+                    }
+                    paths::write_atomic(&path, &replaced).with_context(|| format!("Failed to write {}", path.display()))?;
+                    let file = path.display().to_string();
+                    let mtime = replace::manifest::mtime_ms(&path);
+                    entries.extend(
+                        replace::manifest::ManifestEntry::from_substitutions(&file, &content, &subs)
+                            .into_iter()
+                            .map(|entry| replace::manifest::ManifestEntry { mtime, ..entry }),
+                    );
+                    info!("Replaced {} hex codes in {}", file_replacements, path.display());
                 }
             }
-
-            // Output JSON to file or stdout
-            let report = ColorReport(counts.clone());
-            let json = serde_json::to_string_pretty(&report).unwrap();
-            match out {
-                Some(ref out_path) => {
-                    if let Err(e) = std::fs::write(out_path, json) {
-                        eprintln!("Failed to write output file {}: {}", out_path, e);
-                        std::process::exit(1);
+            if *check {
+                match format {
+                    CheckFormat::Json => println!("{}", serde_json::to_string_pretty(&check_report)?),
+                    CheckFormat::Text => {
+                        for file in &check_report.files {
+                            println!("{}", file);
+                        }
+                        if files_changed > 0 {
+                            info!("{} hex codes in {} files can be replaced with variables", total_replacements, files_changed);
+                        } else {
+                            info!("All files use CSS variables");
+                        }
                     }
                 }
-                None => {
-                    println!("{}", json);
+                if files_changed > 0 {
+                    std::process::exit(1);
                 }
-            }
-        }
-        Commands::Replace { patterns, ignore } => {
-            use std::collections::HashMap;
-            use std::fs;
-            use glob::glob;
-            use regex::Regex;
-            // Load mapping
-            let map: HashMap<String, Vec<String>> = match fs::read_to_string("colours_map.json") {
-                Ok(s) => serde_json::from_str(&s).expect("Invalid colours_map.json"),
-                Err(e) => {
-                    eprintln!("Failed to read colours_map.json: {}", e);
+            } else if *dry_run {
+                info!("Would replace: {} hex codes in {} files", total_replacements, files_changed);
+                if files_changed > 0 {
                     std::process::exit(1);
                 }
-            };
-            // Parse colours.css for valid vars
-            let css = fs::read_to_string("colours.css").expect("Could not read colours.css");
-            let mut canon_to_var = HashMap::new();
-            for line in css.lines() {
-                if let Some((var, hex)) = line.trim().strip_prefix("--color-").and_then(|rest| rest.split_once(":")) {
-                    let var_name = format!("--color-{}", var.trim());
-                    let hex_val = hex.trim().trim_end_matches(';').to_lowercase();
-                    canon_to_var.insert(hex_val.clone(), var_name);
+            } else {
+                if backup.is_some() {
+                    manifest.save(manifest_path)?;
+                }
+                // An incremental run always rewrites the manifest so the next one skips what this one saw
+                if !entries.is_empty() || *incremental {
+                    replace::manifest::append(Path::new(&replace_manifest), &entries)?;
+                }
+                if let Some(patch_file) = patch_file {
+                    write_output(&cli.output.path(patch_file), &patch, "patch")?;
                 }
+                if skipped > 0 {
+                    info!("Skipped {} files not modified since the last run", skipped);
+                }
+                if *interactive {
+                    info!("Files accepted: {}, skipped: {}, aborted: {}", accepted, declined, aborted);
+                }
+                info!("Total replacements: {} in {} files", total_replacements, files_changed);
             }
-            // Build hex->var map strictly from colours.css
-            let mut hex_to_var = HashMap::new();
-            for (canon, hexes) in &map {
-                let canon_hex = canon.trim_start_matches('#').to_lowercase();
-                let css_hex = format!("#{}", canon_hex);
-                let var = match canon_to_var.get(&css_hex) {
-                    Some(v) => v.clone(),
-                    None => {
-                        eprintln!("No variable name found in colours.css for canonical hex {}", canon);
-                        std::process::exit(1);
+        }
+        Commands::Restore { patterns } => {
+            let manifest_path = Path::new(backup::BACKUP_MANIFEST_FILE);
+            let Some(mut manifest) = backup::BackupManifest::load(manifest_path) else {
+                bail!("No backups found ({} is missing)", backup::BACKUP_MANIFEST_FILE);
+            };
+            let globs = patterns
+                .iter()
+                .map(|p| glob::Pattern::new(p).with_context(|| format!("Invalid glob pattern {}", p)))
+                .collect::<Result<Vec<_>>>()?;
+            let originals: Vec<String> = manifest
+                .files
+                .keys()
+                .filter(|f| globs.iter().any(|g| g.matches(f)))
+                .cloned()
+                .collect();
+            let mut restored = 0;
+            for original in &originals {
+                match manifest.restore(original) {
+                    Ok(()) => {
+                        restored += 1;
+                        info!("Restored {}", original);
                     }
-                };
-                for h in hexes {
-                    hex_to_var.insert(h.to_lowercase(), var.clone());
+                    Err(e) => error!("Failed to restore {}: {:#}", original, anyhow::Error::new(e)),
                 }
             }
-            // For each file matching glob
-            let mut total_replacements = 0;
-            let mut files_changed = 0;
-            let exts = ["css", "scss", "sass", "vue", "astro", "svelte"];
-            for pat in patterns {
-                for entry in glob(pat).expect("Invalid glob pattern") {
-                    if let Ok(path) = entry {
-                        // skip if matches any ignore pattern
-                        if ignore.iter().any(|ig| path.to_string_lossy().contains(ig)) {
-                            continue;
-                        }
-                        if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-                            if !exts.contains(&ext) {
-                                continue;
-                            }
-                        } else {
-                            continue;
-                        }
-                        let content = match fs::read_to_string(&path) {
-                            Ok(c) => c,
-                            Err(_) => continue,
-                        };
-                        let mut replaced = content.clone();
-                        let mut file_replacements = 0;
-                        for (hex, var) in &hex_to_var {
-                            // Regex for hex (case-insensitive, with or without #)
-                            let re = Regex::new(&format!(r"(?i){}", regex::escape(hex))).unwrap();
-                            let new_replaced = re.replace_all(&replaced, format!("var({})", var));
-                            let count = new_replaced.matches(&format!("var({})", var)).count();
-                            if count > replaced.matches(hex).count() {
-                                file_replacements += count;
-                            }
-                            replaced = new_replaced.into_owned();
-                        }
-                        if file_replacements > 0 && replaced != content {
-                            fs::write(&path, replaced).expect("Failed to write file");
-                            files_changed += 1;
-                            total_replacements += file_replacements;
-                            println!("Replaced {} hex codes in {}", file_replacements, path.display());
-                        }
+            manifest.save(manifest_path)?;
+            info!("Restored {} of {} files", restored, originals.len());
+            if restored < originals.len() {
+                std::process::exit(1);
+            }
+        }
+        Commands::Audit { patterns, filter, css_vars, error_on_unused } => {
+            let css_path = cli.output.path(css_vars);
+            let css = fs::read_to_string(&css_path).with_context(|| format!("Failed to read {}", css_path))?;
+            let report = hexvar_core::audit::audit_vars(&css, &collect_paths_with(patterns, &filter.path_filter())?);
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            if *error_on_unused && !report.unused.is_empty() {
+                std::process::exit(1);
+            }
+        }
+        Commands::Validate { patterns, filter, css_vars, format } => {
+            let map_path = cli.output.map_path();
+            let css_path = cli.output.path(css_vars);
+            let paths = collect_paths_with(patterns, &filter.path_filter())?;
+            let report = ValidationReport::load(Path::new(&map_path), Path::new(&css_path), &paths)?;
+            match format {
+                CheckFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+                CheckFormat::Text => print!("{}", report.render_text()),
+            }
+            if !report.is_clean() {
+                std::process::exit(1);
+            }
+        }
+        Commands::Diff { before, after, format, threshold } => {
+            let before = ColorReport::load(Path::new(before))?;
+            let after = ColorReport::load(Path::new(after))?;
+            let report = diff::DiffReport::new(&before, &after, *threshold);
+            match format {
+                DiffFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+                DiffFormat::Table => print!("{}", report.render_table()),
+            }
+        }
+        Commands::Matrix { report, format, threshold, delta_e } => {
+            let report = ColorReport::load(Path::new(report))?;
+            let clusters = hexvar_core::cluster_colors(&report.to_tally().counts, *delta_e);
+            let matrix = DistanceMatrix::new(&clusters);
+            match (format, threshold) {
+                (MatrixFormat::Csv, None) => print!("{}", matrix.render_csv()),
+                (MatrixFormat::Json, None) => println!("{}", serde_json::to_string_pretty(&matrix)?),
+                (MatrixFormat::Csv, Some(t)) => print!("{}", matrix::render_pairs_csv(&matrix.pairs_below(*t))),
+                (MatrixFormat::Json, Some(t)) => println!("{}", serde_json::to_string_pretty(&matrix.pairs_below(*t))?),
+            }
+        }
+        Commands::Stats { report, format, delta_e } => {
+            let report = ColorReport::load(Path::new(report))?;
+            let health = PaletteHealth::new(&report.to_tally().counts, *delta_e);
+            match format {
+                CheckFormat::Text => println!("{}", health.render_text()),
+                CheckFormat::Json => println!("{}", serde_json::to_string_pretty(&health)?),
+            }
+        }
+        Commands::Explain { a, b, css_vars, delta_e, color_space, format } => {
+            // The config file is only read when it is needed for the threshold
+            let (threshold, source) = match delta_e {
+                Some(t) => (*t, ThresholdSource::Flag),
+                None => match config::Config::discover()?.delta_e {
+                    Some(t) => {
+                        let t = scan::check_delta_e(t).map_err(|e| anyhow!("Invalid delta_e in the config file: {}", e))?;
+                        (t, ThresholdSource::Config)
                     }
+                    None => (10.0, ThresholdSource::Default),
+                },
+            };
+            let map_path = cli.output.map_path();
+            let css_path = cli.output.path(css_vars);
+            let explanation =
+                Explanation::load(a, b, Path::new(&map_path), Path::new(&css_path), threshold, source, *color_space)?
+                .with_context(|| format!("Expected two hex codes like #1a2b3c, got {} and {}", a, b))?;
+            match format {
+                CheckFormat::Text => print!("{}", explanation.render_text()),
+                CheckFormat::Json => println!("{}", serde_json::to_string_pretty(&explanation)?),
+            }
+        }
+        Commands::Merge(args) => merge::run(args, &cli.output)?,
+        Commands::Convert { patterns, filter, uppercase, lowercase, expand_short, dry_run } => {
+            let re = Regex::new(HEX_PATTERN).unwrap();
+            let case = match (uppercase, lowercase) {
+                (true, _) => convert::HexCase::Upper,
+                (_, true) => convert::HexCase::Lower,
+                _ => convert::HexCase::Preserve,
+            };
+            let opts = convert::ConvertOptions { case, expand_short: *expand_short };
+            let rewrite = |text: &str| (convert::convert_content(text, &re, opts), 0);
+            rewrite_hex_codes(patterns, filter, *dry_run, ("convert", "Converted"), rewrite)?;
+        }
+        Commands::Normalize { patterns, filter, lowercase, uppercase: _, dry_run } => {
+            let re = Regex::new(HEX_PATTERN).unwrap();
+            let case = if *lowercase { convert::HexCase::Lower } else { convert::HexCase::Upper };
+            let rewrite = |text: &str| normalize_content(text, &re, case);
+            rewrite_hex_codes(patterns, filter, *dry_run, ("normalize", "Normalized"), rewrite)?;
+        }
+        Commands::Init(args) => init::run(args)?,
+        Commands::Completions { shell } => {
+            let shell: clap_complete::Shell = (*shell).into();
+            let mut script = Vec::new();
+            clap_complete::generate(shell, &mut Cli::command(), "hexvar", &mut script);
+            // A reader like `head` that closes the pipe early isn't an error
+            match std::io::stdout().write_all(&script) {
+                Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => {
+                    return Err(e).context("Failed to write the completion script");
                 }
+                _ => {}
             }
-            println!("Total replacements: {} in {} files", total_replacements, files_changed);
         }
     }
+    Ok(())
 }
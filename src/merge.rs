@@ -0,0 +1,68 @@
+use crate::scan::{OutputArgs, ScanSettings};
+use crate::write_output;
+use anyhow::{Context, Result};
+use clap::{Args, ValueHint};
+use hexvar_core::{ColorReport, VarFormat};
+use std::path::Path;
+
+/// Flags for the `merge` command
+#[derive(Args)]
+pub struct MergeArgs {
+    /// Reports written by `hexvar scan --out`, e.g. one per package
+    #[arg(value_name = "REPORT", value_hint = ValueHint::FilePath, required = true)]
+    pub reports: Vec<String>,
+    /// Subtract the counts of every later report from the first instead of adding them
+    #[arg(long)]
+    pub subtract: bool,
+    /// Write the combined report to this file instead of stdout
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub out: Option<String>,
+    /// Cluster the combined counts and write CSS variables (and the mapping) to this file
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub css_vars: Option<String>,
+    /// Delta E threshold for merging similar colors with --css-vars (0 disables clustering)
+    #[arg(long, default_value_t = 10.0, requires = "css_vars", value_parser = crate::scan::parse_delta_e)]
+    pub delta_e: f32,
+    /// Syntax of the variables file written by --css-vars
+    #[arg(long, value_enum, default_value_t = VarFormat::Css, requires = "css_vars")]
+    pub format: VarFormat,
+}
+
+/// Run the `merge` command: combine reports, then write the result and any CSS variables
+pub fn run(args: &MergeArgs, output: &OutputArgs) -> Result<()> {
+    let mut merged: Option<ColorReport> = None;
+    for path in &args.reports {
+        let report = ColorReport::load(Path::new(path)).with_context(|| format!("Failed to load report {}", path))?;
+        match &mut merged {
+            None => merged = Some(report),
+            Some(merged) if args.subtract => merged.subtract(&report),
+            Some(merged) => merged.merge(&report),
+        }
+    }
+    let merged = merged.unwrap_or_else(|| ColorReport(Vec::new()));
+
+    if let Some(css_vars) = &args.css_vars {
+        let mut settings = ScanSettings::without_scan(output);
+        settings.css_vars = Some(output.path(css_vars));
+        settings.delta_e = args.delta_e;
+        settings.format = args.format;
+        let tally = merged.to_tally();
+        let clusters = settings.clusters_for(&tally);
+        settings.write_all(&tally, &clusters, false)?;
+    }
+
+    let json = serde_json::to_string_pretty(&merged)?;
+    match &args.out {
+        Some(out) => {
+            let out = output.path(out);
+            if let Some(dir) = Path::new(&out).parent().filter(|d| !d.as_os_str().is_empty()) {
+                std::fs::create_dir_all(dir).with_context(|| format!("Failed to create directory {}", dir.display()))?;
+            }
+            write_output(&out, &json, "merged report")
+        }
+        None => {
+            println!("{}", json);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,1258 @@
+use crate::config;
+use crate::write_output;
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{Args, ValueEnum, ValueHint};
+use hexvar_core::cache::{ScanCache, DEFAULT_CACHE_FILE};
+use hexvar_core::cluster::{
+    canonical_map, cluster_colors_with, kmeans_colors, top_clusters, top_clusters_per_family, wcss, ClusterAlgo,
+    DistanceMetric,
+};
+use hexvar_core::color::{hex_to_rgba, HEX_PATTERN};
+use hexvar_core::scanner::{self, AlphaMode, FileScanned, ScanOptions, ScanTally, Scanner};
+use hexvar_core::encoding::SourceEncoding;
+use hexvar_core::families::{self, FamilyReport};
+use hexvar_core::git;
+use hexvar_core::matrix::{near_duplicates, NearDuplicate};
+use hexvar_core::naming::schemes::NamingSchemeKind;
+use hexvar_core::naming::{self, NameResolver};
+use hexvar_core::paths::{self, collect_paths_with, display_name, sort_paths, FileOrder, PathFilter};
+use hexvar_core::sarif::SarifLog;
+use hexvar_core::stats::PaletteStats;
+use hexvar_core::report::{JsonStyle, SectionedReport};
+use hexvar_core::output::{CanonicalCase, CssVarsOptions, CssVarsSort};
+use hexvar_core::{output, replace, report, Cluster, ColorReport, HexvarError, ReportSort, VarFormat};
+use indicatif::{ProgressBar, ProgressStyle};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufWriter, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::{info, warn};
+
+/// File selection flags shared by every command that walks source files
+#[derive(Args)]
+pub struct FilterArgs {
+    /// Gitignore-style patterns or directories to ignore
+    #[arg(short, long, value_name = "IGNORE")]
+    pub ignore: Vec<String>,
+    /// Additional file extension to include (e.g. "pcss"), on top of the built-in list
+    #[arg(long = "ext", value_name = "EXT")]
+    pub exts: Vec<String>,
+    /// Also skip files excluded by .gitignore in this directory and its parents
+    #[arg(long)]
+    pub use_gitignore: bool,
+    /// Skip files more than N path components below the current directory,
+    /// counting the file name, so 1 only keeps files in the directory itself
+    #[arg(long, value_name = "N")]
+    pub max_depth: Option<usize>,
+}
+
+impl FilterArgs {
+    pub fn path_filter(&self) -> PathFilter {
+        PathFilter {
+            ignore: self.ignore.clone(),
+            extra_exts: self.exts.clone(),
+            use_gitignore: self.use_gitignore,
+            max_files: None,
+            max_depth: self.max_depth,
+            only: None,
+        }
+    }
+}
+
+/// Where generated files are written and read, shared by every command
+#[derive(Args, Clone)]
+pub struct OutputArgs {
+    /// Directory that generated files (CSS variables, mapping, reports) are written to and read from
+    #[arg(long, global = true, value_name = "PATH", value_hint = ValueHint::DirPath)]
+    pub output_dir: Option<PathBuf>,
+    /// File name of the canonical color mapping written with --css-vars and read by `replace`
+    #[arg(long, visible_alias = "map", global = true, value_name = "FILE", value_hint = ValueHint::FilePath, default_value = MAP_FILE)]
+    pub map_file: String,
+}
+
+impl OutputArgs {
+    /// `file` inside --output-dir, if one is set
+    pub fn path(&self, file: &str) -> String {
+        match &self.output_dir {
+            Some(dir) => dir.join(file).display().to_string(),
+            None => file.to_string(),
+        }
+    }
+
+    /// Path of the canonical color mapping
+    pub fn map_path(&self) -> String {
+        self.path(&self.map_file)
+    }
+}
+
+/// Flags shared by the `scan` and `watch` commands
+#[derive(Args)]
+pub struct ScanArgs {
+    /// Output CSS file with variables for each hex code
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub css_vars: Option<String>,
+    /// Syntax of the variables file written by --css-vars [default: css]
+    #[arg(long, value_enum)]
+    pub format: Option<VarFormat>,
+    /// With --format ts, don't write the `.d.ts` declarations next to the
+    /// --css-vars file
+    #[arg(long)]
+    pub no_types: bool,
+    /// Selector of the block holding the CSS variables, e.g. `html` or `.theme-default`
+    #[arg(long, value_name = "SELECTOR", default_value = output::DEFAULT_CSS_VARS_SELECTOR, value_parser = parse_selector)]
+    pub css_vars_selector: String,
+    /// Letter case of the hex values in the variables file
+    #[arg(long, value_enum, default_value_t = CanonicalCase::Lower)]
+    pub canonical_case: CanonicalCase,
+    /// End each variable that merged several colors with a comment listing them
+    /// and their counts, e.g. `/* merged: #1a2c3d (12), #1b2d3e (3) */`
+    #[arg(long)]
+    pub css_vars_comment: bool,
+    /// Order of the variables in the variables file (default: most-used first)
+    #[arg(long, value_enum, value_name = "ORDER")]
+    pub css_vars_sort: Option<CssVarsSort>,
+    /// Also write the variables in a `@media (prefers-color-scheme: dark) { SELECTOR { } }` block
+    #[arg(long, value_name = "SELECTOR", value_parser = parse_selector)]
+    pub dark_selector: Option<String>,
+    /// JSON object of dark mode values for --dark-selector, keyed by variable name or
+    /// canonical hex code, e.g. `{"--color-white": "#121212"}`. Other variables keep
+    /// their light value
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath, requires = "dark_selector")]
+    pub dark_colors: Option<String>,
+    /// Glob patterns to include (e.g., "src/**/*.css"), falls back to `patterns` in hexvar.toml
+    #[arg(value_name = "GLOB", value_hint = ValueHint::AnyPath)]
+    pub patterns: Vec<String>,
+    #[command(flatten)]
+    pub filter: FilterArgs,
+    /// Output a self-contained HTML report with color swatches for each cluster
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub html: Option<String>,
+    /// Output a W3C Design Tokens JSON file for the canonical colors
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub design_tokens: Option<String>,
+    /// Output a Tailwind config module with the canonical colors as `theme.colors`
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub tailwind: Option<String>,
+    /// Output a `postcss-custom-properties` config module with the canonical variables
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub postcss: Option<String>,
+    /// Write the --postcss config as an ES module (`export default`) instead of CommonJS
+    #[arg(long, requires = "postcss")]
+    pub esm: bool,
+    /// Leave out colors with fewer than N occurrences from the report and all outputs
+    #[arg(long, value_name = "N")]
+    pub min_count: Option<u32>,
+    /// Leave out colors with more than N occurrences from the report and all outputs
+    #[arg(long, value_name = "N")]
+    pub max_count: Option<u32>,
+    /// Only report the N most-used canonical colors in the JSON report and all outputs
+    #[arg(long, value_name = "N")]
+    pub top: Option<usize>,
+    /// Only report the N most-used canonical colors of each hue family (reds, blues, neutrals, ...)
+    #[arg(long, value_name = "N")]
+    pub top_per_cluster: Option<usize>,
+    /// Order of colors in the JSON report (default: unordered)
+    #[arg(long, value_enum, value_name = "ORDER")]
+    pub sort: Option<ReportSort>,
+    /// Output file for JSON report (default: stdout)
+    #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub out: Option<String>,
+    /// Also write the report as YAML, with colors in alphabetical order
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub yaml: Option<String>,
+    /// List the files with the most unique colors after the summary, and add a
+    /// `"files"` key of path -> unique color count to the JSON report
+    #[arg(long)]
+    pub per_file: bool,
+    /// Group canonical colors into hue families in the JSON report, as
+    /// `{"red": {"#ff0000": 12, ...}, ...}`
+    #[arg(long, conflicts_with = "streaming")]
+    pub group_by_family: bool,
+    /// Chroma below which --group-by-family counts a color as achromatic
+    #[arg(long, value_name = "CHROMA", default_value_t = families::DEFAULT_ACHROMATIC_CHROMA, requires = "group_by_family")]
+    pub achromatic_chroma: f32,
+    /// Write JSON output without any whitespace
+    #[arg(long, conflicts_with = "indent")]
+    pub compact: bool,
+    /// Spaces per indentation level in JSON output [default: 2]
+    #[arg(long, value_name = "N")]
+    pub indent: Option<usize>,
+    /// Write the JSON report entry by entry instead of building it in memory first,
+    /// for very large file sets. Colors are ordered by hex code unless --sort is given
+    #[arg(long)]
+    pub streaming: bool,
+    /// Delta E (CIE76) threshold below which colors are merged into one variable.
+    /// A Delta E of about 2.3 is a "just noticeable difference": most people cannot
+    /// tell two colors closer than that apart. Lower values keep more distinct
+    /// variables, higher values merge more aggressively. 0 disables clustering.
+    /// [default: 10.0]
+    #[arg(long, value_name = "DELTA_E", value_parser = parse_delta_e)]
+    pub delta_e: Option<f32>,
+    /// Clustering algorithm: greedy merging by --delta-e, or k-means with --clusters groups
+    #[arg(long, value_enum, default_value_t)]
+    pub cluster_algo: ClusterAlgo,
+    /// Number of clusters for --cluster-algo kmeans. Colors with different alpha
+    /// never share a cluster, so N is raised to the number of distinct alpha values
+    #[arg(
+        long,
+        value_name = "N",
+        required_if_eq("cluster_algo", "kmeans"),
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub clusters: Option<usize>,
+    /// Color difference formula --delta-e is measured with. `cie94` and `ciede2000`
+    /// cluster blues and violets more accurately; CIEDE2000 is about 10x slower
+    #[arg(long, value_enum, default_value_t)]
+    pub color_space: DistanceMetric,
+    /// Template for variable names. Placeholders: {prefix}, {name} (CSS color name or
+    /// hex fallback), {hex} (bare digits), {r}, {g}, {b} (decimal channels)
+    #[arg(long, value_name = "TEMPLATE", default_value = naming::DEFAULT_NAME_TEMPLATE)]
+    pub name_template: String,
+    /// Value substituted for {prefix} in --name-template
+    #[arg(long, value_name = "PREFIX", default_value = naming::DEFAULT_PREFIX)]
+    pub prefix: String,
+    /// JSON or TOML file of custom color names (e.g. `brand-blue = "#0052cc"`) tried
+    /// before the CSS color names when naming --css-vars variables
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub color_names: Option<String>,
+    /// Table the {name} placeholder comes from
+    #[arg(long, value_enum, default_value_t)]
+    pub naming_scheme: NamingSchemeKind,
+    /// Add WCAG contrast ratios against white and black to each canonical color in the JSON report
+    #[arg(long)]
+    pub wcag: bool,
+    /// Print per-channel and lightness statistics and the most distinct and most
+    /// similar canonical color pairs after the summary
+    #[arg(long)]
+    pub stats: bool,
+    /// Print a true-color swatch for each of the 20 most-used canonical colors after the summary
+    #[arg(long)]
+    pub swatches: bool,
+    /// List the canonical colors whose variable name is made of hex digits, like
+    /// `--color-1a2b3c`, after the summary, to add to a --color-names file
+    #[arg(long)]
+    pub report_missing_names: bool,
+    /// List pairs of canonical colors closer than T Delta E, but not close enough
+    /// to be merged, under "near_duplicates" in the JSON report
+    #[arg(long, value_name = "T", value_parser = parse_delta_e)]
+    pub min_delta_e: Option<f32>,
+    /// Also count CSS color keywords such as `red` or `cornflowerblue` in declaration values
+    #[arg(long)]
+    pub include_named_colors: bool,
+    /// Skip colors inside `/* */` and `//` comments (the default)
+    #[arg(long, overrides_with = "include_comments")]
+    pub skip_comments: bool,
+    /// Count colors inside comments too, marking them `"in_comment": true` in --locations
+    #[arg(long, overrides_with = "skip_comments")]
+    pub include_comments: bool,
+    /// Skip colors inside the quoted strings of `content` declarations, like
+    /// `content: "#ff0000"` (the default)
+    #[arg(long, overrides_with = "include_content_strings")]
+    pub skip_content_strings: bool,
+    /// Count colors inside `content` strings too
+    #[arg(long, overrides_with = "skip_content_strings")]
+    pub include_content_strings: bool,
+    /// Leave out gradient stops, i.e. colors inside `linear-gradient()` and other
+    /// `*-gradient()` functions
+    #[arg(long)]
+    pub skip_gradient: bool,
+    /// Also count the color each `color-mix()` of two hex codes mixes to, as a
+    /// derived color located at the call
+    #[arg(long)]
+    pub resolve_color_mix: bool,
+    /// Recognize SCSS `$var: #hex;` and Less `@var: #hex;` declarations: mark them
+    /// in the locations and name their colors after the variable
+    #[arg(long)]
+    pub scss_aware: bool,
+    /// Count the CSS properties each color is used in, e.g. `border` or
+    /// `background-color`, under "properties" in the JSON report
+    #[arg(long)]
+    pub track_properties: bool,
+    /// Encoding to read files in, e.g. `windows-1252` or `utf-16le`, or `detect`
+    /// to guess it per file. A byte order mark always takes precedence, and files
+    /// that fail to decode are listed under "decode_errors" in the report
+    #[arg(long, value_name = "CHARSET", default_value = "utf-8")]
+    pub encoding: SourceEncoding,
+    /// Count fully opaque 8-digit hex codes as their 6-digit form, e.g. `#1a2b3cff`
+    /// as `#1a2b3c`. Semi-transparent codes keep their alpha
+    #[arg(long, conflicts_with = "strip_alpha")]
+    pub normalize_alpha: bool,
+    /// Count every 8-digit hex code as its 6-digit form, dropping the alpha even
+    /// when it isn't `ff`
+    #[arg(long)]
+    pub strip_alpha: bool,
+    /// Leave out colors already defined as variables in the `:root` blocks of the
+    /// --css-vars file (default: colours.css), which is then read but not rewritten
+    #[arg(long)]
+    pub exclude_existing_vars: bool,
+    /// Leave out the colors listed in FILE, one hex code per line, or every hex
+    /// code in a JSON file such as a color mapping
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub ignore_file: Option<PathBuf>,
+    /// Leave out these colors, e.g. `--ignore-hex '#aabbcc,#123'`
+    #[arg(long, value_name = "HEX", value_delimiter = ',', value_parser = parse_hex)]
+    pub ignore_hex: Vec<String>,
+    /// Record the file, line, and column of every match in the JSON report
+    #[arg(long)]
+    pub locations: bool,
+    /// Write a SARIF 2.1.0 log with a warning for every color literal (implies --locations)
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+    pub sarif: Option<String>,
+    /// Cache file of per-file results; unchanged files are not re-scanned
+    #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath, default_value = DEFAULT_CACHE_FILE)]
+    pub cache: String,
+    /// Ignore and don't write the cache, forcing a full rescan
+    #[arg(long)]
+    pub no_cache: bool,
+    /// Memory-map files instead of reading them into memory, which saves an
+    /// allocation per file on large bundled stylesheets
+    #[arg(long)]
+    pub mmap: bool,
+    /// Maximum number of threads used for scanning (default: all cores)
+    #[arg(short, long, value_name = "N")]
+    pub jobs: Option<usize>,
+    /// Abort with exit code 2 if the globs match more than N files
+    #[arg(long, value_name = "N")]
+    pub max_files: Option<usize>,
+    /// List the files that would be scanned, after filtering, without scanning them
+    #[arg(long)]
+    pub dry_run_paths: bool,
+    /// Only scan files matching the globs that `git diff --name-only REF` lists
+    /// as changed
+    #[arg(long, value_name = "REF")]
+    pub since: Option<String>,
+    /// With --since outside a git repository, warn and scan every matching file
+    /// instead of failing
+    #[arg(long, requires = "since")]
+    pub fallback_full: bool,
+    /// Order to scan files in, so reports are reproducible: alphabetical by path,
+    /// newest first, or largest first
+    #[arg(long, value_enum, value_name = "ORDER", default_value_t = FileOrder::Alpha)]
+    pub sort_files: FileOrder,
+    /// Only scan the first N matching files in --sort-files order
+    #[arg(long, value_name = "N")]
+    pub limit_files: Option<usize>,
+    /// Report file paths relative to BASE_DIR, so reports from different working
+    /// directories match [default: the current directory]
+    #[arg(long, value_name = "BASE_DIR", value_hint = ValueHint::DirPath, num_args = 0..=1, require_equals = true, default_missing_value = ".")]
+    pub output_relative_paths: Option<PathBuf>,
+    /// How to report progress while scanning [default: bar when stdout is a terminal, else none]
+    #[arg(long, value_enum, value_name = "MODE")]
+    pub progress: Option<ProgressMode>,
+}
+
+/// A `--progress json` line for one scanned file
+#[derive(Serialize)]
+struct FileProgress {
+    file: String,
+    hex_count: u32,
+    elapsed_ms: u64,
+}
+
+/// The last `--progress json` line, once every file is scanned
+#[derive(Serialize)]
+struct DoneProgress {
+    done: bool,
+    total_files: usize,
+    total_hexes: u32,
+}
+
+/// Files scanned and colors matched per file extension, for the summary
+#[derive(Default)]
+pub struct ExtensionCounts(HashMap<String, (usize, u32)>);
+
+impl ExtensionCounts {
+    fn add(&mut self, path: &Path, matches: u32) {
+        let ext = if paths::is_stdin(path) {
+            "stdin".to_string()
+        } else {
+            path.extension().map_or_else(|| "(none)".to_string(), |e| e.to_string_lossy().to_ascii_lowercase())
+        };
+        let entry = self.0.entry(ext).or_default();
+        entry.0 += 1;
+        entry.1 += matches;
+    }
+
+    /// `css: 142 files, 320 hex codes | scss: 58 files, 180 hex codes`, most hex codes first
+    fn render(&self) -> String {
+        let mut exts: Vec<(&String, &(usize, u32))> = self.0.iter().collect();
+        exts.sort_by(|a, b| b.1 .1.cmp(&a.1 .1).then_with(|| a.0.cmp(b.0)));
+        exts.iter()
+            .map(|(ext, (files, hexes))| format!("{}: {} files, {} hex codes", ext, files, hexes))
+            .collect::<Vec<_>>()
+            .join(" | ")
+    }
+}
+
+/// How `scan` reports progress while reading files
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ProgressMode {
+    /// An interactive progress bar on stderr
+    Bar,
+    /// A JSON line on stderr per file, `{"file":...,"hex_count":...,"elapsed_ms":...}`,
+    /// then `{"done":true,"total_files":...,"total_hexes":...}`
+    Json,
+    /// No progress output
+    None,
+}
+
+/// Scan settings after filling in unset flags from hexvar.toml / .hexvarrc
+pub struct ScanSettings {
+    pub patterns: Vec<String>,
+    pub filter: PathFilter,
+    pub delta_e: f32,
+    pub cluster_algo: ClusterAlgo,
+    pub clusters: Option<usize>,
+    /// `--color-space`: how greedy clustering measures Delta E
+    pub color_space: DistanceMetric,
+    pub format: VarFormat,
+    /// Selectors and dark mode values of a CSS variables file
+    pub css_opts: CssVarsOptions,
+    /// `--css-vars-comment`: list merged colors after each variable
+    pub css_vars_comment: bool,
+    /// `--css-vars-sort`: order of the variables, if not most-used first
+    pub css_vars_sort: Option<CssVarsSort>,
+    /// Write `.d.ts` declarations next to a `--format ts` variables file
+    pub ts_types: bool,
+    pub name_template: String,
+    pub prefix: String,
+    /// Source of the `{name}` placeholder
+    pub names: NameResolver,
+    pub css_vars: Option<String>,
+    pub out: Option<String>,
+    pub yaml: Option<String>,
+    pub html: Option<String>,
+    pub design_tokens: Option<String>,
+    pub tailwind: Option<String>,
+    /// `--postcss` file, and whether it is an ES module
+    pub postcss: Option<(String, bool)>,
+    pub sarif: Option<String>,
+    /// Canonical color mapping written alongside `css_vars`
+    pub map_file: String,
+    pub output: OutputArgs,
+    pub wcag: bool,
+    pub stats: bool,
+    pub swatches: bool,
+    pub report_missing_names: bool,
+    /// `--min-delta-e`: report canonical color pairs closer than this
+    pub min_delta_e: Option<f32>,
+    pub sort: Option<ReportSort>,
+    pub streaming: bool,
+    /// Layout of the JSON report, design tokens, and SARIF log
+    pub json_style: JsonStyle,
+    pub progress: ProgressMode,
+    /// Achromatic chroma threshold when grouping the report by family
+    pub group_by_family: Option<f32>,
+    pub min_count: Option<u32>,
+    pub max_count: Option<u32>,
+    pub top: Option<usize>,
+    pub top_per_cluster: Option<usize>,
+    /// Colors of the existing variables file and its path, with --exclude-existing-vars
+    pub existing_vars: Option<(String, BTreeSet<String>)>,
+    /// Colors left out by --ignore-file and --ignore-hex, normalized like the counts
+    pub ignored_colors: BTreeSet<String>,
+    pub scan_opts: ScanOptions,
+    /// Memory-map files instead of reading them, with --mmap
+    pub mmap: bool,
+    /// List the matched files instead of scanning them
+    pub dry_run_paths: bool,
+    /// Git ref whose changes limit the scan, and whether to scan everything
+    /// when not in a git repository
+    pub since: Option<(String, bool)>,
+    pub sort_files: FileOrder,
+    pub limit_files: Option<usize>,
+    /// `--output-relative-paths`: absolute base directory and working directory
+    pub relative_paths: Option<(PathBuf, PathBuf)>,
+    /// `None` when caching is disabled
+    pub cache: Option<String>,
+}
+
+/// File the canonical color mapping is written to alongside --css-vars
+pub const MAP_FILE: &str = "colours_map.json";
+
+/// CSS variables file `replace` reads its variable names from
+pub const DEFAULT_CSS_VARS_FILE: &str = "colours.css";
+
+/// Tokens file `replace --js-style import` imports constants from
+pub const DEFAULT_TOKENS_FILE: &str = "colours.js";
+
+/// Most canonical colors shown by --swatches
+const SWATCH_LIMIT: usize = 20;
+
+/// Most files listed by --per-file
+const PER_FILE_LIMIT: usize = 10;
+
+/// Clap parser for selector flags, rejecting ones that would break the CSS block
+fn parse_selector(selector: &str) -> std::result::Result<String, String> {
+    output::validate_selector(selector).map(|()| selector.trim().to_string())
+}
+
+/// Parse a Delta E threshold, which must be a finite number of at least 0
+pub fn parse_delta_e(value: &str) -> std::result::Result<f32, String> {
+    value.trim().parse::<f32>().map_err(|e| e.to_string()).and_then(check_delta_e)
+}
+
+/// `delta_e` if it is a finite number of at least 0, for thresholds that
+/// don't come from the command line, like `delta_e` in the config file
+pub fn check_delta_e(delta_e: f32) -> std::result::Result<f32, String> {
+    if delta_e.is_finite() && delta_e >= 0.0 {
+        Ok(delta_e)
+    } else {
+        Err(format!("{} isn't a Delta E of 0 or more", delta_e))
+    }
+}
+
+/// Clap parser for hex code flags
+fn parse_hex(hex: &str) -> std::result::Result<String, String> {
+    let hex = hex.trim();
+    match hex.strip_prefix('#').and_then(hex_to_rgba) {
+        Some(_) => Ok(hex.to_string()),
+        None => Err(format!("{:?} isn't a 3, 4, 6, or 8 digit hex code", hex)),
+    }
+}
+
+/// Read an --ignore-file: every hex code in a JSON file (keys, values, and
+/// array items alike), or otherwise one hex code per line
+fn load_ignored_colors(path: &Path) -> Result<Vec<String>> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    if let Ok(json) = serde_json::from_str::<serde_json::Value>(&text) {
+        let mut found = Vec::new();
+        collect_json_hexes(&json, &mut found);
+        return Ok(found);
+    }
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| (i, line.trim()))
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(i, line)| {
+            parse_hex(line).map_err(|e| anyhow::anyhow!("Invalid ignore file {} line {}: {}", path.display(), i + 1, e))
+        })
+        .collect()
+}
+
+fn collect_json_hexes(value: &serde_json::Value, found: &mut Vec<String>) {
+    match value {
+        serde_json::Value::String(s) => found.extend(parse_hex(s).ok()),
+        serde_json::Value::Array(items) => items.iter().for_each(|item| collect_json_hexes(item, found)),
+        serde_json::Value::Object(map) => {
+            for (key, item) in map {
+                found.extend(parse_hex(key).ok());
+                collect_json_hexes(item, found);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Read a --dark-colors file, lowercasing hex code keys to match canonical colors
+fn load_dark_colors(path: &Path) -> Result<HashMap<String, String>> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let table: HashMap<String, String> =
+        serde_json::from_str(&text).with_context(|| format!("Invalid dark colors file {}", path.display()))?;
+    if let Some((key, value)) = table.iter().find(|(_, v)| v.contains(['{', '}', ';'])) {
+        bail!("Invalid dark colors file {}: value {:?} of {} isn't a CSS color", path.display(), value, key);
+    }
+    Ok(table
+        .into_iter()
+        .map(|(key, value)| (if key.starts_with('#') { scanner::normalize_hex(&key) } else { key }, value))
+        .collect())
+}
+
+/// Load a `--color-names` table: TOML for `.toml` files, JSON otherwise
+fn load_color_names(path: &Path) -> Result<NameResolver> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let table: BTreeMap<String, String> = if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("toml")) {
+        toml::from_str(&text).with_context(|| format!("Invalid color names file {}", path.display()))?
+    } else {
+        serde_json::from_str(&text).with_context(|| format!("Invalid color names file {}", path.display()))?
+    };
+    NameResolver::new(table).with_context(|| format!("Invalid color names file {}", path.display()))
+}
+
+impl ScanArgs {
+    /// Merge the command line with the discovered config file, failing if no patterns are set.
+    /// Generated file paths are placed inside `output`'s directory.
+    pub fn resolve(&self, output: &OutputArgs) -> Result<ScanSettings> {
+        let config = config::Config::discover()?;
+        let patterns = if self.patterns.is_empty() { config.patterns } else { self.patterns.clone() };
+        if patterns.is_empty() {
+            bail!("No glob patterns given on the command line or in hexvar.toml");
+        }
+        naming::validate_template(&self.name_template)?;
+        if let Some(n) = self.jobs {
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(n)
+                .build_global()
+                .context("Failed to configure thread pool")?;
+        }
+        let mut css_vars = self.css_vars.clone().or(config.css_vars).map(|f| output.path(&f));
+        let existing_vars = if self.exclude_existing_vars {
+            // The existing tokens are an input now, so the file isn't regenerated without them
+            let path = css_vars.take().unwrap_or_else(|| output.path(DEFAULT_CSS_VARS_FILE));
+            let css = std::fs::read_to_string(&path).with_context(|| format!("Failed to read CSS variables file {}", path))?;
+            Some((path, scanner::root_var_colors(&css)))
+        } else {
+            None
+        };
+        let alpha = match (self.normalize_alpha, self.strip_alpha) {
+            (_, true) => AlphaMode::Strip,
+            (true, false) => AlphaMode::Opaque,
+            _ => AlphaMode::Keep,
+        };
+        let mut ignored = self.ignore_hex.clone();
+        if let Some(path) = &self.ignore_file {
+            ignored.extend(load_ignored_colors(path)?);
+        }
+        let ignored_colors = ignored.iter().map(|hex| scanner::normalize_hex_with(hex, alpha)).collect();
+        let relative_paths = match &self.output_relative_paths {
+            Some(base) => {
+                // Canonical like the base, so symlinked directories still match
+                let cwd = std::env::current_dir()
+                    .and_then(std::fs::canonicalize)
+                    .context("Failed to read the current directory")?;
+                let base = std::fs::canonicalize(base).with_context(|| format!("Failed to resolve {}", base.display()))?;
+                Some((base, cwd))
+            }
+            None => None,
+        };
+        Ok(ScanSettings {
+            patterns,
+            filter: PathFilter {
+                ignore: if self.filter.ignore.is_empty() { config.ignore } else { self.filter.ignore.clone() },
+                max_files: self.max_files,
+                ..self.filter.path_filter()
+            },
+            delta_e: match self.delta_e.or(config.delta_e) {
+                Some(delta_e) => check_delta_e(delta_e).map_err(|e| anyhow!("Invalid delta_e in the config file: {}", e))?,
+                None => 10.0,
+            },
+            cluster_algo: self.cluster_algo,
+            color_space: self.color_space,
+            clusters: self.clusters,
+            format: self.format.or(config.format).unwrap_or_default(),
+            ts_types: !self.no_types,
+            css_opts: CssVarsOptions {
+                selector: self.css_vars_selector.clone(),
+                dark_selector: self.dark_selector.clone(),
+                dark_colors: match &self.dark_colors {
+                    Some(path) => load_dark_colors(Path::new(path))?,
+                    None => HashMap::new(),
+                },
+                case: self.canonical_case,
+                merged_counts: None,
+            },
+            css_vars_comment: self.css_vars_comment,
+            css_vars_sort: self.css_vars_sort,
+            name_template: self.name_template.clone(),
+            prefix: self.prefix.clone(),
+            names: match &self.color_names {
+                Some(path) => load_color_names(Path::new(path))?,
+                None => NameResolver::default(),
+            }
+            .with_scheme(self.naming_scheme),
+            css_vars,
+            out: self.out.clone().or(config.out).map(|f| output.path(&f)),
+            yaml: self.yaml.as_deref().map(|f| output.path(f)),
+            html: self.html.as_deref().map(|f| output.path(f)),
+            design_tokens: self.design_tokens.as_deref().map(|f| output.path(f)),
+            tailwind: self.tailwind.as_deref().map(|f| output.path(f)),
+            postcss: self.postcss.as_deref().map(|f| (output.path(f), self.esm)),
+            sarif: self.sarif.as_deref().map(|f| output.path(f)),
+            map_file: output.map_path(),
+            output: output.clone(),
+            wcag: self.wcag,
+            stats: self.stats,
+            swatches: self.swatches,
+            report_missing_names: self.report_missing_names,
+            min_delta_e: self.min_delta_e,
+            sort: self.sort,
+            streaming: self.streaming,
+            json_style: match (self.compact, self.indent) {
+                (true, _) => JsonStyle::Compact,
+                (false, Some(indent)) => JsonStyle::Pretty { indent },
+                (false, None) => JsonStyle::default(),
+            },
+            progress: self.progress.unwrap_or(if std::io::stdout().is_terminal() {
+                ProgressMode::Bar
+            } else {
+                ProgressMode::None
+            }),
+            group_by_family: self.group_by_family.then_some(self.achromatic_chroma),
+            min_count: self.min_count,
+            max_count: self.max_count,
+            top: self.top,
+            top_per_cluster: self.top_per_cluster,
+            existing_vars,
+            ignored_colors,
+            scan_opts: ScanOptions {
+                locations: self.locations || self.sarif.is_some(),
+                named_colors: self.include_named_colors,
+                comments: self.include_comments,
+                per_file: self.per_file,
+                skip_gradients: self.skip_gradient,
+                resolve_color_mix: self.resolve_color_mix,
+                content_strings: self.include_content_strings,
+                scss_aware: self.scss_aware,
+                encoding: self.encoding,
+                track_properties: self.track_properties,
+                alpha,
+            },
+            mmap: self.mmap,
+            dry_run_paths: self.dry_run_paths,
+            since: self.since.clone().map(|r| (r, self.fallback_full)),
+            sort_files: self.sort_files,
+            limit_files: self.limit_files,
+            relative_paths,
+            cache: (!self.no_cache).then(|| self.cache.clone()),
+        })
+    }
+}
+
+impl ScanSettings {
+    /// Defaults for commands that cluster existing results instead of scanning,
+    /// writing nothing until output files are set
+    pub fn without_scan(output: &OutputArgs) -> ScanSettings {
+        ScanSettings {
+            patterns: Vec::new(),
+            filter: PathFilter::default(),
+            delta_e: 10.0,
+            cluster_algo: ClusterAlgo::default(),
+            color_space: DistanceMetric::default(),
+            clusters: None,
+            format: VarFormat::default(),
+            ts_types: true,
+            css_opts: CssVarsOptions::default(),
+            css_vars_comment: false,
+            css_vars_sort: None,
+            name_template: naming::DEFAULT_NAME_TEMPLATE.to_string(),
+            prefix: naming::DEFAULT_PREFIX.to_string(),
+            names: NameResolver::default(),
+            css_vars: None,
+            out: None,
+            yaml: None,
+            html: None,
+            design_tokens: None,
+            tailwind: None,
+            postcss: None,
+            sarif: None,
+            map_file: output.map_path(),
+            output: output.clone(),
+            wcag: false,
+            stats: false,
+            swatches: false,
+            report_missing_names: false,
+            min_delta_e: None,
+            sort: None,
+            streaming: false,
+            json_style: JsonStyle::default(),
+            progress: ProgressMode::None,
+            group_by_family: None,
+            min_count: None,
+            max_count: None,
+            top: None,
+            top_per_cluster: None,
+            existing_vars: None,
+            ignored_colors: BTreeSet::new(),
+            scan_opts: ScanOptions::default(),
+            mmap: false,
+            dry_run_paths: false,
+            since: None,
+            sort_files: FileOrder::default(),
+            relative_paths: None,
+            limit_files: None,
+            cache: None,
+        }
+    }
+
+    /// Every file this scan writes, so `watch` can ignore its own output
+    pub fn output_files(&self) -> Vec<String> {
+        let mut files: Vec<String> = [&self.css_vars, &self.out, &self.yaml, &self.html, &self.design_tokens, &self.tailwind, &self.sarif, &self.cache]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect();
+        if let Some((postcss, _)) = &self.postcss {
+            files.push(postcss.clone());
+        }
+        if let Some(css_vars) = &self.css_vars {
+            files.push(self.map_file.clone());
+            if let Some(declarations) = self.ts_declaration_path(css_vars) {
+                files.push(declarations);
+            }
+        }
+        files
+    }
+
+    /// Where the `.d.ts` declarations for the variables file `css_vars` go, if
+    /// they're written at all
+    fn ts_declaration_path(&self, css_vars: &str) -> Option<String> {
+        (self.format == VarFormat::Ts && self.ts_types).then(|| output::ts_declaration_path(css_vars))
+    }
+
+    /// Create the directories generated files go in, e.g. for --output-dir
+    fn create_output_dirs(&self) -> Result<()> {
+        for file in self.output_files() {
+            if let Some(dir) = Path::new(&file).parent().filter(|d| !d.as_os_str().is_empty()) {
+                std::fs::create_dir_all(dir).with_context(|| format!("Failed to create directory {}", dir.display()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Every file matching the patterns and filters, and changed since the
+    /// --since ref, in --sort-files order and cut to --limit-files. Exits with
+    /// code 2 when there are more than --max-files.
+    pub fn collect_paths(&self) -> Result<Vec<PathBuf>> {
+        let mut filter = self.filter.clone();
+        if let Some((reference, fallback_full)) = &self.since {
+            match git::changed_since(reference) {
+                Ok(changed) => filter.only = Some(changed),
+                Err(e @ HexvarError::NotGitRepository) if *fallback_full => warn!("{}; scanning all files", e),
+                Err(e) => return Err(anyhow::Error::new(e).context(format!("Failed to list files changed since {}", reference))),
+            }
+        }
+        match collect_paths_with(&self.patterns, &filter) {
+            Err(e @ HexvarError::TooManyFiles { .. }) => {
+                warn!("{}", e);
+                std::process::exit(2);
+            }
+            paths => {
+                let mut paths = paths?;
+                sort_paths(&mut paths, self.sort_files);
+                if let Some(limit) = self.limit_files {
+                    paths.truncate(limit);
+                }
+                Ok(paths)
+            }
+        }
+    }
+
+    /// Print every file that would be scanned, for --dry-run-paths
+    pub fn print_paths(&self) -> Result<()> {
+        let paths = self.collect_paths()?;
+        for path in &paths {
+            println!("{}", display_name(path));
+        }
+        info!("{} files would be scanned", paths.len());
+        Ok(())
+    }
+
+    /// Collect and scan all matching files, returning the file count, results,
+    /// and per-extension counts
+    pub fn scan(&self, progress: ProgressMode) -> Result<(usize, ScanTally, ExtensionCounts)> {
+        let scanner = Scanner::new(Regex::new(HEX_PATTERN).unwrap(), self.scan_opts).with_mmap(self.mmap);
+        // Collect all file paths matching patterns (ignoring ignores)
+        let paths = self.collect_paths()?;
+        // Set up progress bar
+        let file_count = paths.len();
+        let pb = if progress == ProgressMode::Bar { ProgressBar::new(file_count as u64) } else { ProgressBar::hidden() };
+        pb.set_style(ProgressStyle::with_template("{spinner} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} {msg}")
+            .unwrap()
+            .progress_chars("|/-\\ "));
+
+        let report_progress = |path: &Path, scanned: FileScanned| match progress {
+            ProgressMode::Bar => {
+                pb.set_message(display_name(path));
+                pb.inc(1);
+            }
+            ProgressMode::Json => {
+                let line = FileProgress {
+                    file: display_name(path),
+                    hex_count: scanned.matches,
+                    elapsed_ms: scanned.elapsed.as_millis() as u64,
+                };
+                eprintln!("{}", serde_json::to_string(&line).unwrap());
+            }
+            ProgressMode::None => {}
+        };
+        let extensions = Mutex::new(ExtensionCounts::default());
+        let on_file = |path: &Path, scanned: FileScanned| {
+            extensions.lock().unwrap().add(path, scanned.matches);
+            report_progress(path, scanned);
+        };
+        let mut tally = match &self.cache {
+            None => scanner.scan_paths(&paths, on_file),
+            Some(cache) => {
+                let cache_path = Path::new(cache);
+                let previous = ScanCache::load(cache_path, self.scan_opts);
+                let (tally, updated) = scanner.scan_paths_cached(&paths, &previous, on_file);
+                // A stale cache only costs a slower next run, so don't fail the scan
+                if let Err(e) = updated.save(cache_path) {
+                    warn!("{:#}", anyhow::Error::new(e));
+                }
+                tally
+            }
+        };
+        pb.finish_and_clear();
+        if progress == ProgressMode::Json {
+            let done = DoneProgress { done: true, total_files: file_count, total_hexes: tally.total() };
+            eprintln!("{}", serde_json::to_string(&done).unwrap());
+        }
+        if let Some((base, cwd)) = &self.relative_paths {
+            tally.map_paths(|file| paths::relative_to(file, base, cwd));
+        }
+        Ok((file_count, tally, extensions.into_inner().unwrap()))
+    }
+
+    /// Drop colors already defined in the --exclude-existing-vars file, returning how many
+    pub fn apply_existing_vars(&self, tally: &mut ScanTally) -> usize {
+        match &self.existing_vars {
+            Some((_, colors)) => tally.retain(|hex, _| !colors.contains(hex)),
+            None => 0,
+        }
+    }
+
+    /// Drop the colors of --ignore-file and --ignore-hex, returning how many
+    pub fn apply_ignored_colors(&self, tally: &mut ScanTally) -> usize {
+        tally.retain(|hex, _| !self.ignored_colors.contains(hex))
+    }
+
+    /// Drop colors outside --min-count / --max-count, returning how many were
+    /// below and above the thresholds
+    pub fn apply_thresholds(&self, tally: &mut ScanTally) -> (usize, usize) {
+        let below = match self.min_count {
+            Some(min) => tally.retain(|_, count| count >= min),
+            None => 0,
+        };
+        let above = match self.max_count {
+            Some(max) => tally.retain(|_, count| count <= max),
+            None => 0,
+        };
+        (below, above)
+    }
+
+    fn writes_files(&self) -> bool {
+        self.css_vars.is_some()
+            || self.html.is_some()
+            || self.design_tokens.is_some()
+            || self.tailwind.is_some()
+            || self.postcss.is_some()
+    }
+
+    /// Cluster colors if any output needs clusters, or return no clusters
+    pub fn clusters_for(&self, tally: &ScanTally) -> Vec<Cluster> {
+        let needed = self.writes_files()
+            || self.wcag
+            || self.stats
+            || self.swatches
+            || self.report_missing_names
+            || self.min_delta_e.is_some()
+            || self.sort == Some(ReportSort::Alpha)
+            || self.group_by_family.is_some()
+            || self.top.is_some()
+            || self.top_per_cluster.is_some();
+        if needed {
+            self.cluster(tally)
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Keep only the clusters selected by --top-per-cluster and --top, and the
+    /// colors merged into them. Returns how many clusters were dropped.
+    pub fn apply_top(&self, tally: &mut ScanTally, clusters: &mut Vec<Cluster>) -> usize {
+        let before = clusters.len();
+        if let Some(n) = self.top_per_cluster {
+            *clusters = top_clusters_per_family(std::mem::take(clusters), &tally.counts, n);
+        }
+        if let Some(n) = self.top {
+            *clusters = top_clusters(std::mem::take(clusters), &tally.counts, n);
+        }
+        if clusters.len() < before {
+            let kept: HashSet<&str> = clusters.iter().flat_map(|c| c.members.iter().map(String::as_str)).collect();
+            tally.retain(|hex, _| kept.contains(hex));
+        }
+        before - clusters.len()
+    }
+
+    /// Write every requested output and the JSON report
+    pub fn write_all(&self, tally: &ScanTally, clusters: &[Cluster], stdout_fallback: bool) -> Result<()> {
+        self.create_output_dirs()?;
+        if self.writes_files() {
+            self.write_outputs(tally, clusters)?;
+        }
+        if let Some(sarif_path) = &self.sarif {
+            self.write_sarif(tally, sarif_path)?;
+        }
+        if let Some(yaml_path) = &self.yaml {
+            let mut report = ColorReport::from_tally(tally);
+            if self.wcag {
+                report.add_wcag(clusters);
+            }
+            write_output(yaml_path, &report.to_yaml(), "YAML report")?;
+        }
+        self.write_report(tally, clusters, stdout_fallback)
+    }
+
+    /// Print swatches for the most-used clusters, without ANSI colors when
+    /// `NO_COLOR` is set or stdout is not a terminal
+    pub fn print_swatches(&self, tally: &ScanTally, clusters: &[Cluster]) {
+        let ansi = std::env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal();
+        let canonical_map = canonical_map(clusters, &tally.sources);
+        print!("{}", output::render_terminal_swatches(clusters, &canonical_map, &tally.counts, SWATCH_LIMIT, ansi));
+    }
+
+    fn cluster(&self, tally: &ScanTally) -> Vec<Cluster> {
+        let mut clusters = match (self.cluster_algo, self.clusters) {
+            (ClusterAlgo::Kmeans, Some(k)) => {
+                let clusters = kmeans_colors(&tally.counts, k);
+                if clusters.len() > k {
+                    warn!(
+                        "--clusters {} raised to {}: colors with different alpha values never share a cluster",
+                        k,
+                        clusters.len()
+                    );
+                }
+                clusters
+            }
+            _ => cluster_colors_with(&tally.counts, self.delta_e, self.color_space),
+        };
+        let names = if tally.variables.is_empty() {
+            self.names.clone()
+        } else {
+            let variables = tally.variables.iter().filter_map(|(hex, vars)| Some((hex.clone(), vars.first()?.clone())));
+            self.names.clone().with_variables(variables)
+        };
+        for warning in naming::assign_names(&mut clusters, &self.name_template, &self.prefix, &tally.counts, &names) {
+            warn!("{}", warning);
+        }
+        clusters
+    }
+
+    /// Generate the CSS variables file and other outputs from the clusters
+    fn write_outputs(&self, tally: &ScanTally, clusters: &[Cluster]) -> Result<()> {
+        let counts = &tally.counts;
+        let delta_e = self.delta_e;
+        let canonical_map = canonical_map(clusters, &tally.sources);
+        if let Some(html_path) = &self.html {
+            let report = output::render_html_report(clusters, &canonical_map, counts);
+            write_output(html_path, &report, "HTML report")?;
+        }
+        if let Some(tokens_path) = &self.design_tokens {
+            let tokens = output::DesignTokensReport::new(clusters, &canonical_map);
+            write_output(tokens_path, &self.json_style.to_string(&tokens)?, "design tokens")?;
+        }
+        if let Some(tailwind_path) = &self.tailwind {
+            let config = output::TailwindRenderer::new(clusters.to_vec()).render();
+            write_output(tailwind_path, &config, "Tailwind config")?;
+        }
+        if let Some((postcss_path, esm)) = &self.postcss {
+            let config = output::PostcssRenderer::new(clusters.to_vec(), *esm).render();
+            write_output(postcss_path, &config, "PostCSS config")?;
+        }
+        if let Some(css_path) = &self.css_vars {
+            // Output the mapping of canonical hex -> all merged hexes
+            let map_file = &self.map_file;
+            let file = std::fs::File::create(map_file)
+                .with_context(|| format!("Failed to create mapping file {}", map_file))?;
+            serde_json::to_writer_pretty(file, &canonical_map)
+                .with_context(|| format!("Failed to write mapping file {}", map_file))?;
+            info!("Wrote canonical color mapping to {}", map_file);
+            // CLI output about optimization
+            let unique_hexes = counts.len();
+            let canonical_count = clusters.len();
+            if self.cluster_algo == ClusterAlgo::Kmeans {
+                info!(
+                    "Optimization: Reduced {unique_hexes} unique hex codes to {canonical_count} canonical CSS variables using k-means clustering (WCSS = {wcss:.2}).\nSee colours_map.json for mappings.",
+                    unique_hexes = unique_hexes,
+                    canonical_count = canonical_count,
+                    wcss = wcss(clusters)
+                );
+            } else if delta_e > 0.0 {
+                info!(
+                    "Optimization: Reduced {unique_hexes} unique hex codes to {canonical_count} canonical CSS variables using perceptual color clustering (Delta E < {delta_e}).\nSee colours_map.json for mappings.",
+                    unique_hexes = unique_hexes,
+                    canonical_count = canonical_count,
+                    delta_e = delta_e
+                );
+            } else {
+                info!(
+                    "Clustering disabled (Delta E = 0): wrote {canonical_count} CSS variables, one per unique hex code.\nSee colours_map.json for mappings.",
+                    canonical_count = canonical_count
+                );
+            }
+            let sorted = self.css_vars_sort.map(|order| {
+                let mut sorted = clusters.to_vec();
+                order.sort(&mut sorted, counts);
+                sorted
+            });
+            let clusters = sorted.as_deref().unwrap_or(clusters);
+            // Render both before writing either, so a failure can't leave the
+            // declarations out of step with the variables
+            let vars = if self.css_vars_comment {
+                let opts = CssVarsOptions { merged_counts: Some(counts.clone()), ..self.css_opts.clone() };
+                output::generate_css_vars_with(clusters, self.format, &opts)
+            } else {
+                output::generate_css_vars_with(clusters, self.format, &self.css_opts)
+            };
+            let declarations = self.ts_declaration_path(css_path).map(|path| (path, output::render_ts_declaration(clusters)));
+            write_output(css_path, &vars, "CSS variables")?;
+            if let Some((path, declarations)) = declarations {
+                write_output(&path, &declarations, "TypeScript declarations")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the SARIF log, with fixes from the mapping of a previous (or this) `--css-vars` run
+    fn write_sarif(&self, tally: &ScanTally, path: &str) -> Result<()> {
+        let css_path = self.css_vars.clone().unwrap_or_else(|| self.output.path(DEFAULT_CSS_VARS_FILE));
+        let hex_to_var = replace::load_hex_to_var(Path::new(&self.map_file), Path::new(&css_path)).unwrap_or_default();
+        let log = SarifLog::new(tally, &hex_to_var);
+        write_output(path, &self.json_style.to_string(&log)?, "SARIF log")
+    }
+
+    /// Canonical color pairs to list under "near_duplicates" for --min-delta-e
+    fn near_duplicates(&self, clusters: &[Cluster]) -> Vec<NearDuplicate> {
+        match self.min_delta_e {
+            Some(below) => near_duplicates(clusters, below, self.delta_e),
+            None => Vec::new(),
+        }
+    }
+
+    /// Write the JSON report to --out, or to stdout when `stdout_fallback` is set
+    fn write_report(&self, tally: &ScanTally, clusters: &[Cluster], stdout_fallback: bool) -> Result<()> {
+        if self.streaming {
+            return self.stream_report(tally, clusters, stdout_fallback);
+        }
+        let json = match self.group_by_family {
+            Some(chroma) => self.json_style.to_string(&FamilyReport::new(clusters, &tally.counts, chroma))?,
+            None => {
+                let mut report = ColorReport::from_tally(tally);
+                if self.wcag {
+                    report.add_wcag(clusters);
+                }
+                if let Some(order) = self.sort {
+                    report.sort(order, clusters);
+                }
+                let near_duplicates = self.near_duplicates(clusters);
+                if tally.files.is_empty() && near_duplicates.is_empty() && tally.decode_errors.is_empty() {
+                    self.json_style.to_string(&report)?
+                } else {
+                    self.json_style.to_string(&SectionedReport {
+                        report: &report,
+                        files: tally.files_by_count(),
+                        near_duplicates: &near_duplicates,
+                        decode_errors: &tally.decode_errors,
+                    })?
+                }
+            }
+        };
+        match &self.out {
+            Some(out_path) => {
+                std::fs::write(out_path, json).with_context(|| format!("Failed to write output file {}", out_path))?;
+            }
+            None if stdout_fallback => {
+                println!("{}", json);
+            }
+            None => {}
+        }
+        Ok(())
+    }
+
+    /// Like `write_report`, serializing straight into the output file or stdout
+    fn stream_report(&self, tally: &ScanTally, clusters: &[Cluster], stdout_fallback: bool) -> Result<()> {
+        let near_duplicates = self.near_duplicates(clusters);
+        let write = |writer: &mut dyn Write| -> Result<()> {
+            report::write_streaming(tally, self.sort, clusters, self.wcag, &near_duplicates, self.json_style, &mut *writer)?;
+            writeln!(writer)?;
+            writer.flush()?;
+            Ok(())
+        };
+        match &self.out {
+            Some(out_path) => {
+                let file = File::create(out_path).with_context(|| format!("Failed to create output file {}", out_path))?;
+                write(&mut BufWriter::new(file)).with_context(|| format!("Failed to write output file {}", out_path))
+            }
+            None if stdout_fallback => write(&mut BufWriter::new(std::io::stdout().lock())),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Run the `scan` command
+pub fn run(args: &ScanArgs, output: &OutputArgs) -> Result<()> {
+    let settings = args.resolve(output)?;
+    if settings.dry_run_paths {
+        return settings.print_paths();
+    }
+    let (file_count, mut tally, extensions) = settings.scan(settings.progress)?;
+    let existing = settings.apply_existing_vars(&mut tally);
+    let ignored = settings.apply_ignored_colors(&mut tally);
+    let total = tally.total();
+    let unique = tally.counts.len();
+    let (below, above) = settings.apply_thresholds(&mut tally);
+    let mut clusters = settings.clusters_for(&tally);
+    let omitted = settings.apply_top(&mut tally, &mut clusters);
+
+    info!("\n==== HEXVAR SUMMARY ====");
+    if unique == 0 {
+        info!("No hex codes found in {} files.", file_count);
+    } else {
+        info!("Files scanned:      {}", file_count);
+        info!("By extension:       {}", extensions.render());
+        info!("Unique hex codes:   {}", unique);
+        info!("Total occurrences:  {}", total);
+        if let Some((path, _)) = settings.existing_vars.as_ref().filter(|_| existing > 0) {
+            info!("{} colors already defined in {} excluded", existing, path);
+        }
+        if ignored > 0 {
+            info!("{} known colors ignored", ignored);
+        }
+        if below > 0 {
+            info!("{} colors below threshold suppressed", below);
+        }
+        if above > 0 {
+            info!("{} colors above threshold suppressed", above);
+        }
+        if omitted > 0 {
+            info!("{} less-used canonical colors omitted by --top", omitted);
+        }
+    }
+    info!("=======================\n");
+    if !tally.decode_errors.is_empty() {
+        let files: Vec<&str> = tally.decode_errors.iter().map(String::as_str).collect();
+        let encoding = settings.scan_opts.encoding;
+        warn!("{} files could not be decoded with --encoding {}: {}", files.len(), encoding, files.join(", "));
+    }
+
+    if settings.stats {
+        if let Some(stats) = PaletteStats::new(&tally.counts, &clusters) {
+            info!("{}\n", stats.render_text());
+        }
+    }
+
+    if settings.scan_opts.per_file && !tally.files.is_empty() {
+        let files: Vec<String> = tally
+            .files_by_count()
+            .into_iter()
+            .take(PER_FILE_LIMIT)
+            .map(|(file, n)| format!("{} ({})", file, n))
+            .collect();
+        info!("Top {} files by unique hex count: {}\n", files.len(), files.join(", "));
+    }
+
+    if settings.report_missing_names {
+        let mut missing: Vec<&Cluster> = clusters.iter().filter(|c| naming::is_hex_derived(&c.name)).collect();
+        missing.sort_by(|a, b| a.name.cmp(&b.name));
+        if !missing.is_empty() {
+            info!("==== COLORS WITHOUT NAMES ====");
+            for cluster in &missing {
+                info!("{} {}", cluster.hex, cluster.name);
+            }
+            info!("Add them to a --color-names file to give them readable names");
+            info!("==============================\n");
+        } else if !clusters.is_empty() {
+            info!("Every canonical color has a named variable\n");
+        }
+    }
+
+    if settings.swatches && !clusters.is_empty() {
+        settings.print_swatches(&tally, &clusters);
+        println!();
+    }
+    settings.write_all(&tally, &clusters, true)
+}
@@ -0,0 +1,159 @@
+use crate::scan::{OutputArgs, ProgressMode, ScanArgs, ScanSettings};
+use anyhow::{Context, Result};
+use hexvar_core::paths::IgnoreMatcher;
+use hexvar_core::scanner::ScanTally;
+use notify::{Event, RecursiveMode, Watcher};
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{error, info};
+
+/// Events arriving within this window of each other trigger a single rescan
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Directory to watch for a glob pattern: everything before its first wildcard component
+fn watch_root(pattern: &str) -> PathBuf {
+    let mut root = PathBuf::new();
+    for comp in Path::new(pattern).components() {
+        if comp.as_os_str().to_string_lossy().contains(['*', '?', '[', '{']) {
+            break;
+        }
+        root.push(comp);
+    }
+    // A pattern without wildcards names a single file; watch its directory
+    if root.as_os_str() == pattern {
+        root.pop();
+    }
+    if root.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        root
+    }
+}
+
+/// Path relative to `cwd` with any leading `./` removed, for matching against patterns
+fn relative(path: &Path, cwd: &Path) -> PathBuf {
+    let path = path.strip_prefix(cwd).unwrap_or(path);
+    path.components().filter(|c| *c != Component::CurDir).collect()
+}
+
+/// Current UTC wall-clock time as `HH:MM:SS`
+fn timestamp() -> String {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0) % 86_400;
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// Describe how the set of unique colors changed between two scans
+fn change_summary(previous: &ScanTally, current: &ScanTally) -> String {
+    let added = current.counts.keys().filter(|hex| !previous.counts.contains_key(*hex)).count();
+    let removed = previous.counts.keys().filter(|hex| !current.counts.contains_key(*hex)).count();
+    let total = current.total();
+    format!(
+        "{} unique colors (+{} / -{}), {} occurrences",
+        current.counts.len(),
+        added,
+        removed,
+        total
+    )
+}
+
+struct EventFilter {
+    cwd: PathBuf,
+    patterns: Vec<glob::Pattern>,
+    ignore: IgnoreMatcher,
+    outputs: Vec<PathBuf>,
+}
+
+impl EventFilter {
+    fn new(settings: &ScanSettings, cwd: PathBuf) -> Result<Self> {
+        let patterns = settings
+            .patterns
+            .iter()
+            .map(|p| {
+                let p = relative(Path::new(p), &cwd);
+                glob::Pattern::new(&p.to_string_lossy()).with_context(|| format!("Invalid glob pattern {}", p.display()))
+            })
+            .collect::<Result<_>>()?;
+        let outputs = settings.output_files().iter().map(|f| relative(Path::new(f), &cwd)).collect();
+        Ok(EventFilter { patterns, ignore: IgnoreMatcher::new(&settings.filter)?, outputs, cwd })
+    }
+
+    /// Whether a change to `path` should trigger a rescan
+    fn is_relevant(&mut self, path: &Path) -> bool {
+        let rel = relative(path, &self.cwd);
+        // Never react to files hexvar itself writes, or every scan would trigger another
+        if self.outputs.contains(&rel) {
+            return false;
+        }
+        if self.ignore.is_ignored(&rel) {
+            return false;
+        }
+        self.patterns.iter().any(|p| p.matches_path(&rel))
+    }
+}
+
+/// Run the `watch` command: scan once, then rescan whenever a matched file changes
+pub fn run(args: &ScanArgs, output: &OutputArgs) -> Result<()> {
+    let settings = args.resolve(output)?;
+    if settings.dry_run_paths {
+        return settings.print_paths();
+    }
+    let cwd = std::env::current_dir().context("Could not determine current directory")?;
+    let mut filter = EventFilter::new(&settings, cwd)?;
+
+    let (tx, rx) = mpsc::channel::<notify::Result<Event>>();
+    let mut watcher = notify::recommended_watcher(tx).context("Failed to start file watcher")?;
+    let mut roots: Vec<PathBuf> = settings.patterns.iter().map(|p| watch_root(p)).collect();
+    roots.sort();
+    roots.dedup();
+    for root in &roots {
+        watcher
+            .watch(root, RecursiveMode::Recursive)
+            .with_context(|| format!("Failed to watch {}", root.display()))?;
+    }
+
+    let rescan = |previous: &ScanTally| -> Result<ScanTally> {
+        let start = Instant::now();
+        // A progress bar would garble the change log, but JSON progress is still useful to tools
+        let progress = if settings.progress == ProgressMode::Json { ProgressMode::Json } else { ProgressMode::None };
+        let (file_count, mut tally, _) = settings.scan(progress)?;
+        settings.apply_existing_vars(&mut tally);
+        settings.apply_ignored_colors(&mut tally);
+        settings.apply_thresholds(&mut tally);
+        let mut clusters = settings.clusters_for(&tally);
+        settings.apply_top(&mut tally, &mut clusters);
+        settings.write_all(&tally, &clusters, false)?;
+        info!(
+            "[{}] Scanned {} files in {:.1?}: {}",
+            timestamp(),
+            file_count,
+            start.elapsed(),
+            change_summary(previous, &tally)
+        );
+        Ok(tally)
+    };
+
+    let mut tally = rescan(&ScanTally::default())?;
+    info!("Watching {} for changes (Ctrl-C to stop)", settings.patterns.join(", "));
+
+    let mut is_relevant = |res: notify::Result<Event>| match res {
+        Ok(event) => !event.kind.is_access() && event.paths.iter().any(|p| filter.is_relevant(p)),
+        Err(e) => {
+            error!("File watcher failed: {}", e);
+            false
+        }
+    };
+    while let Ok(res) = rx.recv() {
+        if !is_relevant(res) {
+            continue;
+        }
+        // Swallow the burst of events an editor save produces before rescanning
+        while rx.recv_timeout(DEBOUNCE).is_ok() {}
+        // Keep watching after a failed rescan; the next save may fix it
+        match rescan(&tally) {
+            Ok(next) => tally = next,
+            Err(e) => error!("[{}] {:#}", timestamp(), e),
+        }
+    }
+    Ok(())
+}